@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use windows::{
+    core::{Interface, Result},
+    Win32::Foundation::S_OK,
+    Win32::Media::Audio::Endpoints::IAudioMeterInformation,
+    Win32::Media::Audio::{
+        eRender, AudioSessionStateActive, IAudioSessionControl2, IAudioSessionManager2, IMMDeviceEnumerator,
+        MMDeviceEnumerator, DEVICE_STATE_ACTIVE,
+    },
+    Win32::System::Com::{CLSCTX_ALL, COINIT_APARTMENTTHREADED},
+};
+
+use crate::com;
+use crate::process::process_name;
+
+// How long an active session still counts after it was last audible, so a
+// pause in a call or between tracks doesn't end the exemption
+const QUIET_HOLD: Duration = Duration::from_secs(10);
+
+// When each process's active session was last audible
+static LAST_HEARD: Mutex<Vec<(u32, Instant)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    // Device enumerator, created once per thread asking
+    static ENUMERATOR: RefCell<Option<IMMDeviceEnumerator>> = const { RefCell::new(None) };
+}
+
+// Function to list the processes playing audio right now on any output
+// device, by executable name. A session only counts while it is active and
+// was audible in the last QUIET_HOLD, not merely open, as call and browser
+// apps keep theirs open. Names
+// rather than ids, since browsers play audio from a helper process with the
+// same name as the one owning the windows.
+pub fn playing_processes() -> Vec<String> {
+    let mut names: Vec<String> = audible_process_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(process_name)
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+fn audible_process_ids() -> Result<Vec<u32>> {
+    let mut ids = Vec::new();
+    let mut last_heard = LAST_HEARD.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    unsafe {
+        let enumerator = com::thread_instance(&ENUMERATOR, &MMDeviceEnumerator, COINIT_APARTMENTTHREADED)?;
+        let devices = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+        for i in 0..devices.GetCount()? {
+            let Ok(manager) = devices.Item(i).and_then(|device| device.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None)) else {
+                continue;
+            };
+            let sessions = manager.GetSessionEnumerator()?;
+            for j in 0..sessions.GetCount()? {
+                let Ok(session) = sessions.GetSession(j) else { continue };
+                if session.GetState().ok() != Some(AudioSessionStateActive) {
+                    continue;
+                }
+                let Ok(session) = session.cast::<IAudioSessionControl2>() else { continue };
+                // Windows' own sounds belong to no app
+                if session.IsSystemSoundsSession() == S_OK {
+                    continue;
+                }
+                let Ok(id) = session.GetProcessId() else { continue };
+                if id == 0 {
+                    continue;
+                }
+                if session.cast::<IAudioMeterInformation>().and_then(|meter| meter.GetPeakValue()).is_ok_and(|peak| peak > 0.0) {
+                    last_heard.retain(|(heard_id, _)| *heard_id != id);
+                    last_heard.push((id, now));
+                }
+                if last_heard.iter().any(|(heard_id, heard)| *heard_id == id && now.duration_since(*heard) < QUIET_HOLD) {
+                    ids.push(id);
+                }
+            }
+        }
+    }
+    // Sessions that stopped or went quiet for too long start over
+    last_heard.retain(|(id, heard)| ids.contains(id) && now.duration_since(*heard) < QUIET_HOLD);
+    Ok(ids)
+}
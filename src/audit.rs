@@ -0,0 +1,421 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config::data_dir;
+use crate::secrets;
+
+// Hash the first entry chains from
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// One line of the audit log. Each entry's hash is an HMAC, keyed with a
+// secret only this user can decrypt, over its own fields and the previous
+// entry's hash, so editing or removing a line breaks the chain and a chain
+// can't be recomputed from scratch without the key.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEntry {
+    seq: u64,
+    // Seconds since the Unix epoch
+    time: u64,
+    event: String,
+    detail: String,
+    prev: String,
+    hash: String,
+}
+
+// Function to hex-encode the SHA-256 of some text
+pub fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Function to hex-encode the HMAC-SHA256 of some text (RFC 2104)
+fn hmac_hex(key: &[u8], text: &str) -> String {
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = Sha256::new().chain_update(block.map(|byte| byte ^ 0x36)).chain_update(text.as_bytes()).finalize();
+    let outer = Sha256::new().chain_update(block.map(|byte| byte ^ 0x5c)).chain_update(inner).finalize();
+    outer.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl AuditEntry {
+    // Keyed hash of everything but the hash field itself
+    fn compute_hash(&self, key: &str) -> String {
+        let body = serde_json::json!({
+            "seq": self.seq,
+            "time": self.time,
+            "event": self.event,
+            "detail": self.detail,
+            "prev": self.prev,
+        });
+        hmac_hex(key.as_bytes(), &body.to_string())
+    }
+}
+
+// The last entry written, kept next to the log so entries cut off its end
+// are noticed too. The mac covers the other two fields.
+#[derive(Serialize, Deserialize)]
+struct Head {
+    seq: u64,
+    hash: String,
+    mac: String,
+}
+
+impl Head {
+    fn new(seq: u64, hash: String, key: &str) -> Self {
+        let mac = hmac_hex(key.as_bytes(), &format!("head {} {}", seq, hash));
+        Self { seq, hash, mac }
+    }
+}
+
+// Append-only log writer, positioned after the last entry in the file
+struct AuditLog {
+    file: File,
+    seq: u64,
+    last_hash: String,
+    key: String,
+    head_path: PathBuf,
+}
+
+// The open log, shared so any part of the monitor can record actions
+static AUDIT: Mutex<Option<AuditLog>> = Mutex::new(None);
+
+pub fn audit_log_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("audit.jsonl"))
+}
+
+// The chain's key, DPAPI-encrypted for the current user
+fn key_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("audit.key"))
+}
+
+fn head_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("audit.head"))
+}
+
+// Function to read the chain's key; None when there is none yet
+fn read_key() -> std::result::Result<Option<String>, Box<dyn std::error::Error>> {
+    let path = key_path().ok_or("LOCALAPPDATA is not set")?;
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(secrets::reveal(text.trim()).map_err(|e| format!("audit key: {}", e))?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Function to read the head record, checking its mac; None when there is none
+fn read_head(key: &str) -> std::result::Result<Option<Head>, Box<dyn std::error::Error>> {
+    let path = head_path().ok_or("LOCALAPPDATA is not set")?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let head: Head = serde_json::from_str(&text).map_err(|e| format!("audit head is unreadable: {}", e))?;
+    if Head::new(head.seq, head.hash.clone(), key).mac != head.mac {
+        return Err("audit head was modified".into());
+    }
+    Ok(Some(head))
+}
+
+// Function to check whether an entry is the one right after the head (or the
+// first entry when there is no head yet) and is genuine
+fn one_behind(head: Option<&Head>, entry: &AuditEntry, key: &str) -> bool {
+    let (seq, hash) = head.map_or((0, GENESIS_HASH), |head| (head.seq + 1, head.hash.as_str()));
+    entry.seq == seq && entry.prev == hash && entry.compute_hash(key) == entry.hash
+}
+
+// Function to replace the head record, writing a temporary file first so a
+// crash never leaves a half-written head behind
+fn write_head(path: &Path, head: &Head) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let temporary = path.with_extension("head.tmp");
+    fs::write(&temporary, serde_json::to_string(head)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+// Function to open the audit log for appending, continuing the existing chain
+pub fn open() -> std::result::Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = audit_log_path().ok_or("LOCALAPPDATA is not set")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    let mut seq = 0;
+    let mut last_hash = GENESIS_HASH.to_string();
+    let last_line = File::open(&path)
+        .ok()
+        .and_then(|file| BufReader::new(file).lines().map_while(Result::ok).filter(|l| !l.trim().is_empty()).last());
+    let key = match read_key()? {
+        Some(key) => key,
+        None if last_line.is_some() => {
+            return Err(format!("{} has no key to continue its chain with; move it aside to start a new log", path.display()).into());
+        }
+        None => {
+            let key = secrets::random_hex()?;
+            fs::write(key_path().ok_or("LOCALAPPDATA is not set")?, secrets::protect(&key)?)?;
+            key
+        }
+    };
+    
+    // Only continue a chain that still ends where the last run left it;
+    // appending would otherwise hide entries cut off its end
+    let head = read_head(&key)?;
+    let head_path = head_path().ok_or("LOCALAPPDATA is not set")?;
+    if let Some(line) = &last_line {
+        let entry: AuditEntry = serde_json::from_str(line)
+            .map_err(|e| format!("last audit entry is unreadable, not appending to a broken chain: {}", e))?;
+        match &head {
+            Some(head) if head.seq == entry.seq && head.hash == entry.hash => {}
+            // The last run stopped between writing an entry and its head
+            head if one_behind(head.as_ref(), &entry, &key) => {
+                warn!("The audit head was one entry behind the log, catching it up");
+                write_head(&head_path, &Head::new(entry.seq, entry.hash.clone(), &key))?;
+            }
+            _ => return Err("the audit log no longer ends at its last recorded entry, not appending (see `audit verify`)".into()),
+        }
+        seq = entry.seq + 1;
+        last_hash = entry.hash;
+    } else if head.is_some() {
+        return Err("the audit log was emptied or removed, not starting over (see `audit verify`)".into());
+    }
+    
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    *AUDIT.lock().unwrap_or_else(|e| e.into_inner()) = Some(AuditLog { file, seq, last_hash, key, head_path });
+    Ok(path)
+}
+
+// Function to record an action; does nothing when the audit log isn't open
+pub fn record(event: &str, detail: &str) {
+    let mut audit = AUDIT.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(log) = audit.as_mut() else { return };
+    
+    let mut entry = AuditEntry {
+        seq: log.seq,
+        time: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+        event: event.to_string(),
+        detail: detail.to_string(),
+        prev: log.last_hash.clone(),
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash(&log.key);
+    
+    let written = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| writeln!(log.file, "{}", line).and_then(|()| log.file.flush()).map_err(|e| e.to_string()));
+    match written {
+        Ok(()) => {
+            if let Err(e) = write_head(&log.head_path, &Head::new(entry.seq, entry.hash.clone(), &log.key)) {
+                warn!("Could not update the audit head: {}", e);
+            }
+            log.seq += 1;
+            log.last_hash = entry.hash;
+        }
+        Err(e) => warn!("Could not write to the audit log: {}", e),
+    }
+}
+
+// Function to check the whole chain, printing where it breaks. Returns
+// whether the log is intact.
+pub fn verify() -> std::result::Result<bool, Box<dyn std::error::Error>> {
+    let path = audit_log_path().ok_or("LOCALAPPDATA is not set")?;
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("✗ No audit log at {}", path.display());
+            return Ok(false);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let Some(key) = read_key()? else {
+        println!("✗ No key for the audit log at {}", path.display());
+        return Ok(false);
+    };
+    let head = match read_head(&key) {
+        Ok(head) => head,
+        Err(e) => {
+            println!("✗ {}", e);
+            return Ok(false);
+        }
+    };
+    
+    let lines = BufReader::new(file).lines().collect::<std::io::Result<Vec<String>>>()?;
+    match check_chain(&lines, &key, head.as_ref()) {
+        Ok(count) => {
+            println!("✓ {} entries verified in {}", count, path.display());
+            Ok(true)
+        }
+        Err(e) => {
+            println!("✗ {}", e);
+            Ok(false)
+        }
+    }
+}
+
+// Function to walk the log's lines, returning how many entries chain
+// correctly up to the head, or where the chain breaks
+fn check_chain(lines: &[String], key: &str, head: Option<&Head>) -> std::result::Result<u64, String> {
+    let mut expected_seq = 0;
+    let mut prev = GENESIS_HASH.to_string();
+    // What the last entry chained from
+    let mut before_last = GENESIS_HASH.to_string();
+    for (index, line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let entry: AuditEntry =
+            serde_json::from_str(line).map_err(|e| format!("Line {}: not a valid entry ({})", line_number, e))?;
+        if entry.seq != expected_seq {
+            return Err(format!(
+                "Line {}: expected entry {}, found {} (entries missing or reordered)",
+                line_number, expected_seq, entry.seq
+            ));
+        }
+        if entry.prev != prev {
+            return Err(format!("Line {}: does not chain from the previous entry", line_number));
+        }
+        if entry.compute_hash(key) != entry.hash {
+            return Err(format!("Line {}: contents were modified", line_number));
+        }
+        
+        expected_seq += 1;
+        before_last = std::mem::replace(&mut prev, entry.hash);
+    }
+    
+    // The chain itself can't show entries missing from its end. A head one
+    // entry behind is a run stopped between writing an entry and its head.
+    let intact_end = match head {
+        Some(head) => {
+            (expected_seq == head.seq + 1 && prev == head.hash) || (expected_seq == head.seq + 2 && before_last == head.hash)
+        }
+        None => expected_seq <= 1,
+    };
+    if !intact_end {
+        let last = head.map_or("none".to_string(), |head| head.seq.to_string());
+        return Err(format!("The log ends before its last recorded entry ({}): entries were removed from the end", last));
+    }
+    Ok(expected_seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // RFC 4231 test cases 1, 2, 6 and 7 (the others use data that isn't UTF-8)
+    #[test]
+    fn hmac_hex_matches_rfc_4231() {
+        assert_eq!(
+            hmac_hex(&[0x0b; 20], "Hi There"),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hmac_hex(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac_hex(&[0xaa; 131], "Test Using Larger Than Block-Size Key - Hash Key First"),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        assert_eq!(
+            hmac_hex(
+                &[0xaa; 131],
+                "This is a test using a larger than block-size key and a larger than block-size data. \
+                 The key needs to be hashed before being used by the HMAC algorithm."
+            ),
+            "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2"
+        );
+    }
+    
+    const KEY: &str = "test key";
+    
+    // A chain of entries as record writes them, with the head of the last
+    fn chain(count: u64) -> (Vec<String>, Head) {
+        let mut lines = Vec::new();
+        let mut prev = GENESIS_HASH.to_string();
+        for seq in 0..count {
+            let mut entry = AuditEntry {
+                seq,
+                time: 1_700_000_000 + seq,
+                event: "minimize".to_string(),
+                detail: format!("window {}", seq),
+                prev: prev.clone(),
+                hash: String::new(),
+            };
+            entry.hash = entry.compute_hash(KEY);
+            prev = entry.hash.clone();
+            lines.push(serde_json::to_string(&entry).unwrap());
+        }
+        (lines, Head::new(count - 1, prev, KEY))
+    }
+    
+    #[test]
+    fn an_untouched_chain_verifies() {
+        let (lines, head) = chain(3);
+        assert_eq!(check_chain(&lines, KEY, Some(&head)), Ok(3));
+    }
+    
+    #[test]
+    fn edited_removed_and_reordered_entries_break_the_chain() {
+        let (lines, head) = chain(3);
+        
+        let mut edited = lines.clone();
+        edited[1] = edited[1].replace("window 1", "window 9");
+        assert!(check_chain(&edited, KEY, Some(&head)).unwrap_err().contains("modified"));
+        
+        let mut removed = lines.clone();
+        removed.remove(1);
+        assert!(check_chain(&removed, KEY, Some(&head)).unwrap_err().contains("expected entry 1"));
+        
+        let mut swapped = lines.clone();
+        swapped.swap(0, 1);
+        assert!(check_chain(&swapped, KEY, Some(&head)).is_err());
+        
+        assert!(check_chain(&lines, "other key", Some(&head)).unwrap_err().contains("modified"));
+    }
+    
+    #[test]
+    fn entries_cut_off_the_end_are_noticed() {
+        let (lines, head) = chain(3);
+        assert!(check_chain(&lines[..1], KEY, Some(&head)).unwrap_err().contains("removed from the end"));
+        assert!(check_chain(&[], KEY, Some(&head)).is_err());
+    }
+    
+    #[test]
+    fn a_head_one_entry_behind_is_accepted() {
+        let (lines, _) = chain(3);
+        let (_, behind) = chain(2);
+        assert_eq!(check_chain(&lines, KEY, Some(&behind)), Ok(3));
+        let (_, two_behind) = chain(1);
+        assert!(check_chain(&lines, KEY, Some(&two_behind)).is_err());
+        
+        let (first, _) = chain(1);
+        assert_eq!(check_chain(&first, KEY, None), Ok(1));
+        assert!(check_chain(&lines, KEY, None).is_err());
+    }
+    
+    #[test]
+    fn one_behind_needs_a_genuine_next_entry() {
+        let (lines, head) = chain(3);
+        let (_, behind) = chain(2);
+        let last: AuditEntry = serde_json::from_str(&lines[2]).unwrap();
+        assert!(one_behind(Some(&behind), &last, KEY));
+        assert!(!one_behind(Some(&head), &last, KEY));
+        assert!(!one_behind(Some(&behind), &last, "other key"));
+        
+        let first: AuditEntry = serde_json::from_str(&lines[0]).unwrap();
+        assert!(one_behind(None, &first, KEY));
+        assert!(!one_behind(None, &last, KEY));
+    }
+}
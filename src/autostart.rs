@@ -0,0 +1,46 @@
+use windows::{
+    core::{w, HSTRING, PCWSTR},
+    Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS},
+    Win32::System::Registry::{RegDeleteKeyValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ},
+};
+
+use crate::task::quote;
+
+// Per-user key whose values Explorer runs at sign-in
+const RUN_KEY: PCWSTR = w!("Software\\Microsoft\\Windows\\CurrentVersion\\Run");
+const VALUE_NAME: PCWSTR = w!("fak-opacity");
+
+// Function to start monitoring when the current user signs in, by pointing a
+// Run entry at this executable with the given run flags. Unlike the scheduled
+// task this needs no administrator rights, but can't run elevated.
+pub fn enable(run_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let command = std::iter::once(quote(&exe.to_string_lossy()))
+        .chain(["run".to_string(), "--at-logon".to_string()])
+        .chain(run_args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    
+    // REG_SZ data is the UTF-16 string with its terminating null
+    let data = HSTRING::from(command.as_str());
+    let size = ((data.len() + 1) * std::mem::size_of::<u16>()) as u32;
+    let status = unsafe {
+        RegSetKeyValueW(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME, REG_SZ.0, Some(data.as_ptr() as *const _), size)
+    };
+    if status != ERROR_SUCCESS {
+        return Err(format!("could not write the Run entry: {}", status.to_hresult().message()).into());
+    }
+    println!("✓ fak-opacity will start when you sign in: {}", command);
+    Ok(())
+}
+
+// Function to remove the Run entry again
+pub fn disable() -> Result<(), Box<dyn std::error::Error>> {
+    let status = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, RUN_KEY, VALUE_NAME) };
+    match status {
+        ERROR_SUCCESS => println!("✓ fak-opacity will no longer start when you sign in"),
+        ERROR_FILE_NOT_FOUND => println!("ℹ Autostart was not enabled"),
+        _ => return Err(format!("could not remove the Run entry: {}", status.to_hresult().message()).into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use tracing::warn;
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::Graphics::Gdi::*,
+    Win32::System::Com::COINIT_APARTMENTTHREADED,
+    Win32::System::Console::{GetConsoleWindow, SetConsoleTitleW},
+    Win32::UI::Shell::{ITaskbarList3, TaskbarList},
+    Win32::UI::WindowsAndMessaging::{CreateIconIndirect, DestroyIcon, GetSystemMetrics, HICON, ICONINFO, SM_CXSMICON},
+};
+
+use crate::com;
+
+// Function to draw a red badge with the count on it, as an overlay icon
+fn count_icon(count: usize) -> Option<HICON> {
+    // Overlay icons are drawn at the small icon size, which follows the DPI
+    let size = unsafe { GetSystemMetrics(SM_CXSMICON) }.max(16);
+    let mut text: Vec<u16> = if count > 99 { "99+".to_string() } else { count.to_string() }
+        .encode_utf16()
+        .collect();
+    let mut rect = RECT { left: 0, top: 0, right: size, bottom: size };
+    
+    unsafe {
+        let screen = GetDC(None);
+        let dc = CreateCompatibleDC(Some(screen));
+        let color = CreateCompatibleBitmap(screen, size, size);
+        // Monochrome AND mask: black is opaque, white lets the button show through
+        let mask = CreateBitmap(size, size, 1, 1, None);
+        ReleaseDC(None, screen);
+        
+        // Mask: a black disc on white
+        let previous = SelectObject(dc, HGDIOBJ(mask.0));
+        FillRect(dc, &rect, HBRUSH(GetStockObject(WHITE_BRUSH).0));
+        SelectObject(dc, GetStockObject(BLACK_BRUSH));
+        SelectObject(dc, GetStockObject(NULL_PEN));
+        let _ = Ellipse(dc, 0, 0, size + 1, size + 1);
+        
+        // Color: a red disc with the count in white, black (unchanged) elsewhere
+        SelectObject(dc, HGDIOBJ(color.0));
+        FillRect(dc, &rect, HBRUSH(GetStockObject(BLACK_BRUSH).0));
+        let red = CreateSolidBrush(COLORREF(0x0000_30D0));
+        SelectObject(dc, HGDIOBJ(red.0));
+        let _ = Ellipse(dc, 0, 0, size + 1, size + 1);
+        SetBkMode(dc, TRANSPARENT);
+        SetTextColor(dc, COLORREF(0x00FF_FFFF));
+        DrawTextW(dc, &mut text, &mut rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE);
+        
+        SelectObject(dc, previous);
+        let _ = DeleteObject(HGDIOBJ(red.0));
+        let _ = DeleteDC(dc);
+        
+        let info = ICONINFO {
+            fIcon: true.into(),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&info).ok();
+        // The icon keeps its own copies of the bitmaps
+        let _ = DeleteObject(HGDIOBJ(mask.0));
+        let _ = DeleteObject(HGDIOBJ(color.0));
+        icon
+    }
+}
+
+// Shows how many windows are currently suppressed on the console's taskbar
+// button (as an overlay icon) and in its title
+pub struct TaskbarBadge {
+    taskbar: Option<ITaskbarList3>,
+    hwnd: HWND,
+    icon: Option<HICON>,
+    count: Option<usize>,
+}
+
+impl TaskbarBadge {
+    pub fn new() -> Self {
+        let hwnd = unsafe { GetConsoleWindow() };
+        let taskbar = com::create_instance::<ITaskbarList3>(&TaskbarList, COINIT_APARTMENTTHREADED)
+            .and_then(|taskbar| unsafe { taskbar.HrInit() }.map(|()| taskbar));
+        
+        // Terminals without a classic console window only get the title
+        let taskbar = match taskbar {
+            Ok(taskbar) if !hwnd.is_invalid() => Some(taskbar),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Taskbar overlay unavailable, showing the count in the title only: {}", e);
+                None
+            }
+        };
+        
+        Self { taskbar, hwnd, icon: None, count: None }
+    }
+    
+    // Update the badge; does nothing when the count hasn't changed
+    pub fn set_count(&mut self, count: usize) {
+        if self.count == Some(count) {
+            return;
+        }
+        self.count = Some(count);
+        
+        let title = if count == 0 {
+            "fak-opacity".to_string()
+        } else {
+            format!("fak-opacity · {} suppressed", count)
+        };
+        unsafe {
+            let _ = SetConsoleTitleW(&HSTRING::from(title.as_str()));
+        }
+        
+        let Some(taskbar) = &self.taskbar else { return };
+        let icon = if count == 0 { None } else { count_icon(count) };
+        unsafe {
+            let _ = taskbar.SetOverlayIcon(self.hwnd, icon.unwrap_or_default(), &HSTRING::from(title.as_str()));
+            if let Some(previous) = std::mem::replace(&mut self.icon, icon) {
+                let _ = DestroyIcon(previous);
+            }
+        }
+    }
+}
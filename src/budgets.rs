@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+use crate::config::{data_dir, FocusBudget};
+use crate::WindowInfo;
+
+// How often the focused times are written out, so a crash loses little
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+fn times_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("focus-time.json"))
+}
+
+// Function to get today's local date as YYYY-MM-DD
+fn today() -> String {
+    let now = unsafe { GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", now.wYear, now.wMonth, now.wDay)
+}
+
+// Something that changed what the budgets allow
+pub enum BudgetChange {
+    // This budget was just used up
+    Exhausted(FocusBudget),
+    // Midnight passed; every budget starts over
+    NewDay,
+}
+
+// How long each budgeted app had focus today, kept across runs in
+// focus-time.json so restarting doesn't hand out a fresh budget
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FocusBudgets {
+    // Local date the times are for
+    day: String,
+    // Budget's process rule -> time focused today
+    focused: BTreeMap<String, Duration>,
+    // When the last tick was and which budget's app had focus then
+    #[serde(skip)]
+    last_tick: Option<(Instant, Option<String>)>,
+    #[serde(skip)]
+    last_saved: Option<Instant>,
+    // Whether the times are written to focus-time.json at all
+    #[serde(skip)]
+    persist: bool,
+}
+
+impl FocusBudgets {
+    // Load today's times, starting over when they are from another day
+    pub fn load() -> Self {
+        let stored: Self = times_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        if stored.day == today() {
+            Self { persist: true, ..stored }
+        } else {
+            Self { day: today(), persist: true, ..Default::default() }
+        }
+    }
+    
+    // Start today from nothing, keeping the times in memory only
+    pub fn in_memory() -> Self {
+        Self { day: today(), ..Default::default() }
+    }
+    
+    fn save(&mut self) {
+        self.last_saved = Some(Instant::now());
+        let saved = times_path().ok_or_else(|| "LOCALAPPDATA is not set".into()).and_then(|path| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, serde_json::to_string_pretty(self)?)?;
+            Ok::<(), Box<dyn std::error::Error>>(())
+        });
+        if let Err(e) = saved {
+            warn!("Could not save the focused times: {}", e);
+        }
+    }
+    
+    // Count the time since the last tick towards the app that had focus then.
+    // `focused` is the foreground window now, None while nothing should count
+    // (monitoring paused). Returns the budgets used up by this tick.
+    pub fn tick(&mut self, focused: Option<&WindowInfo>, budgets: &[FocusBudget]) -> Vec<BudgetChange> {
+        let mut changes = Vec::new();
+        let now = Instant::now();
+        if self.day != today() {
+            self.day = today();
+            self.focused.clear();
+            changes.push(BudgetChange::NewDay);
+        } else if let Some((at, Some(process))) = self.last_tick.take()
+            && let Some(budget) = budgets.iter().find(|budget| budget.process == process)
+        {
+            let spent = self.focused.entry(process).or_default();
+            let before = *spent;
+            *spent += now - at;
+            if before < budget.daily && *spent >= budget.daily {
+                changes.push(BudgetChange::Exhausted(budget.clone()));
+            }
+        }
+        
+        let budget = focused.and_then(|window| budgets.iter().find(|budget| window.matches_process(&budget.process)));
+        self.last_tick = Some((now, budget.map(|budget| budget.process.clone())));
+        if self.persist && (!changes.is_empty() || self.last_saved.is_none_or(|at| at.elapsed() >= SAVE_INTERVAL)) {
+            self.save();
+        }
+        changes
+    }
+    
+    // The budget a window's app has used up today, if any
+    pub fn exhausted<'a>(&self, window: &WindowInfo, budgets: &'a [FocusBudget]) -> Option<&'a FocusBudget> {
+        budgets.iter().find(|budget| {
+            window.matches_process(&budget.process) && self.focused.get(&budget.process).is_some_and(|spent| *spent >= budget.daily)
+        })
+    }
+}
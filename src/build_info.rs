@@ -0,0 +1,17 @@
+// Build information embedded by build.rs
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_HASH: &str = env!("FAK_GIT_HASH");
+pub const BUILD_DATE: &str = env!("FAK_BUILD_DATE");
+pub const FEATURES: &str = env!("FAK_FEATURES");
+
+// One-line version string, e.g. "fak-opacity 0.1.0 (1a2b3c4, built 2024-05-01, features: none)"
+pub fn version_string() -> String {
+    format!(
+        "{} {} ({}, built {}, features: {})",
+        env!("CARGO_PKG_NAME"),
+        VERSION,
+        GIT_HASH,
+        BUILD_DATE,
+        FEATURES
+    )
+}
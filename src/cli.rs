@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand};
+
+use fak_opacity::config::{MonitorConfig, RestoreBehavior};
+
+// Command line: `fak-opacity [run] [--target ..] [--ignore ..] [--interval ..]`
+// or one of the maintenance subcommands. Without a subcommand it monitors.
+#[derive(Debug, Parser)]
+#[command(
+    name = "fak-opacity",
+    about = "Minimizes other windows while a target window is focused",
+    disable_version_flag = true
+)]
+pub struct Cli {
+    #[arg(short = 'V', long, help = "Print version and build information")]
+    pub version: bool,
+    
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Monitor windows (the default)")]
+    Run(RunArgs),
+    #[cfg(feature = "update")]
+    #[command(about = "Check GitHub releases for a newer version")]
+    Update {
+        #[command(subcommand)]
+        command: UpdateCommand,
+    },
+    #[command(about = "Work with the audit log")]
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    #[command(about = "Manage the config file")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    #[command(about = "Summarize what shadow mode recorded enforcement would have done")]
+    ShadowReport,
+    #[command(about = "List open windows by process with when each last had focus")]
+    Clutter {
+        #[arg(long, help = "Offer to minimize or close windows unfocused for days")]
+        interactive: bool,
+    },
+    #[command(about = "Bring a target forward, minimize everything else once, and exit")]
+    Clean {
+        #[arg(long, value_name = "KEYWORD", help = "Target keyword to clean for (defaults to the first configured target)")]
+        target: Option<String>,
+    },
+    #[command(about = "Restore the windows minimized during the last run")]
+    RestoreLast,
+    #[command(about = "Stream a process's window lifecycle events")]
+    Watch {
+        #[arg(long, value_name = "EXE", help = "Executable to watch, e.g. slack.exe")]
+        process: String,
+    },
+    #[command(about = "Show what the monitor detects about this environment")]
+    Doctor,
+    #[command(about = "Print the version and what a running monitor reports about itself")]
+    Status {
+        #[arg(long, value_name = "NAME", help = "Control pipe (\\\\.\\pipe\\<NAME>) of a monitor running as a helper; its token is taken from FAK_OPACITY_READ_TOKEN or FAK_OPACITY_CONTROL_TOKEN")]
+        pipe: Option<String>,
+    },
+    #[command(about = "Time every rule against the current windows")]
+    ProfileRules,
+    #[command(about = "Check matching and minimizing against dummy windows")]
+    Selftest,
+    #[command(about = "Try rules against titles and the open windows interactively, touching nothing")]
+    Repl,
+    #[command(about = "Start monitoring at sign-in with a scheduled task")]
+    InstallTask {
+        #[arg(long, help = "Run with highest privileges so elevated windows are minimized too (asks for elevation)")]
+        highest: bool,
+        
+        #[arg(long, hide = true)]
+        user: Option<String>,
+        
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "RUN_FLAGS", help = "Flags for `run`, e.g. --target Trae --restore restore")]
+        run_args: Vec<String>,
+    },
+    #[command(about = "Remove the sign-in scheduled task")]
+    UninstallTask,
+    #[command(about = "Start monitoring at sign-in from the Run registry key (no administrator rights needed)")]
+    Autostart {
+        #[command(subcommand)]
+        command: AutostartCommand,
+    },
+    #[cfg(feature = "service")]
+    #[command(about = "Install, start or stop the Windows service")]
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    #[cfg(feature = "ipc")]
+    #[command(about = "Run as another app's helper process, controlled through a named pipe")]
+    Embedded {
+        #[arg(long, value_name = "NAME", help = "Name shown in notifications and the audit log")]
+        app_name: String,
+        
+        #[arg(long, value_name = "NAME", help = "Pipe to listen on as \\\\.\\pipe\\<NAME> (defaults to <app-name>-fak-opacity); clients first send the token the host put in FAK_OPACITY_CONTROL_TOKEN (or FAK_OPACITY_READ_TOKEN, for reading only)")]
+        pipe: Option<String>,
+        
+        #[command(flatten)]
+        run_args: RunArgs,
+    },
+}
+
+#[cfg(feature = "update")]
+#[derive(Debug, Subcommand)]
+pub enum UpdateCommand {
+    #[command(about = "Check once and report")]
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    #[command(about = "Check the hash chain of the audit log")]
+    Verify,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AutostartCommand {
+    #[command(about = "Start at sign-in with the given run flags")]
+    Enable {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "RUN_FLAGS", help = "Flags for `run`, e.g. --target Trae --restore restore")]
+        run_args: Vec<String>,
+    },
+    #[command(about = "Stop starting at sign-in")]
+    Disable,
+}
+
+#[cfg(feature = "service")]
+#[derive(Debug, Subcommand)]
+pub enum ServiceCommand {
+    #[command(about = "Register the service to start at boot (needs an elevated prompt)")]
+    Install,
+    #[command(about = "Stop and remove the service")]
+    Uninstall,
+    #[command(about = "Start the service now")]
+    Start,
+    #[command(about = "Stop the service, restoring what the monitor changed")]
+    Stop,
+    #[command(hide = true, about = "Run as the service (started by the service manager)")]
+    Run,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    #[command(about = "Encrypt plaintext secrets in config.toml with DPAPI (current user only)")]
+    EncryptSecrets,
+}
+
+// Overrides for the monitoring loop; anything left out comes from the
+// config file, then the built-in defaults
+#[derive(Debug, Default, Args)]
+pub struct RunArgs {
+    #[arg(long = "target", value_name = "KEYWORD", help = "Target window title keyword, or re:<regex> (repeatable)")]
+    pub targets: Vec<String>,
+    
+    #[arg(long = "ignore", value_name = "KEYWORD", help = "Title keyword (or re:<regex>) of windows never minimized (repeatable)")]
+    pub ignored: Vec<String>,
+    
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, help = "Foreground poll interval, e.g. 200ms")]
+    pub interval: Option<Duration>,
+    
+    #[arg(long, value_name = "BEHAVIOR", help = "What happens to minimized windows when focus leaves the targets: keep-minimized, restore, restore-if-was-visible or a delay such as 30s")]
+    pub restore: Option<RestoreBehavior>,
+    
+    #[arg(long, conflicts_with = "restore", help = "Focus mode: restore minimized windows as soon as focus leaves the targets")]
+    pub restore_on_focus_loss: bool,
+    
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, help = "How long a target must keep focus before other windows are suppressed, e.g. 2s")]
+    pub grace: Option<Duration>,
+    
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, help = "Only act once the foreground window has been stable this long, e.g. 300ms")]
+    pub settle: Option<Duration>,
+    
+    #[arg(long, help = "Only minimize windows on the same monitor as the target")]
+    pub same_monitor: bool,
+    
+    #[arg(
+        long,
+        value_name = "OPACITY",
+        num_args = 0..=1,
+        default_missing_value = "160",
+        help = "Minimize nothing; dim every window but the focused one to OPACITY (0-255, default 160)"
+    )]
+    pub dim_inactive: Option<u8>,
+    
+    #[arg(long, help = "Record what enforcement would do without touching any window")]
+    pub shadow: bool,
+    
+    #[arg(long, help = "Print which windows would be minimized and which rule decided each, touching nothing")]
+    pub dry_run: bool,
+    
+    #[arg(long, conflicts_with = "shadow", help = "Guest mode: rules from flags only, no files written, everything restored on exit")]
+    pub ephemeral: bool,
+    
+    #[arg(long, conflicts_with = "ephemeral", help = "Keep a tamper-evident log of every action (check it with `audit verify`)")]
+    pub audit: bool,
+    
+    // Added to the command line autostart and install-task register
+    #[arg(long, hide = true)]
+    pub at_logon: bool,
+}
+
+impl RunArgs {
+    // Apply the arguments that were given on top of the configuration
+    pub fn apply(&self, config: &mut MonitorConfig) {
+        if !self.targets.is_empty() {
+            config.target_keywords = self.targets.clone();
+        }
+        if !self.ignored.is_empty() {
+            config.ignored_keywords = self.ignored.clone();
+        }
+        if let Some(interval) = self.interval {
+            config.poll_interval = interval;
+        }
+        if let Some(behavior) = self.restore {
+            config.restore_behavior = behavior;
+        }
+        if self.restore_on_focus_loss {
+            config.restore_behavior = RestoreBehavior::Restore;
+        }
+        if let Some(delay) = self.grace {
+            config.grace_delay = delay;
+        }
+        if let Some(settle) = self.settle {
+            config.focus_settle = settle;
+        }
+        if self.same_monitor {
+            config.same_monitor_only = true;
+        }
+        if let Some(opacity) = self.dim_inactive {
+            config.dim_inactive_opacity = Some(opacity);
+        }
+        if self.shadow {
+            config.shadow_mode = true;
+        }
+        if self.dry_run {
+            config.dry_run = true;
+        }
+        if self.audit {
+            config.audit_log = true;
+        }
+        if self.ephemeral {
+            config.ephemeral();
+        }
+        if self.at_logon {
+            config.at_logon = true;
+        }
+    }
+}
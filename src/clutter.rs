@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use tracing::{error, info};
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{IsIconic, PostMessageW, WM_CLOSE};
+
+use crate::config::MonitorConfig;
+use crate::history::{now_secs, FocusHistory};
+use crate::process::ProcessNameCache;
+use crate::restore_list::WindowIdentity;
+use crate::{audit, get_all_windows_uncached, is_system_window, log_action, minimize_window, WindowInfo};
+
+// Function to describe how long ago a Unix timestamp was, e.g. "3 days ago"
+fn ago(then: u64, now: u64) -> String {
+    let secs = now.saturating_sub(then);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+// Function to ask what to do with a stale window; returns false to stop asking.
+// What is done goes to the log like the monitor's own actions, with `rule`
+// saying why the window was offered.
+fn offer_action(window: &WindowInfo, rule: &str, input: &mut impl BufRead) -> bool {
+    print!("    [m]inimize, [c]lose, [s]kip, [q]uit? ");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if input.read_line(&mut answer).unwrap_or(0) == 0 {
+        return false;
+    }
+    match answer.trim().to_lowercase().as_str() {
+        "m" => match minimize_window(window.hwnd) {
+            Ok(()) => {
+                log_action("Minimized", window, rule);
+                audit::record("minimize", &format!("{} ({})", window.title, window.process_name));
+                info!("    → Minimized");
+            }
+            Err(e) => error!("    Error minimizing {}: {}", window.title, e),
+        },
+        "c" => {
+            // Ask politely, so the app can prompt about unsaved work
+            match unsafe { PostMessageW(Some(window.hwnd), WM_CLOSE, WPARAM(0), LPARAM(0)) } {
+                Ok(()) => {
+                    log_action("Asked to close", window, rule);
+                    audit::record("close", &format!("{} ({})", window.title, window.process_name));
+                    info!("    → Asked to close");
+                }
+                Err(e) => error!("    Error closing {}: {}", window.title, e),
+            }
+        }
+        "q" => return false,
+        _ => {}
+    }
+    true
+}
+
+// Function to list open windows by process with when each last had focus
+// (as recorded by the monitor), flag the ones unfocused for longer than
+// `stale_after`, and optionally offer to minimize or close those
+pub fn run_clutter(config: &MonitorConfig, interactive: bool) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let history = FocusHistory::load();
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut ProcessNameCache::default())?;
+    let now = now_secs();
+    let stale_after = config.clutter_stale_after.as_secs();
+    
+    let mut by_process: BTreeMap<String, Vec<(&WindowInfo, Option<u64>)>> = BTreeMap::new();
+    for window in windows.iter().filter(|window| !is_system_window(window)) {
+        let last_focused = history.last_focused(&WindowIdentity::of(window));
+        by_process
+            .entry(window.process_name.to_lowercase())
+            .or_default()
+            .push((window, last_focused));
+    }
+    
+    // Without any history every window would look stale
+    let has_history = !history.windows.is_empty();
+    if !has_history {
+        println!("ℹ No focus history yet; it is recorded while the monitor runs\n");
+    }
+    
+    let mut stale: Vec<&WindowInfo> = Vec::new();
+    for (process, mut entries) in by_process {
+        // Longest-unfocused first; never-seen windows lead
+        entries.sort_by_key(|(_, last_focused)| last_focused.unwrap_or(0));
+        println!("{} ({} windows)", process, entries.len());
+        for (window, last_focused) in entries {
+            let is_stale = has_history && last_focused.is_none_or(|at| now.saturating_sub(at) >= stale_after);
+            let when = last_focused.map_or_else(|| "never seen focused".to_string(), |at| ago(at, now));
+            let minimized = if unsafe { IsIconic(window.hwnd).as_bool() } { ", minimized" } else { "" };
+            let marker = if is_stale { "⚠" } else { "·" };
+            println!("  {} {} ({}{})", marker, window.title, when, minimized);
+            if is_stale {
+                stale.push(window);
+            }
+        }
+    }
+    
+    println!();
+    println!(
+        "{} of {} windows unfocused for {} or more",
+        stale.len(),
+        windows.iter().filter(|window| !is_system_window(window)).count(),
+        humantime::format_duration(Duration::from_secs(stale_after))
+    );
+    
+    if interactive && !stale.is_empty() {
+        if config.audit_log {
+            audit::open()?;
+        }
+        println!();
+        let rule = format!("clutter: unfocused for {}", humantime::format_duration(Duration::from_secs(stale_after)));
+        let mut input = io::stdin().lock();
+        for window in stale {
+            println!("  {} ({})", window.title, window.process_name);
+            if !offer_action(window, &rule, &mut input) {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
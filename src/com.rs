@@ -0,0 +1,32 @@
+use std::cell::RefCell;
+use std::thread::LocalKey;
+
+use windows::{
+    core::{Interface, Result, GUID},
+    Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT},
+};
+
+// Function to create an in-process COM object, joining COM on the calling
+// thread first. S_FALSE (already initialized) and RPC_E_CHANGED_MODE (the
+// thread is in the other apartment) both leave COM usable, so that result is
+// ignored.
+pub fn create_instance<T: Interface>(class: &GUID, apartment: COINIT) -> Result<T> {
+    unsafe {
+        let _ = CoInitializeEx(None, apartment);
+        CoCreateInstance(class, None, CLSCTX_INPROC_SERVER)
+    }
+}
+
+// Function to get the calling thread's instance of a COM class, creating it
+// on first use. A failed creation is tried again on the next call, e.g. once
+// Explorer is running again.
+pub fn thread_instance<T: Interface>(slot: &'static LocalKey<RefCell<Option<T>>>, class: &GUID, apartment: COINIT) -> Result<T> {
+    slot.with_borrow_mut(|instance| {
+        if let Some(instance) = instance {
+            return Ok(instance.clone());
+        }
+        let created: T = create_instance(class, apartment)?;
+        *instance = Some(created.clone());
+        Ok(created)
+    })
+}
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use windows::{
+    core::HSTRING,
+    Win32::Foundation::{HANDLE, WAIT_OBJECT_0},
+    Win32::Storage::FileSystem::{
+        FindCloseChangeNotification, FindFirstChangeNotificationW, FindNextChangeNotification,
+        FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+    },
+    Win32::System::Threading::WaitForSingleObject,
+};
+
+use crate::config::MonitorConfig;
+
+// Where the running monitor gets a fresh configuration from when its config
+// file changes: the file to watch and how to rebuild the whole configuration
+// (defaults, file and command-line overrides) from it
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub load: Box<dyn Fn() -> Result<MonitorConfig, Box<dyn std::error::Error>>>,
+}
+
+// Change notifications for the directory holding the config file. Editors
+// often replace the file instead of writing to it, so the directory is
+// watched and the file's modification time decides whether it changed.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    handle: HANDLE,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    // Start watching; None when the config file's directory doesn't exist
+    pub fn new(path: &Path) -> Option<Self> {
+        let dir = path.parent()?;
+        let handle = unsafe {
+            FindFirstChangeNotificationW(
+                &HSTRING::from(dir.as_os_str()),
+                false,
+                FILE_NOTIFY_CHANGE_LAST_WRITE | FILE_NOTIFY_CHANGE_FILE_NAME,
+            )
+        }
+        .ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            handle,
+            modified: modified_time(path),
+        })
+    }
+    
+    // Whether the config file was written since the last call; never blocks
+    pub fn changed(&mut self) -> bool {
+        if unsafe { WaitForSingleObject(self.handle, 0) } != WAIT_OBJECT_0 {
+            return false;
+        }
+        let _ = unsafe { FindNextChangeNotification(self.handle) };
+        
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        let _ = unsafe { FindCloseChangeNotification(self.handle) };
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,65 @@
+use tracing::{info, warn};
+
+use crate::process::running_processes;
+
+// What a conflicting tool does to windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    // Moves and resizes windows (zones, snapping, drag helpers)
+    Layout,
+    // Changes per-window opacity
+    Opacity,
+}
+
+// A known tool that acts on the same windows
+struct KnownTool {
+    process: &'static str,
+    name: &'static str,
+    kind: ConflictKind,
+}
+
+const KNOWN_TOOLS: &[KnownTool] = &[
+    KnownTool { process: "PowerToys.FancyZones.exe", name: "FancyZones", kind: ConflictKind::Layout },
+    KnownTool { process: "AltSnap.exe", name: "AltSnap", kind: ConflictKind::Layout },
+    KnownTool { process: "AltDrag.exe", name: "AltDrag", kind: ConflictKind::Layout },
+    KnownTool { process: "DisplayFusion.exe", name: "DisplayFusion", kind: ConflictKind::Layout },
+    KnownTool { process: "WindowTop.exe", name: "WindowTop", kind: ConflictKind::Opacity },
+    KnownTool { process: "Glass2k.exe", name: "Glass2k", kind: ConflictKind::Opacity },
+    KnownTool { process: "Vitrite.exe", name: "Vitrite", kind: ConflictKind::Opacity },
+];
+
+// A conflicting tool found running
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub name: &'static str,
+    pub kind: ConflictKind,
+}
+
+// Find known window managers and opacity tools that are currently running
+pub fn detect_conflicts() -> Vec<Conflict> {
+    let processes = running_processes();
+    KNOWN_TOOLS
+        .iter()
+        .filter(|tool| processes.iter().any(|(_, name)| name.eq_ignore_ascii_case(tool.process)))
+        .map(|tool| Conflict { name: tool.name, kind: tool.kind })
+        .collect()
+}
+
+// Warn about each conflicting tool and what to expect
+pub fn warn_about_conflicts(conflicts: &[Conflict]) {
+    for conflict in conflicts {
+        match conflict.kind {
+            ConflictKind::Layout => warn!(
+                "⚠ {} is running and also moves windows; snapped layouts it creates may not be recognised",
+                conflict.name
+            ),
+            ConflictKind::Opacity => warn!(
+                "⚠ {} is running and also changes window opacity; both tools may fight over the same windows",
+                conflict.name
+            ),
+        }
+    }
+    if !conflicts.is_empty() {
+        info!("");
+    }
+}
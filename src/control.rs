@@ -0,0 +1,440 @@
+use std::ffi::c_void;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::os::windows::io::IntoRawHandle;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use windows::{
+    core::{HSTRING, PWSTR},
+    Win32::Foundation::{CloseHandle, LocalFree, ERROR_PIPE_CONNECTED, HANDLE, HLOCAL, LPARAM, WPARAM},
+    Win32::Security::Authorization::{
+        ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    },
+    Win32::Security::{GetTokenInformation, TokenUser, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, TOKEN_QUERY, TOKEN_USER},
+    Win32::Storage::FileSystem::{ReadFile, WriteFile, FILE_FLAGS_AND_ATTRIBUTES, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+    Win32::System::Console::{FreeConsole, SetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE},
+    Win32::System::Pipes::*,
+    Win32::System::Threading::{GetCurrentProcess, GetCurrentThreadId, OpenProcessToken},
+    Win32::UI::WindowsAndMessaging::PostThreadMessageW,
+};
+
+use crate::config::PipeTokens;
+use crate::events::WM_WAKE;
+use crate::frontend::TrayCommand;
+use crate::subscriptions::{self, Filter};
+
+// State shared with the pipe threads
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static MONITOR_THREAD: AtomicU32 = AtomicU32::new(0);
+// Notifications waiting for the host to collect them, the oldest dropped
+// past MAX_NOTIFICATIONS
+static NOTIFICATIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+const MAX_NOTIFICATIONS: usize = 100;
+// Windows the last pass could not act on for running elevated, for `unreachable`
+static UNREACHABLE: AtomicUsize = AtomicUsize::new(0);
+// Latest enforcement latency percentiles, for `metrics`
+static METRICS: Mutex<String> = Mutex::new(String::new());
+// Features that failed to start, for `status`
+static DEGRADED: Mutex<String> = Mutex::new(String::new());
+// Latest decision latency summary, for `status`
+static DECISION_LATENCY: Mutex<String> = Mutex::new(String::new());
+// Titles of the windows ignored until the session ends, for `status`
+static IGNORED: Mutex<String> = Mutex::new(String::new());
+
+// How often an idle stream checks that its client is still connected
+const IDLE_CHECK: Duration = Duration::from_secs(5);
+
+// Longest line a client may send; anything longer ends the connection
+const MAX_LINE: usize = 4096;
+// Environment variables the host hands the pipe's tokens to `embedded` in,
+// so they don't show up on its command line
+pub const CONTROL_TOKEN_VAR: &str = "FAK_OPACITY_CONTROL_TOKEN";
+pub const READ_TOKEN_VAR: &str = "FAK_OPACITY_READ_TOKEN";
+// Shortest token accepted from the host
+const MIN_TOKEN_LEN: usize = 16;
+
+// Named pipe (\\.\pipe\<name>) a host application drives the monitor through
+// when it embeds the engine as a helper process. Any number of clients can be
+// connected at once. The protocol is one command per line, each answered with
+// a line of its own. A client first proves itself with one of the tokens the
+// host set (see tokens_from_env), which also settles what it may do:
+//
+//   hello <token>                     -> ok control | ok read, or
+//                                        error unauthorized and the
+//                                        connection is closed
+//
+// Read clients may only use the commands reporting state (status, metrics,
+// unreachable, notifications, subscribe); control clients may use them all:
+//
+//   pause, resume, restore-all, exit  -> ok
+//   status                            -> running | paused, followed by
+//                                        "; degraded: <reasons>" when a hook
+//                                        or hotkey failed to start and
+//                                        "; decisions <latency>" once a pass
+//                                        has decided and "; ignored: <titles>"
+//                                        while windows are ignored for the
+//                                        session
+//   metrics                           -> enforcement latency percentiles
+//   unreachable                       -> how many elevated windows the last
+//                                        pass could not act on
+//   notifications                     -> one "notify <text>" line each, then ok
+//   subscribe [actions] [sessions] [notifications] [process=<exe>]
+//                                     -> ok, then a stream of JSON events
+//
+// Subscribing turns the connection into an event stream (see
+// subscriptions::Event); a client that also sends commands opens a second
+// connection. Only local clients running as the same user (or SYSTEM, for
+// the service) can open the pipe at all. The pipe threads block waiting for
+// clients and go away with the process.
+pub struct ControlPipe {
+    commands: Receiver<TrayCommand>,
+}
+
+impl ControlPipe {
+    // Create the pipe; None when it can't be created or another process
+    // already owns the name
+    pub fn spawn(name: &str, tokens: &PipeTokens) -> Option<Self> {
+        if tokens.control.is_empty() {
+            return None;
+        }
+        let path = HSTRING::from(format!(r"\\.\pipe\{}", name));
+        let first = create_instance(&path, FILE_FLAG_FIRST_PIPE_INSTANCE)?;
+        MONITOR_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+        
+        let (sender, commands) = mpsc::channel();
+        let first = first.0 as usize;
+        let tokens = tokens.clone();
+        thread::spawn(move || listen(path, HANDLE(first as *mut _), sender, tokens));
+        Some(Self { commands })
+    }
+    
+    // Commands received since the last call
+    pub fn commands(&self) -> Vec<TrayCommand> {
+        self.commands.try_iter().collect()
+    }
+    
+    // Reflect the pause state in `status` replies
+    pub fn set_paused(&self, paused: bool) {
+        PAUSED.store(paused, Ordering::Relaxed);
+    }
+    
+    // Record the features that failed to start, for `status` replies
+    pub fn set_degraded(&self, reasons: &[String]) {
+        *DEGRADED.lock().unwrap_or_else(|e| e.into_inner()) = reasons.join(", ");
+    }
+    
+    // Update what `unreachable` reports
+    pub fn set_unreachable(&self, count: usize) {
+        UNREACHABLE.store(count, Ordering::Relaxed);
+    }
+    
+    // Update the decision latency `status` reports
+    pub fn set_decision_latency(&self, summary: &str) {
+        *DECISION_LATENCY.lock().unwrap_or_else(|e| e.into_inner()) = summary.to_string();
+    }
+    
+    // Update the session ignores `status` reports
+    pub fn set_ignored(&self, titles: &[String]) {
+        *IGNORED.lock().unwrap_or_else(|e| e.into_inner()) = titles.join(", ");
+    }
+    
+    // Update what `metrics` reports
+    pub fn set_metrics(&self, metrics: &str) {
+        *METRICS.lock().unwrap_or_else(|e| e.into_inner()) = metrics.to_string();
+    }
+    
+    // Queue a notification for the host to show in its own UI
+    pub fn notify(&self, text: &str) {
+        let mut notifications = NOTIFICATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        if notifications.len() >= MAX_NOTIFICATIONS {
+            notifications.remove(0);
+        }
+        notifications.push(text.to_string());
+    }
+}
+
+// Function to take the pipe's tokens from the environment the host started
+// `embedded` with. The control token is required; the read token is optional.
+pub fn tokens_from_env() -> Result<PipeTokens, Box<dyn std::error::Error>> {
+    let control = std::env::var(CONTROL_TOKEN_VAR).map_err(|_| format!("{} must hold the control pipe's token", CONTROL_TOKEN_VAR))?;
+    let read = std::env::var(READ_TOKEN_VAR).ok();
+    if std::iter::once(&control).chain(&read).any(|token| token.len() < MIN_TOKEN_LEN) {
+        return Err(format!("control pipe tokens must be at least {} characters", MIN_TOKEN_LEN).into());
+    }
+    if read.as_ref() == Some(&control) {
+        return Err(format!("{} must differ from {}", READ_TOKEN_VAR, CONTROL_TOKEN_VAR).into());
+    }
+    Ok(PipeTokens { control, read })
+}
+
+// Function to ask the monitor behind a control pipe for its `status` reply,
+// sending the token found in the environment (read token first)
+pub fn query_status(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let token = std::env::var(READ_TOKEN_VAR)
+        .or_else(|_| std::env::var(CONTROL_TOKEN_VAR))
+        .map_err(|_| format!("{} or {} must hold the pipe's token", READ_TOKEN_VAR, CONTROL_TOKEN_VAR))?;
+    let pipe = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!(r"\\.\pipe\{}", name))
+        .map_err(|e| format!("could not open the control pipe {}: {}", name, e))?;
+    (&pipe).write_all(format!("hello {}\nstatus\n", token).as_bytes())?;
+    
+    let mut lines = BufReader::new(&pipe).lines();
+    let hello = lines.next().transpose()?.unwrap_or_default();
+    if !hello.starts_with("ok ") {
+        return Err(format!("the control pipe refused the token ({})", hello).into());
+    }
+    Ok(lines.next().transpose()?.ok_or("the control pipe closed without replying")?)
+}
+
+// Function to create one instance of the pipe, waiting for the next client
+fn create_instance(path: &HSTRING, flags: FILE_FLAGS_AND_ATTRIBUTES) -> Option<HANDLE> {
+    let descriptor = user_only_descriptor()?;
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor.0,
+        bInheritHandle: false.into(),
+    };
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            path,
+            PIPE_ACCESS_DUPLEX | flags,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            4096,
+            4096,
+            0,
+            Some(&attributes),
+        )
+    };
+    unsafe {
+        LocalFree(Some(HLOCAL(descriptor.0)));
+    }
+    (!pipe.is_invalid()).then_some(pipe)
+}
+
+// Function to build the pipe's security descriptor: full access for the user
+// the monitor runs as and for SYSTEM, nobody else. The caller frees it with
+// LocalFree. None when the user can't be found, so no pipe is made.
+fn user_only_descriptor() -> Option<PSECURITY_DESCRIPTOR> {
+    let sddl = HSTRING::from(format!("D:P(A;;GA;;;SY)(A;;GA;;;{})", current_user_sid()?));
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    unsafe { ConvertStringSecurityDescriptorToSecurityDescriptorW(&sddl, SDDL_REVISION_1, &mut descriptor, None) }.ok()?;
+    Some(descriptor)
+}
+
+// Function to get the SID of the user the process runs as, in S-1-5-... form
+fn current_user_sid() -> Option<String> {
+    let mut token = HANDLE::default();
+    unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) }.ok()?;
+    let mut size = 0u32;
+    let _ = unsafe { GetTokenInformation(token, TokenUser, None, 0, &mut size) };
+    // u64s keep the TOKEN_USER at the start aligned
+    let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+    let queried = unsafe { GetTokenInformation(token, TokenUser, Some(buffer.as_mut_ptr() as *mut c_void), size, &mut size) };
+    let _ = unsafe { CloseHandle(token) };
+    queried.ok()?;
+    
+    let user = unsafe { &*(buffer.as_ptr() as *const TOKEN_USER) };
+    let mut text = PWSTR::null();
+    unsafe { ConvertSidToStringSidW(user.User.Sid, &mut text) }.ok()?;
+    let sid = unsafe { text.to_string() }.ok();
+    unsafe {
+        LocalFree(Some(HLOCAL(text.0 as _)));
+    }
+    sid
+}
+
+// What a client may do, settled by the token it opened with
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    Control,
+    Read,
+}
+
+// Function to check a client's `hello <token>` line
+fn authenticate(line: &str, tokens: &PipeTokens) -> Option<Scope> {
+    let token = line.strip_prefix("hello ")?;
+    if same_token(token, &tokens.control) {
+        return Some(Scope::Control);
+    }
+    tokens.read.as_deref().filter(|read| same_token(token, read)).map(|_| Scope::Read)
+}
+
+// Function to compare tokens in time that doesn't depend on where they differ
+fn same_token(given: &str, expected: &str) -> bool {
+    !expected.is_empty()
+        && given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// Function to hand each client that connects its own thread, with a fresh
+// instance waiting for the next one
+fn listen(path: HSTRING, first: HANDLE, commands: Sender<TrayCommand>, tokens: PipeTokens) {
+    let mut pipe = first;
+    loop {
+        let connected = unsafe { ConnectNamedPipe(pipe, None) };
+        if let Err(e) = connected
+            && e.code() != ERROR_PIPE_CONNECTED.to_hresult()
+        {
+            let _ = unsafe { CloseHandle(pipe) };
+            break;
+        }
+        
+        // Pipe handles aren't Send, so they cross the thread as plain values
+        let client = pipe.0 as usize;
+        let commands = commands.clone();
+        let tokens = tokens.clone();
+        thread::spawn(move || serve(HANDLE(client as *mut _), &commands, &tokens));
+        
+        let Some(next) = create_instance(&path, FILE_FLAGS_AND_ATTRIBUTES(0)) else { break };
+        pipe = next;
+    }
+}
+
+// Function to serve one client until it disconnects
+fn serve(pipe: HANDLE, commands: &Sender<TrayCommand>, tokens: &PipeTokens) {
+    // Read the client's commands line by line
+    let mut scope = None;
+    let mut pending = Vec::new();
+    let mut buffer = [0u8; 512];
+    'client: loop {
+        let mut read = 0u32;
+        if unsafe { ReadFile(pipe, Some(&mut buffer), Some(&mut read), None) }.is_err() || read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buffer[..read as usize]);
+        if pending.len() > MAX_LINE && !pending.contains(&b'\n') {
+            write_line(pipe, "error line too long");
+            break;
+        }
+        while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            
+            let Some(scope) = scope else {
+                scope = authenticate(&line, tokens);
+                match scope {
+                    Some(Scope::Control) => write_line(pipe, "ok control"),
+                    Some(Scope::Read) => write_line(pipe, "ok read"),
+                    None => {
+                        write_line(pipe, "error unauthorized");
+                        break 'client;
+                    }
+                };
+                continue;
+            };
+            if let Some(args) = line.strip_prefix("subscribe").filter(|args| args.is_empty() || args.starts_with(' ')) {
+                match Filter::parse(args) {
+                    Ok(filter) => {
+                        if write_line(pipe, "ok") {
+                            stream(pipe, filter);
+                        }
+                        break 'client;
+                    }
+                    Err(e) => {
+                        if !write_line(pipe, &format!("error {}", e)) {
+                            break 'client;
+                        }
+                        continue;
+                    }
+                }
+            }
+            if !write_line(pipe, &handle(&line, scope, commands)) {
+                break 'client;
+            }
+        }
+    }
+    unsafe {
+        let _ = DisconnectNamedPipe(pipe);
+        let _ = CloseHandle(pipe);
+    }
+}
+
+// Function to write events to a subscribed client until it goes away
+fn stream(pipe: HANDLE, filter: Filter) {
+    let subscriber = subscriptions::subscribe(filter);
+    loop {
+        let batch = subscriber.next_batch(IDLE_CHECK);
+        // A quiet stream only notices the client left by checking the pipe
+        let connected = if batch.is_empty() {
+            unsafe { PeekNamedPipe(pipe, None, 0, None, None, None) }.is_ok()
+        } else {
+            batch.iter().all(|line| write_line(pipe, line))
+        };
+        if !connected {
+            break;
+        }
+    }
+    subscriptions::unsubscribe(&subscriber);
+}
+
+// Function to send one line to a client; false once it's gone
+fn write_line(pipe: HANDLE, line: &str) -> bool {
+    let line = format!("{}\n", line.trim_end_matches('\n'));
+    unsafe { WriteFile(pipe, Some(line.as_bytes()), None, None) }.is_ok()
+}
+
+// Function to carry out one command and build its reply
+fn handle(line: &str, scope: Scope, commands: &Sender<TrayCommand>) -> String {
+    let command = match line {
+        "pause" | "resume" | "restore-all" | "exit" if scope != Scope::Control => {
+            return format!("error {} needs the control token", line);
+        }
+        "pause" => TrayCommand::Pause,
+        "resume" => TrayCommand::Resume,
+        "restore-all" => TrayCommand::RestoreAll,
+        "exit" => TrayCommand::Exit,
+        "status" => {
+            let state = if PAUSED.load(Ordering::Relaxed) { "paused" } else { "running" };
+            let mut status = state.to_string();
+            let degraded = DEGRADED.lock().unwrap_or_else(|e| e.into_inner());
+            if !degraded.is_empty() {
+                status.push_str(&format!("; degraded: {}", degraded));
+            }
+            let latency = DECISION_LATENCY.lock().unwrap_or_else(|e| e.into_inner());
+            if !latency.is_empty() {
+                status.push_str(&format!("; decisions {}", latency));
+            }
+            let ignored = IGNORED.lock().unwrap_or_else(|e| e.into_inner());
+            if !ignored.is_empty() {
+                status.push_str(&format!("; ignored: {}", ignored));
+            }
+            return status;
+        }
+        "metrics" => {
+            let metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+            return if metrics.is_empty() { "no passes yet".to_string() } else { metrics.clone() };
+        }
+        "unreachable" => return UNREACHABLE.load(Ordering::Relaxed).to_string(),
+        "notifications" => {
+            let pending = std::mem::take(&mut *NOTIFICATIONS.lock().unwrap_or_else(|e| e.into_inner()));
+            return pending.iter().map(|text| format!("notify {}\n", text)).chain(["ok".to_string()]).collect();
+        }
+        _ => return format!("error unknown command {:?}", line),
+    };
+    let _ = commands.send(command);
+    unsafe {
+        let _ = PostThreadMessageW(MONITOR_THREAD.load(Ordering::Relaxed), WM_WAKE, WPARAM(0), LPARAM(0));
+    }
+    "ok".to_string()
+}
+
+// Function to drop all console output for embedded runs: stdout and stderr go
+// to NUL, and a console window the host didn't suppress is let go of
+pub fn silence_console() {
+    if let Ok(nul) = OpenOptions::new().write(true).open("NUL") {
+        // Kept open for the rest of the process
+        let nul = HANDLE(nul.into_raw_handle());
+        unsafe {
+            let _ = SetStdHandle(STD_OUTPUT_HANDLE, nul);
+            let _ = SetStdHandle(STD_ERROR_HANDLE, nul);
+        }
+    }
+    let _ = unsafe { FreeConsole() };
+}
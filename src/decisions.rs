@@ -0,0 +1,201 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+use windows::Win32::Foundation::HWND;
+
+use crate::rules::{first_matches, KeywordMatcher};
+
+// A title changing more often than this within FLAP_WINDOW counts as flapping
+const FLAP_CHANGES: u32 = 5;
+const FLAP_WINDOW: Duration = Duration::from_secs(10);
+
+// Target and ignored keyword matches (index of the first matching keyword)
+pub type Decision = (Option<usize>, Option<usize>);
+
+// Cached matching result for one window under one ruleset
+struct CachedDecision {
+    // Title with the parts that can't affect matching replaced
+    stable_title: String,
+    last_title: String,
+    decision: Decision,
+    changes: u32,
+    changes_since: Instant,
+    reported: bool,
+}
+
+// Per-window keyword matching results keyed by (hwnd, ruleset hash), so
+// titles that keep changing (progress percentages, timers) are only rematched
+// when the change could alter the decision
+#[derive(Default)]
+pub struct DecisionCache {
+    entries: HashMap<(usize, u64), CachedDecision>,
+    // Lookups answered from the cache and ones that had to rematch
+    hits: u64,
+    misses: u64,
+}
+
+// Function to hash a ruleset so cached decisions never outlive the rules they came from
+fn ruleset_hash(targets: &[KeywordMatcher], ignored: &[KeywordMatcher]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for rules in [targets, ignored] {
+        rules.iter().map(|k| k.keyword.as_str()).collect::<Vec<&str>>().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// Function to collapse the digit runs that count or tick (percentages, clock
+// times, "(3)" unread counts) into one '#'. A keyword without digits can't
+// match inside a digit run, so titles that only differ there ("Building 41%"
+// / "Building 42%") always get the same decision. Other numbers, like the one
+// in "Issue #12", are kept, so another document gets its own entry.
+fn stable_title(title: &str, strip_digits: bool) -> String {
+    if !strip_digits {
+        return title.to_string();
+    }
+    let chars: Vec<char> = title.chars().collect();
+    let mut stable = String::with_capacity(title.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            stable.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        // The run of digits, with the decimal separators between them
+        let start = i;
+        while i < chars.len()
+            && (chars[i].is_ascii_digit() || (matches!(chars[i], '.' | ',') && chars.get(i + 1).is_some_and(char::is_ascii_digit)))
+        {
+            i += 1;
+        }
+        let before = start.checked_sub(1).map(|j| chars[j]);
+        let after = chars.get(i).copied();
+        let ticking = after == Some('%')
+            || before == Some(':')
+            || after == Some(':')
+            || matches!((before, after), (Some('('), Some(')')) | (Some('['), Some(']')));
+        if ticking {
+            stable.push('#');
+        } else {
+            stable.extend(&chars[start..i]);
+        }
+    }
+    stable
+}
+
+impl DecisionCache {
+    // Function to match every window's lowercased title against the target
+    // and ignored keywords, reusing cached decisions where possible
+    pub fn matches(&mut self, windows: &[(HWND, &str)], targets: &[KeywordMatcher], ignored: &[KeywordMatcher]) -> Vec<Decision> {
+        let ruleset = ruleset_hash(targets, ignored);
+        let strip_digits = !targets.iter().chain(ignored).any(|k| k.may_match_digits());
+        let now = Instant::now();
+        
+        // Forget closed windows and decisions made under other rulesets
+        self.entries.retain(|(hwnd, hash), _| {
+            *hash == ruleset && windows.iter().any(|(window, _)| window.0 as usize == *hwnd)
+        });
+        
+        let mut decisions: Vec<Option<Decision>> = Vec::with_capacity(windows.len());
+        let mut misses: Vec<usize> = Vec::new();
+        for (i, (hwnd, title)) in windows.iter().enumerate() {
+            let stable = stable_title(title, strip_digits);
+            match self.entries.get_mut(&(hwnd.0 as usize, ruleset)) {
+                Some(entry) if entry.stable_title == stable => {
+                    if entry.last_title != *title {
+                        entry.last_title = title.to_string();
+                        note_change(entry, now, title);
+                    }
+                    decisions.push(Some(entry.decision));
+                    self.hits += 1;
+                }
+                _ => {
+                    decisions.push(None);
+                    misses.push(i);
+                    self.misses += 1;
+                }
+            }
+        }
+        
+        // Rematch only the windows whose title changed in a way that matters
+        let titles: Vec<&str> = misses.iter().map(|&i| windows[i].1).collect();
+        let target_matches = first_matches(&titles, targets);
+        let ignored_matches = first_matches(&titles, ignored);
+        for (n, &i) in misses.iter().enumerate() {
+            let (hwnd, title) = windows[i];
+            let decision = (target_matches[n], ignored_matches[n]);
+            decisions[i] = Some(decision);
+            
+            let stable = stable_title(title, strip_digits);
+            match self.entries.get_mut(&(hwnd.0 as usize, ruleset)) {
+                Some(entry) => {
+                    entry.stable_title = stable;
+                    entry.last_title = title.to_string();
+                    entry.decision = decision;
+                    note_change(entry, now, title);
+                }
+                None => {
+                    self.entries.insert((hwnd.0 as usize, ruleset), CachedDecision {
+                        stable_title: stable,
+                        last_title: title.to_string(),
+                        decision,
+                        changes: 0,
+                        changes_since: now,
+                        reported: false,
+                    });
+                }
+            }
+        }
+        
+        decisions.into_iter().map(|decision| decision.unwrap_or_default()).collect()
+    }
+    
+    // Share of lookups the cache answered, e.g. "87% of 1204 lookups"
+    pub fn hit_rate(&self) -> String {
+        let lookups = self.hits + self.misses;
+        let percent = (self.hits * 100).checked_div(lookups).unwrap_or(0);
+        format!("{}% of {} lookups", percent, lookups)
+    }
+}
+
+// Function to count title changes and report a window once when it flaps
+fn note_change(entry: &mut CachedDecision, now: Instant, title: &str) {
+    if now.duration_since(entry.changes_since) > FLAP_WINDOW {
+        entry.changes = 0;
+        entry.changes_since = now;
+    }
+    entry.changes += 1;
+    if entry.changes >= FLAP_CHANGES && !entry.reported {
+        info!("ℹ Title keeps changing, matching only when the change matters: {}", title);
+        entry.reported = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn stable_title_folds_ticking_numbers() {
+        assert_eq!(stable_title("building 41%", true), "building #%");
+        assert_eq!(stable_title("building 41.5%", true), "building #%");
+        assert_eq!(stable_title("call 12:03:59", true), "call #:#:#");
+        assert_eq!(stable_title("(3) inbox", true), "(#) inbox");
+        assert_eq!(stable_title("[12] tasks", true), "[#] tasks");
+    }
+    
+    #[test]
+    fn stable_title_keeps_other_numbers() {
+        assert_eq!(stable_title("issue #12 - tracker", true), "issue #12 - tracker");
+        assert_eq!(stable_title("report 2024.docx", true), "report 2024.docx");
+        assert_ne!(stable_title("issue #12", true), stable_title("issue #13", true));
+    }
+    
+    #[test]
+    fn stable_title_without_stripping_is_unchanged() {
+        assert_eq!(stable_title("building 41%", false), "building 41%");
+    }
+}
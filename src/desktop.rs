@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use windows::Win32::System::StationsAndDesktops::{
+    CloseDesktop, OpenInputDesktop, DESKTOP_CONTROL_FLAGS, DESKTOP_READOBJECTS,
+};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+// Check whether the desktop receiving input is the one we run on.
+// OpenInputDesktop fails while the secure desktop (UAC prompt, Ctrl+Alt+Del,
+// lock screen) owns input, and the foreground handle is stale during that time.
+pub fn is_input_desktop_accessible() -> bool {
+    match unsafe { OpenInputDesktop(DESKTOP_CONTROL_FLAGS(0), false, DESKTOP_READOBJECTS) } {
+        Ok(desktop) => {
+            unsafe {
+                let _ = CloseDesktop(desktop);
+            }
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+// Time since the last keyboard or mouse input in this session
+pub fn user_idle_time() -> Duration {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        return Duration::ZERO;
+    }
+    // Both are 32-bit tick counts, so wrapping subtraction survives the 49-day rollover
+    let now = unsafe { GetTickCount() };
+    Duration::from_millis(now.wrapping_sub(info.dwTime) as u64)
+}
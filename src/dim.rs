@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+use windows::{
+    core::BOOL,
+    Win32::Foundation::*,
+    Win32::System::Console::SetConsoleCtrlHandler,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+// How often idle times are re-evaluated; dimming is gradual so this can be slow
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Alpha changes smaller than this aren't worth repainting the window for
+const ALPHA_STEP: u8 = 8;
+
+// Time between the steps of a fade animation, about one frame at 60 Hz
+const ANIMATION_FRAME: Duration = Duration::from_millis(16);
+
+// Windows this tool made layered (idle-dimmed or faded), with their current
+// alpha. Kept globally so the console control handler can undim everything
+// when the monitor exits.
+static DIMMED: Mutex<Option<HashMap<usize, u8>>> = Mutex::new(None);
+// Windows among them whose opacity was picked by hand; idle dimming leaves
+// them alone
+static MANUAL: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+// Opacity changes in progress, stepped by a worker thread while there are any
+static ANIMATIONS: Mutex<Vec<(usize, Animation)>> = Mutex::new(Vec::new());
+// How long fading in or out takes, in milliseconds (0 = instant)
+static ANIMATION_MILLIS: AtomicU64 = AtomicU64::new(0);
+
+// A window's opacity moving from one alpha to another
+struct Animation {
+    from: u8,
+    to: u8,
+    started: Instant,
+}
+
+impl Animation {
+    // Alpha at this point of an animation lasting `duration`
+    fn current(&self, duration: Duration) -> u8 {
+        let progress = (self.started.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0);
+        (self.from as f64 + (self.to as f64 - self.from as f64) * progress).round() as u8
+    }
+}
+
+// Function to set a window's opacity, making it layered first
+fn set_alpha(hwnd: HWND, alpha: u8) -> bool {
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        if style & WS_EX_LAYERED.0 as isize == 0 {
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED.0 as isize);
+        }
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA).is_ok()
+    }
+}
+
+// Function to return a dimmed window to full opacity and drop the layered style
+fn clear_alpha(hwnd: HWND) {
+    unsafe {
+        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), 255, LWA_ALPHA);
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style & !(WS_EX_LAYERED.0 as isize));
+    }
+}
+
+// Function to set how long fading windows in or out takes; zero switches
+// opacity instantly
+pub fn set_animation_duration(duration: Duration) {
+    ANIMATION_MILLIS.store(duration.as_millis() as u64, Ordering::Relaxed);
+}
+
+fn animation_duration() -> Duration {
+    Duration::from_millis(ANIMATION_MILLIS.load(Ordering::Relaxed))
+}
+
+// Function to give a window its final opacity, dropping the layered style at 255
+fn finish_alpha(hwnd: HWND, alpha: u8) -> bool {
+    if alpha == 255 {
+        clear_alpha(hwnd);
+        true
+    } else {
+        set_alpha(hwnd, alpha)
+    }
+}
+
+fn is_animating(hwnd: usize) -> bool {
+    ANIMATIONS.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|(key, _)| *key == hwnd)
+}
+
+// Function to move a window's opacity from `from` to `to` over the animation
+// duration, picking up where a running animation of it got to
+fn animate(hwnd: HWND, from: u8, to: u8) {
+    let duration = animation_duration();
+    let key = hwnd.0 as usize;
+    let mut animations = ANIMATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let from = match animations.iter().position(|(other, _)| *other == key) {
+        Some(i) => animations.swap_remove(i).1.current(duration),
+        None => from,
+    };
+    if duration.is_zero() || from == to {
+        finish_alpha(hwnd, to);
+        return;
+    }
+    if animations.is_empty() {
+        thread::spawn(run_animations);
+    }
+    animations.push((key, Animation { from, to, started: Instant::now() }));
+}
+
+// Worker stepping every animation each frame, until none are left
+fn run_animations() {
+    loop {
+        thread::sleep(ANIMATION_FRAME);
+        let duration = animation_duration();
+        let mut animations = ANIMATIONS.lock().unwrap_or_else(|e| e.into_inner());
+        animations.retain(|(key, animation)| {
+            let hwnd = HWND(*key as *mut _);
+            if !unsafe { IsWindow(Some(hwnd)).as_bool() } {
+                return false;
+            }
+            if animation.started.elapsed() >= duration {
+                finish_alpha(hwnd, animation.to);
+                return false;
+            }
+            set_alpha(hwnd, animation.current(duration));
+            true
+        });
+        if animations.is_empty() {
+            return;
+        }
+    }
+}
+
+// Function to undim every window this tool dimmed
+pub fn undim_all() {
+    MANUAL.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    // Windows still fading back in are done right away too
+    let animating: Vec<usize> = ANIMATIONS.lock().unwrap_or_else(|e| e.into_inner()).drain(..).map(|(key, _)| key).collect();
+    for hwnd in animating {
+        clear_alpha(HWND(hwnd as *mut _));
+    }
+    let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dimmed) = dimmed.as_mut() {
+        for (hwnd, _) in dimmed.drain() {
+            clear_alpha(HWND(hwnd as *mut _));
+        }
+    }
+}
+
+// Console control handler: undim before the process is terminated
+unsafe extern "system" fn ctrl_handler(_ctrl_type: u32) -> BOOL {
+    undim_all();
+    // Let the default handler exit the process
+    false.into()
+}
+
+// Function to start tracking layered windows and undim them on exit
+pub fn install_exit_handler() {
+    let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+    if dimmed.is_some() {
+        return;
+    }
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(ctrl_handler), true) } {
+        warn!("Could not install exit handler, dimmed windows may stay dimmed: {}", e);
+    }
+    *dimmed = Some(HashMap::new());
+}
+
+// Function to make a window semi-transparent until unfade is called, fading
+// it out over the animation duration. Windows that are layered by their own
+// app are left alone.
+pub fn fade(hwnd: HWND, alpha: u8) -> bool {
+    let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(dimmed) = dimmed.as_mut() else { return false };
+    
+    let key = hwnd.0 as usize;
+    let is_layered = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } & WS_EX_LAYERED.0 as isize != 0;
+    if is_layered && !dimmed.contains_key(&key) && !is_animating(key) {
+        return false;
+    }
+    // Making it layered at its current opacity tells whether it can be faded
+    let from = dimmed.get(&key).copied().unwrap_or(255);
+    if !is_animating(key) && !set_alpha(hwnd, from) {
+        return false;
+    }
+    animate(hwnd, from, alpha);
+    dimmed.insert(key, alpha);
+    true
+}
+
+// Function to get the opacity this tool gave a window, 255 when untouched
+pub fn opacity(hwnd: HWND) -> u8 {
+    let dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+    dimmed.as_ref().and_then(|dimmed| dimmed.get(&(hwnd.0 as usize)).copied()).unwrap_or(255)
+}
+
+// Function to set a window's opacity by hand until the monitor exits; 255
+// returns it to normal. Windows layered by their own app are left alone.
+pub fn set_manual_opacity(hwnd: HWND, alpha: u8) -> bool {
+    install_exit_handler();
+    MANUAL.lock().unwrap_or_else(|e| e.into_inner()).retain(|&other| other != hwnd.0 as usize);
+    if alpha == 255 {
+        unfade(&[hwnd]);
+        return true;
+    }
+    let set = fade(hwnd, alpha);
+    if set {
+        MANUAL.lock().unwrap_or_else(|e| e.into_inner()).push(hwnd.0 as usize);
+    }
+    set
+}
+
+fn is_manual(hwnd: usize) -> bool {
+    MANUAL.lock().unwrap_or_else(|e| e.into_inner()).contains(&hwnd)
+}
+
+// Function to return faded windows to full opacity
+pub fn unfade(windows: &[HWND]) {
+    let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(dimmed) = dimmed.as_mut() else { return };
+    for &hwnd in windows {
+        if let Some(alpha) = dimmed.remove(&(hwnd.0 as usize))
+            && unsafe { IsWindow(Some(hwnd)).as_bool() }
+        {
+            animate(hwnd, alpha, 255);
+        }
+    }
+}
+
+// Progressively dims windows that haven't been focused for a while, and
+// undims them the moment they are focused again
+pub struct IdleDimmer {
+    after: Duration,
+    ramp: Duration,
+    floor: u8,
+    started: Instant,
+    last_focus: HashMap<usize, Instant>,
+    last_check: Instant,
+}
+
+impl IdleDimmer {
+    // Windows start dimming after `after` without focus and reach `floor`
+    // opacity (0-255) over the following `ramp`
+    pub fn new(after: Duration, ramp: Duration, floor: u8) -> Self {
+        install_exit_handler();
+        
+        Self {
+            after,
+            ramp,
+            floor,
+            started: Instant::now(),
+            last_focus: HashMap::new(),
+            last_check: Instant::now(),
+        }
+    }
+    
+    // The foreground window was focused just now; undim it right away
+    pub fn focused(&mut self, hwnd: HWND) {
+        self.last_focus.insert(hwnd.0 as usize, Instant::now());
+        if is_manual(hwnd.0 as usize) {
+            return;
+        }
+        let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dimmed) = dimmed.as_mut()
+            && let Some(alpha) = dimmed.remove(&(hwnd.0 as usize))
+        {
+            animate(hwnd, alpha, 255);
+        }
+    }
+    
+    // Whether the periodic idle check is due
+    pub fn due(&self) -> bool {
+        self.last_check.elapsed() >= CHECK_INTERVAL
+    }
+    
+    // Function to update the opacity of the given windows from their idle time
+    pub fn update(&mut self, windows: &[HWND], foreground: HWND) {
+        self.last_check = Instant::now();
+        
+        let mut dimmed = DIMMED.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(dimmed) = dimmed.as_mut() else { return };
+        
+        // Forget windows that were closed
+        self.last_focus.retain(|hwnd, _| windows.iter().any(|w| w.0 as usize == *hwnd));
+        dimmed.retain(|hwnd, _| windows.iter().any(|w| w.0 as usize == *hwnd));
+        
+        for &hwnd in windows {
+            if hwnd == foreground || unsafe { IsIconic(hwnd).as_bool() } {
+                continue;
+            }
+            
+            // Leave windows that are layered by their own app alone
+            let key = hwnd.0 as usize;
+            let is_layered = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } & WS_EX_LAYERED.0 as isize != 0;
+            if (is_layered && !dimmed.contains_key(&key)) || is_manual(key) || is_animating(key) {
+                continue;
+            }
+            
+            let idle = self.last_focus.get(&key).unwrap_or(&self.started).elapsed();
+            let Some(dim_for) = idle.checked_sub(self.after) else { continue };
+            let progress = if self.ramp.is_zero() {
+                1.0
+            } else {
+                (dim_for.as_secs_f64() / self.ramp.as_secs_f64()).min(1.0)
+            };
+            let alpha = (255.0 - (255.0 - self.floor as f64) * progress).round() as u8;
+            
+            let current = dimmed.get(&key).copied().unwrap_or(255);
+            let worth_repainting = current.abs_diff(alpha) >= ALPHA_STEP || (alpha == self.floor && current != alpha);
+            if worth_repainting && set_alpha(hwnd, alpha) {
+                dimmed.insert(key, alpha);
+            }
+        }
+    }
+}
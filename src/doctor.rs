@@ -0,0 +1,104 @@
+use crate::build_info;
+use crate::config::{config_path, data_dir, MonitorConfig};
+use crate::conflicts::{detect_conflicts, warn_about_conflicts};
+use crate::desktop::is_input_desktop_accessible;
+use crate::events::ForegroundEvents;
+use crate::hotkeys::probe_hotkey;
+use crate::power::current_power_state;
+use crate::quick_switch;
+use crate::remote::current_session_kind;
+
+// Function to print what the monitor would see in this environment and how
+// it will adjust, to help diagnose unexpected behavior
+pub fn run_doctor(config: &MonitorConfig) {
+    println!("{}\n", build_info::version_string());
+    
+    let session = current_session_kind();
+    println!("Session: {}", session);
+    if session.is_remote() {
+        println!("  · Polling every {:?} at least", config.remote_poll_interval);
+        if config.idle_dim_after.is_some() {
+            println!("  · Idle dimming is paused (opacity doesn't render well remotely)");
+        }
+    }
+    
+    let power = current_power_state();
+    println!(
+        "Power: {}{}",
+        if power.on_ac { "AC" } else { "battery" },
+        if power.battery_saver { ", Battery Saver on" } else { "" }
+    );
+    if !power.on_ac && !config.enforce_on_battery {
+        println!("  · Enforcement is paused on battery");
+    } else if !power.on_ac && !config.ac_only_targets.is_empty() {
+        println!("  · AC-only targets are not enforced on battery: {:?}", config.ac_only_targets);
+    }
+    
+    println!(
+        "Input desktop: {}",
+        if is_input_desktop_accessible() { "accessible" } else { "secure desktop active, enforcement paused" }
+    );
+    
+    if !config.foreground_events {
+        println!("Foreground tracking: polling every {:?} (events are off in the config)", config.poll_interval);
+    } else if ForegroundEvents::install().is_some() {
+        println!("Foreground tracking: foreground events");
+    } else {
+        println!("Foreground tracking: ⚠ SetWinEventHook failed, the monitor polls every {:?} instead", config.poll_interval);
+    }
+    
+    let mut hotkeys: Vec<(String, &str)> = Vec::new();
+    if let Some(hotkey) = &config.ignore_for_session_hotkey {
+        hotkeys.push((hotkey.clone(), "ignore for session"));
+    }
+    if let Some(hotkey) = &config.ignore_once_hotkey {
+        hotkeys.push((hotkey.clone(), "ignore once"));
+    }
+    if let Some(hotkey) = &config.restore_all_hotkey {
+        hotkeys.push((hotkey.clone(), "restore all"));
+    }
+    if let Some(hotkey) = &config.command_palette_hotkey {
+        hotkeys.push((hotkey.clone(), "command palette"));
+    }
+    let opacity_hotkeys = [
+        (&config.opacity_up_hotkey, "more opaque"),
+        (&config.opacity_down_hotkey, "less opaque"),
+        (&config.opacity_preset_hotkey, "save opacity preset"),
+    ];
+    for (hotkey, purpose) in opacity_hotkeys {
+        if let Some(hotkey) = hotkey {
+            hotkeys.push((hotkey.clone(), purpose));
+        }
+    }
+    if let Some(modifiers) = &config.quick_switch_modifiers {
+        hotkeys.extend((1..=quick_switch::SLOTS).map(|slot| (format!("{}+{}", modifiers, slot), "quick switch")));
+    }
+    if !hotkeys.is_empty() {
+        println!("Hotkeys:");
+        for (hotkey, purpose) in &hotkeys {
+            match probe_hotkey(hotkey) {
+                Ok(()) => println!("  ✓ {} ({})", hotkey, purpose),
+                // A running monitor holds its own hotkeys, so they show as taken too
+                Err(e) => println!("  ⚠ {} ({}): {} (taken by a running monitor or another app?)", hotkey, purpose, e),
+            }
+        }
+    }
+    
+    match config_path() {
+        Some(path) if path.exists() => println!("Config file: {}", path.display()),
+        Some(path) => println!("Config file: none, using built-in defaults (looked for {})", path.display()),
+        None => println!("Config file: unavailable (APPDATA is not set)"),
+    }
+    match data_dir() {
+        Some(dir) => println!("Data directory: {}", dir.display()),
+        None => println!("Data directory: unavailable (LOCALAPPDATA is not set)"),
+    }
+    println!();
+    
+    let conflicts = detect_conflicts();
+    if conflicts.is_empty() {
+        println!("✓ No conflicting window tools running");
+    } else {
+        warn_about_conflicts(&conflicts);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::config::Escalation;
+use crate::restore_list::WindowIdentity;
+use crate::WindowInfo;
+
+// What an escalating pass does to a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationLevel {
+    Fade,
+    Minimize,
+    // Minimize and take out of Alt-Tab and the taskbar
+    Hide,
+}
+
+// Per-window offense counts for SuppressAction::Escalate, kept for the focus
+// session by window fingerprint, so restarting an app doesn't reset its windows.
+// An offense is a window being on screen at full opacity when a pass has to
+// suppress it, so a window that stays faded isn't counted again.
+#[derive(Default)]
+pub struct EscalationCounters {
+    offenses: Vec<(WindowIdentity, u32)>,
+}
+
+impl EscalationCounters {
+    // Function to count an offense (if it is one) and return the window's level
+    pub fn offense(&mut self, window: &WindowInfo, counts: bool, escalation: &Escalation) -> EscalationLevel {
+        let identity = WindowIdentity::of(window);
+        let index = match self.offenses.iter().position(|(existing, _)| existing.same_window(&identity)) {
+            Some(index) => index,
+            None => {
+                self.offenses.push((identity, 0));
+                self.offenses.len() - 1
+            }
+        };
+        let offenses = &mut self.offenses[index].1;
+        if counts {
+            *offenses += 1;
+        }
+        if *offenses >= escalation.hide_after {
+            EscalationLevel::Hide
+        } else if *offenses >= escalation.minimize_after {
+            EscalationLevel::Minimize
+        } else {
+            EscalationLevel::Fade
+        }
+    }
+    
+    // Function to forget every offense once the focus session ends
+    pub fn clear(&mut self) {
+        self.offenses.clear();
+    }
+}
@@ -0,0 +1,242 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+use windows::Win32::{
+    Foundation::HWND,
+    System::SystemInformation::GetTickCount,
+    UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent},
+    UI::WindowsAndMessaging::*,
+};
+
+use crate::process::{process_name, window_process_id};
+
+// Posted to the monitor thread to wake it from ForegroundEvents::wait
+pub const WM_WAKE: u32 = WM_APP + 1;
+
+// Set by the hook callback, cleared when the monitor loop wakes up for it
+static FOREGROUND_CHANGED: AtomicBool = AtomicBool::new(false);
+// Set when the foreground window's title changes, cleared by take_title_changed
+static TITLE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+// Title changes are counted per process over this long
+const SPAM_WINDOW: Duration = Duration::from_secs(60);
+// How long a process that changes titles too often is left out
+const SPAM_EXCLUSION: Duration = Duration::from_secs(10 * 60);
+
+// Processes flooding the hook with title changes (players updating a timer,
+// terminals showing progress). Once one goes over the limit its changes are
+// dropped right after the process lookup, so it can't keep the CPU busy.
+struct TitleSpam {
+    limit: u32,
+    counts: HashMap<u32, (Instant, u32)>,
+    excluded: HashMap<u32, Instant>,
+}
+
+impl TitleSpam {
+    // Count a title change; true when the process is left out
+    fn is_spam(&mut self, process_id: u32) -> bool {
+        let now = Instant::now();
+        if let Some(until) = self.excluded.get(&process_id) {
+            if now < *until {
+                return true;
+            }
+            self.excluded.remove(&process_id);
+        }
+        
+        if self.counts.len() > 256 {
+            self.counts.retain(|_, (start, _)| now.duration_since(*start) < SPAM_WINDOW);
+        }
+        let (start, count) = self.counts.entry(process_id).or_insert((now, 0));
+        if now.duration_since(*start) >= SPAM_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count <= self.limit {
+            return false;
+        }
+        
+        self.counts.remove(&process_id);
+        self.excluded.insert(process_id, now + SPAM_EXCLUSION);
+        info!(
+            "ℹ {} changes window titles more than {} times a minute, ignoring its title changes for {:?}\n",
+            process_name(process_id),
+            self.limit,
+            SPAM_EXCLUSION
+        );
+        true
+    }
+}
+
+// Out-of-context hooks call back on the thread that installed them
+thread_local! {
+    static TITLE_SPAM: RefCell<Option<TitleSpam>> = const { RefCell::new(None) };
+    // When the latest foreground (or foreground title) change happened
+    static LAST_EVENT_AT: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+// Function to remember when an event happened; its time is in GetTickCount
+// milliseconds, so delivery delays count towards the latency
+fn note_event(event_time: u32) {
+    let age = Duration::from_millis(unsafe { GetTickCount() }.wrapping_sub(event_time) as u64);
+    LAST_EVENT_AT.set(Instant::now().checked_sub(age).or(Some(Instant::now())));
+}
+
+unsafe extern "system" fn foreground_hook(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    event_time: u32,
+) {
+    FOREGROUND_CHANGED.store(true, Ordering::Relaxed);
+    note_event(event_time);
+}
+
+unsafe extern "system" fn name_change_hook(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    event_time: u32,
+) {
+    // Only window titles, not the names of controls inside windows
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    let process_id = window_process_id(hwnd);
+    if TITLE_SPAM.with_borrow_mut(|spam| spam.as_mut().is_some_and(|spam| spam.is_spam(process_id))) {
+        return;
+    }
+    if hwnd == unsafe { GetForegroundWindow() } {
+        TITLE_CHANGED.store(true, Ordering::Relaxed);
+        note_event(event_time);
+    }
+}
+
+// EVENT_SYSTEM_FOREGROUND notifications for the current thread. Out-of-context
+// hooks are delivered through the thread's message queue, so the monitor
+// loop waits on its queue instead of polling the foreground window.
+pub struct ForegroundEvents {
+    hook: HWINEVENTHOOK,
+    // EVENT_OBJECT_NAMECHANGE, once watch_titles is called
+    names: Option<HWINEVENTHOOK>,
+}
+
+impl ForegroundEvents {
+    // Install the hook; None when Windows refuses it
+    pub fn install() -> Option<Self> {
+        let hook = unsafe {
+            SetWinEventHook(
+                EVENT_SYSTEM_FOREGROUND,
+                EVENT_SYSTEM_FOREGROUND,
+                None,
+                Some(foreground_hook),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+            )
+        };
+        (!hook.is_invalid()).then_some(Self { hook, names: None })
+    }
+    
+    // Also wake up when the foreground window's title changes, leaving out
+    // processes that change titles more than limit times a minute. Returns
+    // false when Windows refuses the hook.
+    pub fn watch_titles(&mut self, limit: u32) -> bool {
+        if self.names.is_some() {
+            return true;
+        }
+        TITLE_SPAM.set(Some(TitleSpam {
+            limit,
+            counts: HashMap::new(),
+            excluded: HashMap::new(),
+        }));
+        let hook = unsafe {
+            SetWinEventHook(
+                EVENT_OBJECT_NAMECHANGE,
+                EVENT_OBJECT_NAMECHANGE,
+                None,
+                Some(name_change_hook),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+            )
+        };
+        self.names = (!hook.is_invalid()).then_some(hook);
+        self.names.is_some()
+    }
+    
+    // Stop waking up for title changes until watch_titles is called again
+    pub fn unwatch_titles(&mut self) {
+        if let Some(names) = self.names.take() {
+            unsafe {
+                let _ = UnhookWinEvent(names);
+            }
+        }
+        TITLE_CHANGED.store(false, Ordering::Relaxed);
+    }
+    
+    // When the change behind the current pass happened, if one was hooked
+    // since the last call
+    #[cfg(feature = "stats")]
+    pub fn take_event_time(&self) -> Option<Instant> {
+        LAST_EVENT_AT.take()
+    }
+    
+    // Whether the foreground window's title changed since the last call
+    pub fn take_title_changed(&self) -> bool {
+        TITLE_CHANGED.swap(false, Ordering::Relaxed)
+    }
+    
+    // Wait until the foreground window (or, with watch_titles, its title)
+    // changes, a hotkey is pressed, WM_WAKE arrives or the timeout passes.
+    // Hotkey messages stay queued for pending_hotkeys.
+    pub fn wait(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // Deliver hook callbacks and drop messages nothing here handles
+            let mut msg = MSG::default();
+            let mut woken = false;
+            let hotkey_queued = unsafe {
+                while PeekMessageW(&mut msg, None, 0, WM_HOTKEY - 1, PM_REMOVE).as_bool()
+                    || PeekMessageW(&mut msg, None, WM_HOTKEY + 1, u32::MAX, PM_REMOVE).as_bool()
+                {
+                    woken |= msg.message == WM_WAKE;
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+                PeekMessageW(&mut msg, None, WM_HOTKEY, WM_HOTKEY, PM_NOREMOVE).as_bool()
+            };
+            if FOREGROUND_CHANGED.swap(false, Ordering::Relaxed) || TITLE_CHANGED.load(Ordering::Relaxed) || hotkey_queued || woken {
+                return;
+            }
+            
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return;
+            }
+            unsafe {
+                MsgWaitForMultipleObjectsEx(None, remaining.as_millis() as u32, QS_ALLINPUT, MWMO_INPUTAVAILABLE);
+            }
+        }
+    }
+}
+
+impl Drop for ForegroundEvents {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = UnhookWinEvent(self.hook);
+            if let Some(names) = self.names {
+                let _ = UnhookWinEvent(names);
+            }
+        }
+    }
+}
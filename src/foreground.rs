@@ -0,0 +1,108 @@
+use std::fmt;
+
+use windows::Win32::{
+    Foundation::HWND,
+    UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_LBUTTON, VK_RBUTTON},
+    UI::Input::KeyboardAndMouse::IsWindowEnabled,
+    UI::WindowsAndMessaging::*,
+};
+
+// Something the user is in the middle of that enforcement should wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    // Dragging something with the primary mouse button held
+    Dragging,
+    // Moving or resizing a window
+    MovingWindow,
+    // A menu (menu bar, popup or system menu) is open
+    Menu,
+    // A modal dialog is blocking its owner window
+    ModalDialog,
+}
+
+impl Interaction {
+    // Menus and dialogs only hold off enforcement briefly; drags until they finish
+    pub fn is_brief(self) -> bool {
+        matches!(self, Interaction::Menu | Interaction::ModalDialog)
+    }
+}
+
+impl fmt::Display for Interaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Interaction::Dragging => "a drag is in progress",
+            Interaction::MovingWindow => "a window is being moved or resized",
+            Interaction::Menu => "a menu is open",
+            Interaction::ModalDialog => "a modal dialog is open",
+        };
+        f.write_str(description)
+    }
+}
+
+// Get the foreground thread's GUI state (active window, focus, menu and move/size flags)
+pub fn foreground_thread_info() -> Option<GUITHREADINFO> {
+    let mut info = GUITHREADINFO {
+        cbSize: std::mem::size_of::<GUITHREADINFO>() as u32,
+        ..Default::default()
+    };
+    // Thread id 0 means the foreground thread
+    unsafe { GetGUIThreadInfo(0, &mut info) }.ok()?;
+    Some(info)
+}
+
+// Get the foreground window from the foreground thread's GUI state,
+// falling back to GetForegroundWindow when it is unavailable
+pub fn foreground_window() -> HWND {
+    foreground_thread_info()
+        .map(|info| info.hwndActive)
+        .filter(|hwnd| !hwnd.is_invalid())
+        .unwrap_or_else(|| unsafe { GetForegroundWindow() })
+}
+
+// Check whether the primary mouse button is physically held down
+fn primary_button_down() -> bool {
+    let swapped = unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0;
+    let button = if swapped { VK_RBUTTON } else { VK_LBUTTON };
+    // The high bit is set while the key is down
+    let state = unsafe { GetAsyncKeyState(button.0 as i32) };
+    state < 0
+}
+
+// Check whether a window is a dialog whose owner is disabled, i.e. modal
+fn is_modal_dialog(hwnd: HWND) -> bool {
+    let mut class_buffer = [0u16; 16];
+    let class_len = unsafe { GetClassNameW(hwnd, &mut class_buffer) };
+    // "#32770" is the system dialog class
+    if String::from_utf16_lossy(&class_buffer[..class_len.max(0) as usize]) != "#32770" {
+        return false;
+    }
+    
+    match unsafe { GetWindow(hwnd, GW_OWNER) } {
+        Ok(owner) if !owner.is_invalid() => !unsafe { IsWindowEnabled(owner).as_bool() },
+        _ => false,
+    }
+}
+
+// Detect an interaction that windows must not disappear from under:
+// a move/size loop or open menu in the foreground thread, a modal dialog,
+// or a drag (DoDragDrop keeps the primary button held until the drop)
+pub fn interaction_in_progress(include_drags: bool, include_menus: bool) -> Option<Interaction> {
+    let info = foreground_thread_info();
+    
+    if include_drags && info.is_some_and(|info| info.flags.contains(GUI_INMOVESIZE)) {
+        return Some(Interaction::MovingWindow);
+    }
+    if include_menus {
+        let menu_flags = GUI_INMENUMODE | GUI_POPUPMENUMODE | GUI_SYSTEMMENUMODE;
+        if info.is_some_and(|info| (info.flags.0 & menu_flags.0) != 0) {
+            return Some(Interaction::Menu);
+        }
+        if info.is_some_and(|info| is_modal_dialog(info.hwndActive)) {
+            return Some(Interaction::ModalDialog);
+        }
+    }
+    if include_drags && primary_button_down() {
+        return Some(Interaction::Dragging);
+    }
+    None
+}
@@ -0,0 +1,148 @@
+// Builds without the tray or ipc features keep these methods as no-ops
+#![cfg_attr(not(all(feature = "tray", feature = "ipc")), allow(unused_variables, unused_mut))]
+
+#[cfg(feature = "tray")]
+use crate::badge::TaskbarBadge;
+use crate::config::MonitorConfig;
+#[cfg(feature = "ipc")]
+use crate::control::ControlPipe;
+#[cfg(feature = "tray")]
+use crate::tray::TrayIcon;
+
+// Something picked from the tray menu or sent over the control pipe, handled
+// by the monitor loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(any(feature = "tray", feature = "ipc")), allow(dead_code))]
+pub enum TrayCommand {
+    Pause,
+    Resume,
+    RestoreAll,
+    Exit,
+}
+
+// Everything the running monitor shows outside the console and takes commands
+// from: the tray icon and taskbar badge (tray feature) and the control pipe of
+// a host app (ipc feature). Minimal builds have none of them.
+#[derive(Default)]
+pub struct Frontends {
+    #[cfg(feature = "tray")]
+    tray: Option<TrayIcon>,
+    #[cfg(feature = "tray")]
+    badge: Option<TaskbarBadge>,
+    #[cfg(feature = "ipc")]
+    pipe: Option<ControlPipe>,
+}
+
+impl Frontends {
+    // Start the ones the configuration asks for. A missing tray icon is only
+    // warned about; a host that embeds the engine can't drive it without its
+    // pipe, so that one is an error.
+    pub fn start(config: &MonitorConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut frontends = Self::default();
+        #[cfg(feature = "tray")]
+        {
+            frontends.badge = config.taskbar_badge.then(TaskbarBadge::new);
+            frontends.tray = if config.tray_icon { TrayIcon::spawn() } else { None };
+            if config.tray_icon && frontends.tray.is_none() {
+                tracing::warn!("⚠ Could not add the notification-area icon, continuing without it");
+            }
+        }
+        #[cfg(feature = "ipc")]
+        if let Some(name) = &config.control_pipe {
+            frontends.pipe = Some(ControlPipe::spawn(name, &config.pipe_tokens).ok_or_else(|| format!("could not create the control pipe {}", name))?);
+        }
+        #[cfg(not(feature = "ipc"))]
+        if let Some(name) = &config.control_pipe {
+            tracing::warn!("⚠ Built without the ipc feature, not listening on the control pipe {}", name);
+        }
+        Ok(frontends)
+    }
+    
+    // Commands picked from the tray menu or received over the pipe since the last call
+    pub fn commands(&self) -> Vec<TrayCommand> {
+        let mut commands = Vec::new();
+        #[cfg(feature = "tray")]
+        commands.extend(self.tray.iter().flat_map(TrayIcon::commands));
+        #[cfg(feature = "ipc")]
+        commands.extend(self.pipe.iter().flat_map(ControlPipe::commands));
+        commands
+    }
+    
+    // Reflect the pause state in the tray menu and the pipe's `status`
+    pub fn set_paused(&self, paused: bool) {
+        #[cfg(feature = "tray")]
+        if let Some(tray) = &self.tray {
+            tray.set_paused(paused);
+        }
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_paused(paused);
+        }
+    }
+    
+    // Report the features that failed to start in the tray tooltip and the pipe's `status`
+    pub fn set_degraded(&self, reasons: &[String]) {
+        #[cfg(feature = "tray")]
+        if let Some(tray) = &self.tray {
+            tray.set_degraded(reasons);
+        }
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_degraded(reasons);
+        }
+    }
+    
+    // Show a nudge or budget notice from the tray icon and queue it for the host
+    pub fn notify(&self, app_name: &str, text: &str) {
+        #[cfg(feature = "tray")]
+        if let Some(tray) = &self.tray {
+            tray.notify(app_name, text);
+        }
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.notify(text);
+        }
+    }
+    
+    // Update the suppressed count on the taskbar
+    pub fn set_suppressed_count(&mut self, count: usize) {
+        #[cfg(feature = "tray")]
+        if let Some(badge) = self.badge.as_mut() {
+            badge.set_count(count);
+        }
+    }
+    
+    // Update how many windows the pipe's `unreachable` reports
+    pub fn set_unreachable_count(&self, count: usize) {
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_unreachable(count);
+        }
+    }
+    
+    // Update the session ignores the pipe's `status` reports
+    pub fn set_ignored(&self, titles: &[String]) {
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_ignored(titles);
+        }
+    }
+    
+    // Update the decision latency the pipe's `status` reports
+    #[cfg(feature = "stats")]
+    pub fn set_decision_latency(&self, summary: &str) {
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_decision_latency(summary);
+        }
+    }
+    
+    // Update what the pipe's `metrics` reports
+    #[cfg(feature = "stats")]
+    pub fn set_metrics(&self, metrics: &str) {
+        #[cfg(feature = "ipc")]
+        if let Some(pipe) = &self.pipe {
+            pipe.set_metrics(metrics);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use windows::Win32::{
+    Foundation::{HWND, RECT},
+    Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS},
+    Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTONULL},
+    UI::HiDpi::{
+        GetDpiForWindow, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
+        DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+    },
+    UI::Shell::{SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN},
+    UI::WindowsAndMessaging::{
+        GetClassNameW, GetForegroundWindow, GetShellWindow, GetWindowLongW, GetWindowRect, IsIconic, IsZoomed, GWL_STYLE,
+        WS_CAPTION, WS_THICKFRAME,
+    },
+};
+
+// DPI every pixel constant in the geometry code is written for
+const BASE_DPI: u32 = 96;
+
+// Make the process per-monitor DPI aware, so window rects, DWM frame bounds
+// and monitor work areas all come back in the same physical pixels. Without
+// it, GetWindowRect and the monitor APIs are virtualized on scaled monitors
+// while DWM bounds are not, and the comparisons are off on mixed-DPI setups.
+pub fn enable_per_monitor_dpi_awareness() {
+    unsafe {
+        if SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2).is_err() {
+            // Windows 10 before 1703 only knows the first version
+            let _ = SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE);
+        }
+    }
+}
+
+// Scale a distance in 96-DPI pixels to the DPI of the monitor a window is on
+pub fn scale_for_window(pixels: i32, hwnd: HWND) -> i32 {
+    let dpi = match unsafe { GetDpiForWindow(hwnd) } {
+        0 => BASE_DPI,
+        dpi => dpi,
+    };
+    (pixels as i64 * dpi as i64 / BASE_DPI as i64) as i32
+}
+
+// Get the visible bounds of a window, without the invisible resize borders
+// that GetWindowRect includes on Windows 10 and later
+pub fn visible_frame_rect(hwnd: HWND) -> Option<RECT> {
+    let mut rect = RECT::default();
+    let dwm_result = unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut rect as *mut _ as *mut core::ffi::c_void,
+            std::mem::size_of::<RECT>() as u32,
+        )
+    };
+    if dwm_result.is_ok() {
+        return Some(rect);
+    }
+    
+    unsafe { GetWindowRect(hwnd, &mut rect) }.ok()?;
+    Some(rect)
+}
+
+// Whether DWM cloaks a window: it passes IsWindowVisible but isn't drawn, like
+// suspended UWP apps, ApplicationFrameHost leftovers and windows on other
+// virtual desktops
+pub fn is_cloaked(hwnd: HWND) -> bool {
+    let mut cloaked = 0u32;
+    let result = unsafe {
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut _ as *mut core::ffi::c_void,
+            std::mem::size_of::<u32>() as u32,
+        )
+    };
+    result.is_ok() && cloaked != 0
+}
+
+// Get the work area (excluding the taskbar) of the monitor a window is on
+pub fn monitor_work_area(hwnd: HWND) -> Option<RECT> {
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let found = unsafe { GetMonitorInfoW(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST), &mut info) };
+    found.as_bool().then_some(info.rcWork)
+}
+
+// Whether a fullscreen app is in front: a Direct3D exclusive fullscreen game
+// or presentation mode, as the shell reports them, or a borderless window
+// covering its whole monitor. The desktop covers its monitor too, so the
+// shell's own windows don't count, and neither do resizable or maximized
+// windows that merely hide their caption (browsers, editors).
+pub fn is_fullscreen_app_active() -> bool {
+    let state = unsafe { SHQueryUserNotificationState() };
+    if matches!(state, Ok(QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE | QUNS_BUSY)) {
+        return true;
+    }
+    
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_invalid() || hwnd == unsafe { GetShellWindow() } {
+        return false;
+    }
+    let mut class = [0u16; 16];
+    let len = unsafe { GetClassNameW(hwnd, &mut class) };
+    let class = String::from_utf16_lossy(&class[..len.max(0) as usize]);
+    let style = unsafe { GetWindowLongW(hwnd, GWL_STYLE) } as u32;
+    if class == "WorkerW"
+        || class == "Progman"
+        || style & WS_CAPTION.0 == WS_CAPTION.0
+        || style & WS_THICKFRAME.0 != 0
+        || unsafe { IsZoomed(hwnd) }.as_bool()
+    {
+        return false;
+    }
+    
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !unsafe { GetMonitorInfoW(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST), &mut info) }.as_bool() {
+        return false;
+    }
+    let mut rect = RECT::default();
+    if unsafe { GetWindowRect(hwnd, &mut rect) }.is_err() {
+        return false;
+    }
+    let monitor = info.rcMonitor;
+    rect.left <= monitor.left && rect.top <= monitor.top && rect.right >= monitor.right && rect.bottom >= monitor.bottom
+}
+
+// Find the candidates shown on a different monitor than the target. Minimized
+// windows aren't on any monitor, so they never count as elsewhere.
+pub fn on_other_monitors(target: HWND, candidates: &[HWND]) -> Vec<HWND> {
+    let monitor = unsafe { MonitorFromWindow(target, MONITOR_DEFAULTTONEAREST) };
+    candidates
+        .iter()
+        .copied()
+        .filter(|&hwnd| hwnd != target && unsafe { !IsIconic(hwnd).as_bool() })
+        .filter(|&hwnd| {
+            let other = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) };
+            !other.is_invalid() && other != monitor
+        })
+        .collect()
+}
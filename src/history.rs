@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::data_dir;
+use crate::restore_list::WindowIdentity;
+
+// Focus changes are written out at most this often
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Windows remembered at most; the longest-unfocused are forgotten first
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusRecord {
+    #[serde(flatten)]
+    pub window: WindowIdentity,
+    // Seconds since the Unix epoch
+    pub last_focused: u64,
+}
+
+// When each window (by fingerprint, see WindowIdentity) last had focus, kept
+// across runs in focus-history.json
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FocusHistory {
+    pub windows: Vec<FocusRecord>,
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    last_save: Option<Instant>,
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn history_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("focus-history.json"))
+}
+
+impl FocusHistory {
+    // Load the stored history, empty if there is none
+    pub fn load() -> Self {
+        history_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    
+    pub fn save(&mut self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let path = history_path().ok_or("LOCALAPPDATA is not set")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+    
+    // Save pending focus changes unless the last save was recent
+    pub fn save_if_due(&mut self) {
+        if self.dirty
+            && self.last_save.is_none_or(|at| at.elapsed() >= SAVE_INTERVAL)
+            && let Err(e) = self.save()
+        {
+            warn!("Could not save the focus history: {}", e);
+            // Don't retry on every pass
+            self.last_save = Some(Instant::now());
+        }
+    }
+    
+    // Note that a window has focus now
+    pub fn focused(&mut self, window: WindowIdentity) {
+        let now = now_secs();
+        match self.windows.iter_mut().find(|record| record.window.same_window(&window)) {
+            Some(record) => record.last_focused = now,
+            None => {
+                self.windows.push(FocusRecord { window, last_focused: now });
+                if self.windows.len() > MAX_ENTRIES {
+                    self.windows.sort_by_key(|record| std::cmp::Reverse(record.last_focused));
+                    self.windows.truncate(MAX_ENTRIES);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+    
+    // When a window last had focus, if it was ever seen focused
+    pub fn last_focused(&self, window: &WindowIdentity) -> Option<u64> {
+        self.windows
+            .iter()
+            .find(|record| record.window.same_window(window))
+            .map(|record| record.last_focused)
+    }
+}
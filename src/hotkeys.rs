@@ -0,0 +1,80 @@
+use windows::Win32::{
+    UI::Input::KeyboardAndMouse::*,
+    UI::WindowsAndMessaging::{PeekMessageW, MSG, PM_REMOVE, WM_HOTKEY},
+};
+
+// Hotkey ids registered by the monitor
+pub const HOTKEY_IGNORE_FOR_SESSION: i32 = 1;
+pub const HOTKEY_IGNORE_ONCE: i32 = 2;
+pub const HOTKEY_RESTORE_ALL: i32 = 3;
+pub const HOTKEY_OPACITY_UP: i32 = 4;
+pub const HOTKEY_OPACITY_DOWN: i32 = 5;
+pub const HOTKEY_OPACITY_PRESET: i32 = 6;
+pub const HOTKEY_COMMAND_PALETTE: i32 = 7;
+// Quick-switch slots use consecutive ids starting here
+pub const HOTKEY_QUICK_SWITCH_FIRST: i32 = 11;
+// Used briefly to check whether a hotkey can be registered
+const HOTKEY_PROBE: i32 = 100;
+
+// Parse a hotkey such as "Ctrl+Alt+S" or "Win+Shift+F12" into modifiers and a virtual key
+pub fn parse_hotkey(text: &str) -> Option<(HOT_KEY_MODIFIERS, u32)> {
+    let mut modifiers = HOT_KEY_MODIFIERS(0);
+    let mut key = None;
+    
+    for part in text.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "win" | "super" => modifiers |= MOD_WIN,
+            "space" => key = Some(VK_SPACE.0 as u32),
+            "plus" => key = Some(VK_OEM_PLUS.0 as u32),
+            "minus" => key = Some(VK_OEM_MINUS.0 as u32),
+            "up" => key = Some(VK_UP.0 as u32),
+            "down" => key = Some(VK_DOWN.0 as u32),
+            name => key = Some(parse_key(name)?),
+        }
+    }
+    key.map(|key| (modifiers, key))
+}
+
+// Parse a single key name: a letter, a digit or F1-F24
+fn parse_key(name: &str) -> Option<u32> {
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => Some(c as u32),
+        (Some('F'), Some(_)) => {
+            let number: u32 = upper[1..].parse().ok()?;
+            (1..=24).contains(&number).then(|| VK_F1.0 as u32 + number - 1)
+        }
+        _ => None,
+    }
+}
+
+// Register a global hotkey for the current thread; WM_HOTKEY is posted to its queue
+pub fn register_hotkey(id: i32, text: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (modifiers, key) = parse_hotkey(text).ok_or_else(|| format!("invalid hotkey '{}'", text))?;
+    unsafe { RegisterHotKey(None, id, modifiers | MOD_NOREPEAT, key)? };
+    Ok(())
+}
+
+// Check whether a hotkey could be registered right now, by registering and
+// releasing it again
+pub fn probe_hotkey(text: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    register_hotkey(HOTKEY_PROBE, text)?;
+    unsafe { UnregisterHotKey(None, HOTKEY_PROBE)? };
+    Ok(())
+}
+
+// Collect the ids of hotkeys pressed since the last call
+pub fn pending_hotkeys() -> Vec<i32> {
+    let mut pressed = Vec::new();
+    let mut msg = MSG::default();
+    unsafe {
+        while PeekMessageW(&mut msg, None, WM_HOTKEY, WM_HOTKEY, PM_REMOVE).as_bool() {
+            pressed.push(msg.wParam.0 as i32);
+        }
+    }
+    pressed
+}
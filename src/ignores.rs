@@ -0,0 +1,95 @@
+use std::fmt;
+
+use tracing::info;
+use windows::Win32::Foundation::HWND;
+
+use crate::restore_list::WindowIdentity;
+use crate::WindowInfo;
+
+// How long an ignore lasts. Permanent ignores are the configured keywords;
+// the other scopes are added at runtime through hotkeys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreScope {
+    // Until the focus session ends (focus leaves the targets, everything
+    // is restored, or the monitor exits)
+    Session,
+    // Only for the next enforcement pass
+    Once,
+}
+
+impl fmt::Display for IgnoreScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            IgnoreScope::Session => "until the session ends",
+            IgnoreScope::Once => "once",
+        };
+        f.write_str(description)
+    }
+}
+
+// Windows ignored at runtime, by scope. Session ignores also cover the
+// window's fingerprint, so they hold when its app restarts.
+#[derive(Default)]
+pub struct ScopedIgnores {
+    session: Vec<(HWND, WindowIdentity, String)>,
+    once: Vec<(HWND, String)>,
+    // Whether the session ignores changed since take_changed
+    changed: bool,
+}
+
+impl ScopedIgnores {
+    pub fn add(&mut self, scope: IgnoreScope, window: &WindowInfo) {
+        match scope {
+            IgnoreScope::Session => {
+                if self.scope_of(window) != Some(IgnoreScope::Session) {
+                    self.session.push((window.hwnd, WindowIdentity::of(window), window.title.clone()));
+                    self.changed = true;
+                }
+            }
+            IgnoreScope::Once => {
+                if !self.once.iter().any(|(existing, _)| *existing == window.hwnd) {
+                    self.once.push((window.hwnd, window.title.clone()));
+                }
+            }
+        }
+    }
+    
+    // Scope under which a window is ignored, if any
+    pub fn scope_of(&self, window: &WindowInfo) -> Option<IgnoreScope> {
+        if !self.session.is_empty() {
+            let identity = WindowIdentity::of(window);
+            if self.session.iter().any(|(hwnd, existing, _)| *hwnd == window.hwnd || existing.same_window(&identity)) {
+                return Some(IgnoreScope::Session);
+            }
+        }
+        self.once.iter().any(|(existing, _)| *existing == window.hwnd).then_some(IgnoreScope::Once)
+    }
+    
+    // Forget the once-only ignores after an enforcement pass
+    pub fn end_pass(&mut self) {
+        self.once.clear();
+    }
+    
+    // Forget every runtime ignore once the focus session ends
+    pub fn end_session(&mut self) {
+        self.changed |= !self.session.is_empty();
+        self.session.clear();
+        self.once.clear();
+    }
+    
+    // Titles of the windows ignored until the session ends, if they changed
+    // since the last call
+    pub fn take_changed(&mut self) -> Option<Vec<String>> {
+        std::mem::take(&mut self.changed).then(|| self.session.iter().map(|(_, _, title)| title.clone()).collect())
+    }
+    
+    // Print every ignore scope, including the permanent configured keywords
+    pub fn print_status(&self, permanent: &[String]) {
+        let session: Vec<&String> = self.session.iter().map(|(_, _, title)| title).collect();
+        let once: Vec<&String> = self.once.iter().map(|(_, title)| title).collect();
+        info!("Ignore scopes:");
+        info!("  permanent: {:?}", permanent);
+        info!("  session:   {:?}", session);
+        info!("  once:      {:?}\n", once);
+    }
+}
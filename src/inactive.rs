@@ -0,0 +1,148 @@
+use std::thread;
+use std::time::Instant;
+
+use tracing::{info, warn};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{GetAncestor, GetForegroundWindow, IsIconic, GA_ROOTOWNER};
+
+use crate::config::MonitorConfig;
+use crate::desktop::is_input_desktop_accessible;
+use crate::events::ForegroundEvents;
+use crate::frontend::{Frontends, TrayCommand};
+use crate::geometry::is_fullscreen_app_active;
+use crate::process::ProcessNameCache;
+use crate::remote::{current_session_kind, SessionKind};
+use crate::rules::compile_keywords;
+use crate::{dim, get_all_windows_uncached, is_system_window, shadow, shutdown, WindowInfo};
+
+// The gentler operating mode: nothing is minimized, every window but the
+// focused one (and the window owning it) is dimmed to `opacity` instead and
+// back to full opacity once it gets focus. Ignored windows are never dimmed;
+// target rules don't apply. Like enforcement, it only reports what it would
+// dim in shadow mode or a dry run, and leaves opacity alone in remote sessions
+// and while a secure desktop has input.
+pub fn run_dim_inactive(config: &MonitorConfig, opacity: u8) -> Result<(), Box<dyn std::error::Error>> {
+    let ignored = compile_keywords(&config.ignored_keywords)?;
+    dim::set_animation_duration(config.opacity_animation);
+    dim::install_exit_handler();
+    // Runs before the handler above, undimming everything on Ctrl+C
+    shutdown::install();
+    let events = if config.foreground_events { ForegroundEvents::install() } else { None };
+    let frontends = Frontends::start(config)?;
+    let mut process_names = ProcessNameCache::default();
+    // Windows dimmed, or with observe_only the ones that would be
+    let mut dimmed: Vec<HWND> = Vec::new();
+    let mut last_session_kind = SessionKind::default();
+    let mut paused = false;
+    // Whether dimming is held off for a fullscreen app in front
+    let mut fullscreen_paused = false;
+    let mut last_active: Option<HWND> = None;
+    // New windows opening in the background are dimmed at the next refresh
+    let mut last_refresh = Instant::now();
+    
+    loop {
+        if shutdown::requested() {
+            info!("Stopping, undimming every window");
+            dim::undim_all();
+            shutdown::finished();
+            return Ok(());
+        }
+        
+        for command in frontends.commands() {
+            match command {
+                // Restoring everything would only last until the next refresh,
+                // so it pauses dimming too
+                TrayCommand::Pause | TrayCommand::Resume | TrayCommand::RestoreAll => {
+                    paused = command != TrayCommand::Resume;
+                    info!("{}\n", if paused { "Dimming paused, every window undimmed" } else { "Dimming resumed" });
+                    frontends.set_paused(paused);
+                    dim::unfade(&std::mem::take(&mut dimmed));
+                    last_active = None;
+                }
+                TrayCommand::Exit => {
+                    info!("Exiting on request");
+                    dim::undim_all();
+                    return Ok(());
+                }
+            }
+        }
+        
+        // Leave every window as it is while a game or other fullscreen app
+        // is in front, like enforcement does
+        let fullscreen = config.pause_when_fullscreen && is_fullscreen_app_active();
+        if fullscreen != fullscreen_paused {
+            info!("{}\n", if fullscreen { "Fullscreen app in front, pausing dimming" } else { "Fullscreen app gone, dimming resumed" });
+            fullscreen_paused = fullscreen;
+            last_active = None;
+        }
+        
+        // Opacity effects are off in remote sessions
+        let session_kind = current_session_kind();
+        if session_kind != last_session_kind {
+            if session_kind.is_remote() {
+                info!("Running in a {}, every window undimmed\n", session_kind);
+                dim::unfade(&std::mem::take(&mut dimmed));
+            } else {
+                info!("Back on the {}, dimming resumed\n", session_kind);
+            }
+            last_session_kind = session_kind;
+            last_active = None;
+        }
+        
+        // Secure desktops (UAC prompt, lock screen) can't be looked at; check
+        // again once they are gone
+        let accessible = is_input_desktop_accessible();
+        if !accessible {
+            last_active = None;
+        }
+        
+        let active = unsafe { GetForegroundWindow() };
+        if !paused
+            && !fullscreen_paused
+            && !session_kind.is_remote()
+            && accessible
+            && (last_active != Some(active) || last_refresh.elapsed() >= config.housekeeping_interval)
+        {
+            last_active = Some(active);
+            last_refresh = Instant::now();
+            // A focused dialog keeps the window it belongs to bright too
+            let owner = unsafe { GetAncestor(active, GA_ROOTOWNER) };
+            let windows = get_all_windows_uncached(config.include_owned_windows, &mut process_names)?;
+            let to_dim: Vec<&WindowInfo> = windows
+                .iter()
+                .filter(|window| window.hwnd != active && window.hwnd != owner && !is_system_window(window))
+                .filter(|window| unsafe { !IsIconic(window.hwnd).as_bool() })
+                .filter(|window| !ignored.iter().any(|keyword| keyword.is_match(&window.title_lower)))
+                .collect();
+            
+            if config.observe_only() {
+                let newly: Vec<&WindowInfo> = to_dim.iter().copied().filter(|window| !dimmed.contains(&window.hwnd)).collect();
+                for window in &newly {
+                    info!("  · Would dim: {}", window.title);
+                }
+                if config.shadow_mode
+                    && !newly.is_empty()
+                    && let Err(e) = shadow::record("dim-inactive", "dim", &newly)
+                {
+                    warn!("Could not record shadow decisions: {}", e);
+                }
+                dimmed = to_dim.iter().map(|window| window.hwnd).collect();
+            } else {
+                let (still_dimmed, to_undim): (Vec<HWND>, Vec<HWND>) =
+                    dimmed.drain(..).partition(|hwnd| to_dim.iter().any(|window| window.hwnd == *hwnd));
+                dim::unfade(&to_undim);
+                dimmed = still_dimmed;
+                for window in to_dim {
+                    if !dimmed.contains(&window.hwnd) && dim::fade(window.hwnd, opacity) {
+                        dimmed.push(window.hwnd);
+                    }
+                }
+            }
+        }
+        
+        match &events {
+            Some(events) => events.wait(config.housekeeping_interval),
+            None => thread::sleep(config.poll_interval),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::MonitorConfig;
+use crate::WindowInfo;
+
+// Attempts are counted over this rolling window
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+// Per app: the target rule that suppressed it, and when it was focused since
+struct BlockedApp {
+    rule: String,
+    attempts: Vec<Instant>,
+    nudged: Option<Instant>,
+}
+
+// Counts how often apps the tool suppressed get focused again, so a gentle
+// notification can point out a pattern ("You've opened Slack 5 times this hour")
+#[derive(Default)]
+pub struct InterruptionStats {
+    apps: HashMap<String, BlockedApp>,
+}
+
+// Function to name an app the way the user knows it
+fn display_name(window: &WindowInfo) -> String {
+    if !window.process_version.file_description.is_empty() {
+        return window.process_version.file_description.clone();
+    }
+    window.process_name.trim_end_matches(".exe").to_string()
+}
+
+impl InterruptionStats {
+    // A window of this app was minimized while the given target was active
+    pub fn blocked(&mut self, window: &WindowInfo, rule: &str) {
+        let app = self.apps.entry(window.process_name.to_lowercase()).or_insert_with(|| BlockedApp {
+            rule: rule.to_string(),
+            attempts: Vec::new(),
+            nudged: None,
+        });
+        app.rule = rule.to_string();
+    }
+    
+    // A non-target window got focus. Returns the message to show when a
+    // suppressed app reaches its rule's attempt threshold, at most once an hour.
+    pub fn focused(&mut self, window: &WindowInfo, config: &MonitorConfig) -> Option<String> {
+        let app = self.apps.get_mut(&window.process_name.to_lowercase())?;
+        let now = Instant::now();
+        app.attempts.retain(|at| now.duration_since(*at) < WINDOW);
+        app.attempts.push(now);
+        
+        let threshold = config.nudge_after_for(&app.rule)?;
+        let recently_nudged = app.nudged.is_some_and(|at| now.duration_since(at) < WINDOW);
+        if app.attempts.len() < threshold as usize || recently_nudged {
+            return None;
+        }
+        app.nudged = Some(now);
+        Some(format!("You've opened {} {} times this hour", display_name(window), app.attempts.len()))
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Most recent measurements kept for percentiles
+const SAMPLES: usize = 1000;
+
+// Running statistics of how long enforcement decisions take
+#[derive(Default)]
+pub struct LatencyStats {
+    count: u32,
+    total: Duration,
+    max: Duration,
+    recent: VecDeque<Duration>,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+        if self.recent.len() == SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+    }
+    
+    // Function to get the given percentile (0-100) of the recent measurements
+    fn percentile(&self, percentile: f64) -> Duration {
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let Some(last) = sorted.len().checked_sub(1) else { return Duration::ZERO };
+        sorted[((last as f64) * percentile / 100.0).round() as usize]
+    }
+    
+    // Percentiles of the recent measurements, e.g. "p50 12.1 ms, p90 30.4 ms, p99 61.0 ms, max 75.2 ms over 40 passes"
+    pub fn distribution(&self) -> String {
+        format!(
+            "p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms over {} passes",
+            ms(self.percentile(50.0)),
+            ms(self.percentile(90.0)),
+            ms(self.percentile(99.0)),
+            ms(self.max),
+            self.count
+        )
+    }
+    
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count
+        }
+    }
+    
+    // One-line summary, e.g. "0.412 ms (avg 0.380 ms, max 1.204 ms over 12 decisions)"
+    pub fn summary(&self, last: Duration) -> String {
+        format!(
+            "{:.3} ms (avg {:.3} ms, max {:.3} ms over {} decisions)",
+            ms(last),
+            ms(self.average()),
+            ms(self.max),
+            self.count
+        )
+    }
+}
@@ -0,0 +1,2169 @@
+// Window management behind fak-opacity: enumerating top-level windows,
+// matching them against target and ignored keyword rules, acting on them
+// (minimize, fade, restore) and the monitoring loop that ties it together.
+// The fak-opacity binary is a thin command line over this crate.
+
+pub mod audit;
+mod audio;
+pub mod autostart;
+#[cfg(feature = "tray")]
+mod badge;
+mod budgets;
+pub mod build_info;
+pub mod clutter;
+mod com;
+pub mod config;
+pub mod config_watch;
+pub mod conflicts;
+#[cfg(feature = "ipc")]
+pub mod control;
+pub mod decisions;
+mod desktop;
+mod dim;
+pub mod doctor;
+mod escalation;
+mod events;
+mod foreground;
+mod frontend;
+pub mod geometry;
+mod history;
+mod hotkeys;
+mod ignores;
+mod inactive;
+#[cfg(feature = "stats")]
+mod interruptions;
+#[cfg(feature = "stats")]
+mod latency;
+mod limits;
+mod logon;
+pub mod logging;
+mod opacity;
+mod overlay;
+mod palette;
+mod placement;
+mod power;
+pub mod process;
+mod quick_switch;
+mod rate_limit;
+mod remote;
+pub mod repl;
+pub mod restore_list;
+pub mod rules;
+pub mod secrets;
+pub mod selftest;
+#[cfg(feature = "service")]
+pub mod service;
+mod session;
+pub mod shadow;
+mod sharing;
+mod shutdown;
+mod signature;
+mod snap;
+mod subscriptions;
+mod switcher;
+pub mod task;
+#[cfg(feature = "tray")]
+mod tray;
+#[cfg(feature = "update")]
+pub mod update;
+mod virtual_desktops;
+pub mod watch;
+mod window_ages;
+
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, error, info, warn};
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use budgets::{BudgetChange, FocusBudgets};
+use config::{FocusBudget, MonitorConfig, ProcessException, SuppressAction, TargetResolution};
+use config_watch::{ConfigSource, ConfigWatcher};
+use decisions::DecisionCache;
+use escalation::{EscalationCounters, EscalationLevel};
+use events::ForegroundEvents;
+use history::FocusHistory;
+use limits::FocusRecency;
+use logon::LogonSettle;
+use desktop::{is_input_desktop_accessible, user_idle_time};
+use dim::IdleDimmer;
+use foreground::{foreground_window, interaction_in_progress, Interaction};
+use frontend::{Frontends, TrayCommand};
+use geometry::{is_fullscreen_app_active, on_other_monitors};
+use opacity::{step_opacity, OpacityPresets};
+use placement::{move_to_monitor, restore_placement, CursorFollower};
+use power::{current_power_state, PowerState};
+use hotkeys::{
+    pending_hotkeys, register_hotkey, HOTKEY_COMMAND_PALETTE, HOTKEY_IGNORE_FOR_SESSION, HOTKEY_IGNORE_ONCE, HOTKEY_OPACITY_DOWN,
+    HOTKEY_OPACITY_PRESET, HOTKEY_OPACITY_UP, HOTKEY_QUICK_SWITCH_FIRST, HOTKEY_RESTORE_ALL,
+};
+use ignores::{IgnoreScope, ScopedIgnores};
+#[cfg(feature = "stats")]
+use interruptions::InterruptionStats;
+#[cfg(feature = "stats")]
+use latency::LatencyStats;
+use process::{pin_working_set, process_image_path, process_name, process_thread_ids, running_processes, window_process_id, ProcessNameCache, VersionStrings};
+use palette::{CommandPalette, PaletteCommand};
+use quick_switch::QuickSwitch;
+use rate_limit::RateLimiter;
+use remote::{current_session_kind, SessionKind};
+use restore_list::{RestoreList, WindowIdentity};
+use rules::{compile_keywords, print_profiles, profile_keywords, KeywordMatcher};
+use session::{Effect, FocusSession};
+use audio::playing_processes;
+use sharing::shared_windows;
+use signature::PublisherCache;
+use snap::snap_group_members;
+use subscriptions::Event;
+use virtual_desktops::on_other_desktops;
+use window_ages::WindowAges;
+
+// Structure to store window information
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+    pub hwnd: HWND,
+    pub title: String,
+    // Lowercased once at enumeration so matching doesn't allocate
+    pub title_lower: String,
+    pub class_name: String,
+    pub process_id: u32,
+    pub process_name: String,
+    // Full path of the owning executable, empty when it can't be read
+    pub process_path: String,
+    // FileDescription / ProductName of the owning executable
+    pub process_version: VersionStrings,
+    // Whether the owning process runs elevated
+    pub process_elevated: bool,
+}
+
+impl WindowInfo {
+    // Whether a process rule names this window's process, by exe file name
+    // ("Code.exe") or by its description or product name ("Visual Studio Code")
+    pub fn matches_process(&self, rule: &str) -> bool {
+        [&self.process_name, &self.process_version.file_description, &self.process_version.product_name]
+            .iter()
+            .any(|name| !name.is_empty() && name.eq_ignore_ascii_case(rule))
+    }
+}
+
+// Enumerator of the listable windows, re-enumerating at most once per cache_duration
+pub struct WindowEnumerator {
+    windows: Vec<WindowInfo>,
+    last_update: Instant,
+    cache_duration: Duration,
+    include_owned_windows: bool,
+    enumeration_processes: Vec<String>,
+    process_names: ProcessNameCache,
+}
+
+impl WindowEnumerator {
+    pub fn new(config: &MonitorConfig) -> Self {
+        Self {
+            windows: Vec::new(),
+            last_update: Instant::now() - Duration::from_secs(1), // Force initial update
+            cache_duration: config.cache_duration,
+            include_owned_windows: config.include_owned_windows,
+            enumeration_processes: config.enumeration_processes.clone(),
+            process_names: ProcessNameCache::default(),
+        }
+    }
+    
+    pub fn get_windows(&mut self) -> std::result::Result<&Vec<WindowInfo>, Box<dyn std::error::Error>> {
+        if self.last_update.elapsed() > self.cache_duration {
+            self.windows = if self.enumeration_processes.is_empty() {
+                get_all_windows_uncached(self.include_owned_windows, &mut self.process_names)?
+            } else {
+                get_process_windows_uncached(&self.enumeration_processes, self.include_owned_windows, &mut self.process_names)
+            };
+            self.last_update = Instant::now();
+        }
+        Ok(&self.windows)
+    }
+}
+
+// State passed through EnumWindows to the callback
+struct EnumContext<'a> {
+    windows: Vec<WindowInfo>,
+    include_owned_windows: bool,
+    process_names: &'a mut ProcessNameCache,
+}
+
+// Function to check if a window is a top-level window (parented to the desktop)
+fn is_top_level_window(hwnd: HWND, include_owned_windows: bool) -> bool {
+    unsafe {
+        if GetAncestor(hwnd, GA_PARENT) != GetDesktopWindow() {
+            return false;
+        }
+        
+        // Owned top-levels (tool palettes, app windows) are only kept when requested
+        let owner = GetWindow(hwnd, GW_OWNER).unwrap_or(HWND(std::ptr::null_mut()));
+        include_owned_windows || owner == HWND(std::ptr::null_mut())
+    }
+}
+
+// Function to read the information of a listable window: visible, top-level and titled
+pub fn window_info(hwnd: HWND, include_owned_windows: bool, process_names: &mut ProcessNameCache) -> Option<WindowInfo> {
+    // Only get visible windows that are not child windows
+    if !unsafe { IsWindowVisible(hwnd).as_bool() } || !is_top_level_window(hwnd, include_owned_windows) {
+        return None;
+    }
+    
+    let mut title_buffer = [0u16; 256];
+    let mut class_buffer = [0u16; 256];
+    
+    let title_len = unsafe { GetWindowTextW(hwnd, &mut title_buffer) };
+    let class_len = unsafe { GetClassNameW(hwnd, &mut class_buffer) };
+    
+    if title_len <= 0 {
+        return None;
+    }
+    
+    let title = OsString::from_wide(&title_buffer[..title_len as usize])
+        .to_string_lossy()
+        .to_string();
+    let class_name = OsString::from_wide(&class_buffer[..class_len as usize])
+        .to_string_lossy()
+        .to_string();
+    
+    let process_id = window_process_id(hwnd);
+    let process = process_names.get(hwnd, process_id);
+    
+    Some(WindowInfo {
+        hwnd,
+        title_lower: title.to_lowercase(),
+        title,
+        class_name,
+        process_id,
+        process_name: process.name,
+        process_path: process.path,
+        process_version: process.version,
+        process_elevated: process.elevated,
+    })
+}
+
+// Callback function for EnumWindows
+unsafe extern "system" fn enum_windows_proc(
+    hwnd: HWND,
+    lparam: LPARAM,
+) -> BOOL {
+    let context = unsafe { &mut *(lparam.0 as *mut EnumContext) };
+    
+    // Cloaked windows only look visible; minimizing them would act on ghosts
+    if geometry::is_cloaked(hwnd) {
+        return TRUE;
+    }
+    
+    if let Some(window) = window_info(hwnd, context.include_owned_windows, context.process_names) {
+        context.windows.push(window);
+    }
+    
+    TRUE
+}
+
+// Function to get all open windows (uncached)
+pub fn get_all_windows_uncached(
+    include_owned_windows: bool,
+    process_names: &mut ProcessNameCache,
+) -> std::result::Result<Vec<WindowInfo>, Box<dyn std::error::Error>> {
+    let mut context = EnumContext {
+        windows: Vec::with_capacity(50), // Pre-allocate for better performance
+        include_owned_windows,
+        process_names,
+    };
+    
+    unsafe {
+        EnumWindows(
+            Some(enum_windows_proc),
+            LPARAM(&mut context as *mut _ as isize),
+        )?;
+    }
+    
+    let alive: Vec<(HWND, u32)> = context.windows.iter().map(|w| (w.hwnd, w.process_id)).collect();
+    context.process_names.retain_windows(&alive);
+    
+    Ok(context.windows)
+}
+
+// Function to get the windows of the named processes only, enumerating each of
+// their threads instead of every window on the desktop
+pub fn get_process_windows_uncached(
+    process_names_to_scan: &[String],
+    include_owned_windows: bool,
+    process_names: &mut ProcessNameCache,
+) -> Vec<WindowInfo> {
+    let process_ids: Vec<u32> = running_processes()
+        .into_iter()
+        .filter(|(_, name)| process_names_to_scan.iter().any(|scan| scan.eq_ignore_ascii_case(name)))
+        .map(|(process_id, _)| process_id)
+        .collect();
+    
+    let mut context = EnumContext {
+        windows: Vec::new(),
+        include_owned_windows,
+        process_names,
+    };
+    
+    for thread_id in process_thread_ids(&process_ids) {
+        unsafe {
+            // Returns FALSE for threads without windows, which is fine
+            let _ = EnumThreadWindows(
+                thread_id,
+                Some(enum_windows_proc),
+                LPARAM(&mut context as *mut _ as isize),
+            );
+        }
+    }
+    
+    let alive: Vec<(HWND, u32)> = context.windows.iter().map(|w| (w.hwnd, w.process_id)).collect();
+    context.process_names.retain_windows(&alive);
+    
+    context.windows
+}
+
+// Function to minimize window
+pub fn minimize_window(hwnd: HWND) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    unsafe {
+        let _ = ShowWindow(hwnd, SW_MINIMIZE);
+    }
+    Ok(())
+}
+
+// Function to restore a minimized window, optionally leaving the focus where it is
+pub fn restore_window(hwnd: HWND, activate: bool) {
+    unsafe {
+        let _ = ShowWindow(hwnd, if activate { SW_RESTORE } else { SW_SHOWNOACTIVATE });
+    }
+}
+
+// Restore of the last session's windows running in the background, and whether
+// it should restore what's left without waiting out the stagger
+static RESTORE_THREAD: Mutex<Option<thread::JoinHandle<()>>> = Mutex::new(None);
+static RESTORE_HURRY: AtomicBool = AtomicBool::new(false);
+
+// Function to order windows bottom of the current z-order first, so restoring
+// them in turn brings the stacking back as it was. Windows no longer in the
+// z-order go last.
+fn sort_bottom_first(windows: &mut [(HWND, String)]) {
+    let mut z_order: HashMap<usize, usize> = HashMap::new();
+    let mut next = unsafe { GetTopWindow(None) }.ok();
+    while let Some(hwnd) = next {
+        z_order.insert(hwnd.0 as usize, z_order.len());
+        next = unsafe { GetWindow(hwnd, GW_HWNDNEXT) }.ok();
+    }
+    windows.sort_by_key(|(hwnd, _)| std::cmp::Reverse(z_order.get(&(hwnd.0 as usize)).copied()));
+}
+
+// Function to restore windows one at a time, bottom-most first
+fn restore_staggered(mut windows: Vec<(HWND, String)>, stagger: Duration, activate: bool) {
+    sort_bottom_first(&mut windows);
+    for (i, (hwnd, title)) in windows.into_iter().enumerate() {
+        if i > 0 && !stagger.is_zero() && !RESTORE_HURRY.load(Ordering::Relaxed) {
+            thread::sleep(stagger);
+        }
+        restore_window(hwnd, activate);
+        let process = process_name(window_process_id(hwnd));
+        info!(hwnd = ?hwnd.0, process = %process, "  → Restored: {}", title);
+        audit::record("restore", &title);
+        subscriptions::publish(Event::action("restore", &process, &title));
+    }
+}
+
+// Function to restore windows at the end of a focus session in the background,
+// skipping ones that were closed or already restored by the user
+fn restore_session_windows(windows: Vec<(HWND, String)>, config: &MonitorConfig) {
+    // Window handles aren't Send, so they cross the thread as plain values
+    let windows: Vec<(usize, String)> = windows
+        .into_iter()
+        .filter(|(hwnd, _)| unsafe { IsWindow(Some(*hwnd)).as_bool() && IsIconic(*hwnd).as_bool() })
+        .map(|(hwnd, title)| (hwnd.0 as usize, title))
+        .collect();
+    if windows.is_empty() {
+        return;
+    }
+    
+    finish_background_restore();
+    let stagger = config.restore_stagger;
+    let activate = config.restore_activates;
+    let thread = thread::spawn(move || {
+        let windows = windows.into_iter().map(|(hwnd, title)| (HWND(hwnd as *mut _), title)).collect();
+        restore_staggered(windows, stagger, activate);
+    });
+    *RESTORE_THREAD.lock().unwrap_or_else(|e| e.into_inner()) = Some(thread);
+}
+
+// Function to wait for the background restore to finish, hurrying it along,
+// before a pass suppresses windows, so none is minimized again while the
+// restore thread is still bringing it back
+fn finish_background_restore() {
+    let Some(thread) = RESTORE_THREAD.lock().unwrap_or_else(|e| e.into_inner()).take() else { return };
+    RESTORE_HURRY.store(true, Ordering::Relaxed);
+    let _ = thread.join();
+    RESTORE_HURRY.store(false, Ordering::Relaxed);
+}
+
+// Function to group windows by owning process, keeping first-seen order
+fn group_by_process(windows: Vec<&WindowInfo>) -> Vec<(String, Vec<&WindowInfo>)> {
+    let mut groups: Vec<(String, Vec<&WindowInfo>)> = Vec::new();
+    for window in windows {
+        match groups.iter_mut().find(|(process, _)| !process.is_empty() && *process == window.process_name) {
+            Some((_, group)) => group.push(window),
+            None => groups.push((window.process_name.clone(), vec![window])),
+        }
+    }
+    groups
+}
+
+// Function to minimize a process's windows together, logging one summary line
+fn minimize_group<'a>(process: &str, rule: &str, group: &[&'a WindowInfo]) -> Vec<&'a WindowInfo> {
+    let mut minimized: Vec<&WindowInfo> = Vec::with_capacity(group.len());
+    for window in group {
+        if let Err(e) = minimize_window(window.hwnd) {
+            error!("Error minimizing {}: {}", window.title, e);
+        } else {
+            log_action("Minimized", window, rule);
+            audit::record("minimize", &format!("{} ({})", window.title, window.process_name));
+            subscriptions::publish(Event::action("minimize", &window.process_name, &window.title));
+            minimized.push(window);
+        }
+    }
+    
+    match minimized.as_slice() {
+        [] => {}
+        [window] => info!(process, rule, "  → Minimized: {}", window.title),
+        windows => info!(process, rule, "  → Minimized {} windows of {}", windows.len(), process),
+    }
+    minimized
+}
+
+// Function to hide a process's windows outright until the session ends
+fn withdraw_group<'a>(process: &str, rule: &str, group: &[&'a WindowInfo]) -> Vec<&'a WindowInfo> {
+    let withdrawn: Vec<&WindowInfo> = group.iter().copied().filter(|window| switcher::withdraw(window.hwnd)).collect();
+    for window in &withdrawn {
+        log_action("Hidden", window, rule);
+        audit::record("hide", &format!("{} ({})", window.title, window.process_name));
+        subscriptions::publish(Event::action("hide", &window.process_name, &window.title));
+    }
+    
+    match withdrawn.as_slice() {
+        [] => {}
+        [window] => info!(process, rule, "  → Hidden: {}", window.title),
+        windows => info!(process, rule, "  → Hidden {} windows of {}", windows.len(), process),
+    }
+    withdrawn
+}
+
+// Function to ask a process's windows to close; apps may still prompt about
+// unsaved work, and nothing brings closed windows back
+fn close_group<'a>(process: &str, rule: &str, group: &[&'a WindowInfo]) -> Vec<&'a WindowInfo> {
+    let closed: Vec<&WindowInfo> = group
+        .iter()
+        .copied()
+        .filter(|window| unsafe { PostMessageW(Some(window.hwnd), WM_CLOSE, WPARAM(0), LPARAM(0)) }.is_ok())
+        .collect();
+    for window in &closed {
+        log_action("Asked to close", window, rule);
+        audit::record("close", &format!("{} ({})", window.title, window.process_name));
+        subscriptions::publish(Event::action("close", &window.process_name, &window.title));
+    }
+    
+    match closed.as_slice() {
+        [] => {}
+        [window] => info!(process, rule, "  → Asked to close: {}", window.title),
+        windows => info!(process, rule, "  → Asked {} windows of {} to close", windows.len(), process),
+    }
+    closed
+}
+
+// Function to cover a process's windows with an overlay until the session ends
+fn cover_group<'a>(process: &str, rule: &str, group: &[&'a WindowInfo]) -> Vec<&'a WindowInfo> {
+    let newly_covered = overlay::cover(&group.iter().map(|window| window.hwnd).collect::<Vec<_>>());
+    let covered: Vec<&WindowInfo> = group.iter().copied().filter(|window| newly_covered.contains(&window.hwnd)).collect();
+    for window in &covered {
+        log_action("Covered", window, rule);
+        audit::record("cover", &format!("{} ({})", window.title, window.process_name));
+        subscriptions::publish(Event::action("cover", &window.process_name, &window.title));
+    }
+    
+    match covered.as_slice() {
+        [] => {}
+        [window] => info!(process, rule, "  → Covered: {}", window.title),
+        windows => info!(process, rule, "  → Covered {} windows of {}", windows.len(), process),
+    }
+    covered
+}
+
+// Function to fade out a process's windows instead of minimizing them
+fn fade_group<'a>(process: &str, rule: &str, group: &[&'a WindowInfo], alpha: u8) -> Vec<&'a WindowInfo> {
+    let faded: Vec<&WindowInfo> = group
+        .iter()
+        .copied()
+        .filter(|window| unsafe { !IsIconic(window.hwnd).as_bool() } && dim::fade(window.hwnd, alpha))
+        .collect();
+    for window in &faded {
+        log_action("Faded", window, rule);
+        audit::record("fade", &format!("{} ({})", window.title, window.process_name));
+        subscriptions::publish(Event::action("fade", &window.process_name, &window.title));
+    }
+    
+    match faded.as_slice() {
+        [] => {}
+        [window] => info!(process, rule, "  → Faded: {}", window.title),
+        windows => info!(process, rule, "  → Faded {} windows of {}", windows.len(), process),
+    }
+    faded
+}
+
+// Function to log one window acted on at debug level, with the handle,
+// process and rule the log file needs to explain it later
+pub(crate) fn log_action(action: &str, window: &WindowInfo, rule: &str) {
+    debug!(hwnd = ?window.hwnd.0, process = %window.process_name, rule, "{}: {}", action, window.title);
+}
+
+// Function to find which target keyword a window title matches
+pub fn matching_target_keyword<'a>(window: &WindowInfo, targets: &'a [KeywordMatcher]) -> Option<&'a String> {
+    targets
+        .iter()
+        .find(|keyword| keyword.is_match(&window.title_lower))
+        .map(|keyword| &keyword.keyword)
+}
+
+// Function to check if a window is a system window that is never minimized
+pub fn is_system_window(window: &WindowInfo) -> bool {
+    window.title.is_empty() ||
+    window.title.contains("Program Manager") ||
+    window.title.contains("Desktop") ||
+    window.class_name.contains("Shell_TrayWnd")
+}
+
+// Function to check if a window is exempted by a per-process title exception
+// (exceptions are expected to be lowercased already)
+fn is_process_exception(window: &WindowInfo, exceptions: &[ProcessException]) -> bool {
+    exceptions.iter().any(|exception| {
+        window.matches_process(&exception.process) &&
+        exception.allowed_titles.iter().any(|keyword| window.title_lower.contains(keyword.as_str()))
+    })
+}
+
+// Function to check if a window's app is playing audio (one of `playing`)
+// and has a process exception keeping it on screen while it does
+fn is_playing_audio_exception(window: &WindowInfo, exceptions: &[ProcessException], playing: &[String]) -> bool {
+    playing.iter().any(|name| name.eq_ignore_ascii_case(&window.process_name)) &&
+    exceptions.iter().any(|exception| exception.while_playing_audio && window.matches_process(&exception.process))
+}
+
+// Rules compiled from the configuration: target and ignore keywords and the
+// lowercased process exceptions
+pub struct RuleSet {
+    pub targets: Vec<KeywordMatcher>,
+    pub ignored: Vec<KeywordMatcher>,
+    pub process_exceptions: Vec<ProcessException>,
+}
+
+impl RuleSet {
+    pub fn new(config: &MonitorConfig) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let targets = compile_keywords(&config.target_keywords)?;
+        let ignored = compile_keywords(&config.ignored_keywords)?;
+        
+        // Ignore rules win over target rules. A target whose every title is
+        // ignored could never apply, so the ruleset is refused; other overlaps
+        // are reported with how they resolve.
+        for target in &targets {
+            for ignore in &ignored {
+                if target.always_matched_by(ignore) {
+                    return Err(format!(
+                        "target keyword '{}' can never apply: every title it matches is ignored by '{}'",
+                        target.keyword, ignore.keyword
+                    ).into());
+                }
+                if ignore.always_matched_by(target) {
+                    info!("ℹ Windows matching both target '{}' and ignore '{}' are ignored", target.keyword, ignore.keyword);
+                }
+            }
+        }
+        
+        let process_exceptions = config.process_exceptions
+            .iter()
+            .map(|exception| ProcessException {
+                process: exception.process.to_lowercase(),
+                allowed_titles: exception.allowed_titles.iter().map(|t| t.to_lowercase()).collect(),
+                while_playing_audio: exception.while_playing_audio,
+            })
+            .collect();
+        
+        Ok(Self {
+            targets,
+            ignored,
+            process_exceptions,
+        })
+    }
+    
+    // The target rule matching the window, if any; a window an ignore rule
+    // matches is never a target
+    pub fn target<'a>(&'a self, window: &WindowInfo) -> Option<&'a String> {
+        if self.is_ignored(window) {
+            return None;
+        }
+        matching_target_keyword(window, &self.targets)
+    }
+    
+    // Whether an ignore rule matches the window
+    pub fn is_ignored(&self, window: &WindowInfo) -> bool {
+        matching_target_keyword(window, &self.ignored).is_some()
+    }
+}
+
+// Decision of what to do with the other windows while a target is active
+pub struct EnforcementPlan<'a> {
+    // Target that owns the session: its windows are the ones allowed
+    pub owner: Option<String>,
+    pub minimize: Vec<&'a WindowInfo>,
+    pub snap_group: Vec<&'a WindowInfo>,
+    pub shared: Vec<&'a WindowInfo>,
+    // Windows kept because their app is playing audio
+    pub playing_audio: Vec<&'a WindowInfo>,
+    // Windows left alone for being on another virtual desktop
+    pub other_desktops: Vec<HWND>,
+    // Likewise for being on another monitor, with same_monitor_only
+    pub other_monitors: Vec<HWND>,
+    // With dry_run: the rule behind the decision for every other window
+    pub reasons: Vec<(&'a WindowInfo, String)>,
+}
+
+// Function to decide which windows to minimize while a target window is active
+pub fn plan_enforcement<'a>(
+    windows: &'a [WindowInfo],
+    active: HWND,
+    active_target: &String,
+    config: &MonitorConfig,
+    matchers: &RuleSet,
+    decision_cache: &mut DecisionCache,
+) -> EnforcementPlan<'a> {
+    // Windows snapped alongside the target stay allowed
+    let candidates: Vec<HWND> = windows.iter().map(|w| w.hwnd).collect();
+    let snap_group = if config.snap_group_awareness {
+        snap_group_members(active, &candidates)
+    } else {
+        Vec::new()
+    };
+    
+    // Windows being shared in a Teams/Zoom call are never minimized
+    let shared = shared_windows(&config.sharing_processes, &candidates);
+    
+    // Apps playing audio (music, a call) stay when an exception opts into it;
+    // the audio sessions are only looked at when one does
+    let playing = if matchers.process_exceptions.iter().any(|exception| exception.while_playing_audio) {
+        playing_processes()
+    } else {
+        Vec::new()
+    };
+    let playing_audio: Vec<HWND> = windows
+        .iter()
+        .filter(|window| is_playing_audio_exception(window, &matchers.process_exceptions, &playing))
+        .map(|window| window.hwnd)
+        .collect();
+    
+    // Windows parked on other virtual desktops aren't in the way, nor (when
+    // scoped to the target's monitor) are the ones on other screens
+    let other_desktops = if config.virtual_desktop_awareness {
+        on_other_desktops(&candidates)
+    } else {
+        Vec::new()
+    };
+    let other_monitors = if config.same_monitor_only {
+        on_other_monitors(active, &candidates)
+    } else {
+        Vec::new()
+    };
+    
+    // Evaluate the keyword rules against every title up front (in parallel
+    // for large rulesets), reusing decisions for titles that barely changed
+    let titles: Vec<(HWND, &str)> = windows.iter().map(|w| (w.hwnd, w.title_lower.as_str())).collect();
+    let decisions = decision_cache.matches(&titles, &matchers.targets, &matchers.ignored);
+    let active_index = config.target_keywords.iter().position(|keyword| keyword == active_target);
+    
+    // The target whose windows define the scope of this pass
+    let owner_index = match config.target_resolution {
+        // Each window's first matching keyword is its highest-priority target.
+        // Only targets on screen compete: minimized, hidden and ignored
+        // windows don't count. The focused target always does, so it keeps
+        // the session unless one ranked above it is on screen too.
+        TargetResolution::Priority => windows
+            .iter()
+            .zip(&decisions)
+            .filter(|(window, decision)| {
+                decision.1.is_none()
+                    && window.hwnd != active
+                    && !is_system_window(window)
+                    && unsafe { IsWindowVisible(window.hwnd).as_bool() && !IsIconic(window.hwnd).as_bool() }
+            })
+            .filter_map(|(_, decision)| decision.0)
+            .chain(active_index)
+            .min(),
+        TargetResolution::Union | TargetResolution::MostRecent => active_index,
+    };
+    
+    let minimize = windows
+        .iter()
+        .enumerate()
+        .filter(|(i, window)| {
+            // Union keeps every target allowed; MostRecent only the active one
+            let allowed = match config.target_resolution {
+                TargetResolution::Union => decisions[*i].0.is_some(),
+                TargetResolution::MostRecent => decisions[*i].0.is_some() && decisions[*i].0 == active_index,
+                TargetResolution::Priority => decisions[*i].0.is_some() && decisions[*i].0 == owner_index,
+            };
+            
+            window.hwnd != active &&
+            !snap_group.contains(&window.hwnd) &&
+            !shared.contains(&window.hwnd) &&
+            !playing_audio.contains(&window.hwnd) &&
+            !other_desktops.contains(&window.hwnd) &&
+            !other_monitors.contains(&window.hwnd) &&
+            !allowed &&
+            !is_system_window(window) &&
+            decisions[*i].1.is_none() &&
+            !is_process_exception(window, &matchers.process_exceptions)
+        })
+        .map(|(_, window)| window)
+        .collect::<Vec<_>>();
+    
+    // Spell out which rule decided each window, skipping the ones already
+    // reported as snapped, shared, playing audio or on another desktop or monitor
+    let reasons = if config.dry_run {
+        windows
+            .iter()
+            .enumerate()
+            .filter(|(_, window)| window.hwnd != active && !snap_group.contains(&window.hwnd) && !shared.contains(&window.hwnd))
+            .filter(|(_, window)| !playing_audio.contains(&window.hwnd))
+            .filter(|(_, window)| !other_desktops.contains(&window.hwnd) && !other_monitors.contains(&window.hwnd))
+            .map(|(i, window)| {
+                let (target, ignored) = decisions[i];
+                let minimized = minimize.iter().any(|other| other.hwnd == window.hwnd);
+                let reason = match (target, ignored) {
+                    _ if is_system_window(window) => "Kept (system window)".to_string(),
+                    (_, Some(ignored)) => format!("Kept (ignore rule '{}')", matchers.ignored[ignored].keyword),
+                    (Some(target), None) if !minimized => format!("Kept (target rule '{}')", matchers.targets[target].keyword),
+                    _ if !minimized => format!("Kept (process exception for {})", window.process_name),
+                    (Some(target), None) => format!(
+                        "Suppress (target rule '{}' doesn't own this pass under {:?})",
+                        matchers.targets[target].keyword, config.target_resolution
+                    ),
+                    (None, None) => "Suppress (no target or ignore rule matches)".to_string(),
+                };
+                (window, reason)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    
+    EnforcementPlan {
+        owner: owner_index.map(|i| config.target_keywords[i].clone()),
+        minimize,
+        snap_group: windows.iter().filter(|w| snap_group.contains(&w.hwnd)).collect(),
+        shared: windows.iter().filter(|w| shared.contains(&w.hwnd)).collect(),
+        playing_audio: windows.iter().filter(|w| playing_audio.contains(&w.hwnd)).collect(),
+        other_desktops,
+        other_monitors,
+        reasons,
+    }
+}
+
+// What the monitor keeps between passes that the stages after the plan look
+// at: runtime ignores, window ages, signatures, focus order and budgets
+pub struct PassState<'s> {
+    pub scoped_ignores: &'s ScopedIgnores,
+    pub window_ages: &'s mut WindowAges,
+    pub publisher_cache: &'s mut PublisherCache,
+    pub focus_recency: &'s mut FocusRecency,
+    pub focus_budgets: &'s FocusBudgets,
+    // Whether the monitor runs elevated, so elevated windows are in reach
+    pub elevated: bool,
+}
+
+// The windows a pass suppresses once every stage had its say
+pub struct PassDecision<'a> {
+    pub suppress: Vec<&'a WindowInfo>,
+    // Elevated windows a non-elevated monitor can't act on, left out
+    pub unreachable: Vec<&'a WindowInfo>,
+    // What the stages kept back or added, and why
+    pub notes: Vec<(&'a WindowInfo, String)>,
+    // When the windows still settling are old enough for another pass
+    pub settling_until: Option<Instant>,
+}
+
+// Function to take a plan through the stages after it: runtime ignores, new
+// windows settling, trusted publishers, per-process window limits, used-up
+// focus budgets and elevation. The monitor and the REPL's simulation share it.
+pub fn decide_suppression<'a>(
+    windows: &'a [WindowInfo],
+    active: HWND,
+    plan: &EnforcementPlan<'a>,
+    config: &MonitorConfig,
+    state: &mut PassState,
+) -> PassDecision<'a> {
+    let mut notes = Vec::new();
+    
+    // Windows ignored at runtime (session or once) are kept
+    let mut suppress = Vec::new();
+    let mut kept = Vec::new();
+    for &window in &plan.minimize {
+        match state.scoped_ignores.scope_of(window) {
+            Some(scope) => {
+                notes.push((window, format!("Kept (ignored {})", scope)));
+                kept.push(window.hwnd);
+            }
+            None => suppress.push(window),
+        }
+    }
+    // What the plan keeps or leaves alone, and each stage below spares, is
+    // off limits to the window limits and budgets too
+    let mut exempt: Vec<HWND> = plan
+        .snap_group
+        .iter()
+        .chain(&plan.shared)
+        .chain(&plan.playing_audio)
+        .map(|window| window.hwnd)
+        .chain(plan.other_desktops.iter().chain(&plan.other_monitors).chain(&kept).copied())
+        .collect();
+    
+    // Windows that just opened may still be showing a splash screen
+    let mut settling_until: Option<Instant> = None;
+    state.window_ages.update(windows);
+    suppress.retain(|window| match state.window_ages.age(window) {
+        Some(age) if age < config.min_window_age => {
+            notes.push((window, "Waiting for new window to settle".to_string()));
+            let settles_at = Instant::now() + (config.min_window_age - age);
+            settling_until = Some(settling_until.map_or(settles_at, |at| at.min(settles_at)));
+            exempt.push(window.hwnd);
+            false
+        }
+        _ => true,
+    });
+    
+    // Software signed by a trusted publisher is never touched
+    if !config.trusted_publishers.is_empty() {
+        suppress.retain(|window| {
+            let Some(path) = process_image_path(window.process_id) else { return true };
+            match state.publisher_cache.trusted_publisher(&path, &config.trusted_publishers) {
+                Some(publisher) => {
+                    notes.push((window, format!("Kept (signed by {})", publisher)));
+                    exempt.push(window.hwnd);
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+    
+    // Processes over their window limit lose their least recently used windows
+    if !config.process_limits.is_empty() {
+        state.focus_recency.retain(windows);
+        let excess = limits::over_limit(windows, &suppress, &exempt, active, &config.process_limits, state.focus_recency);
+        for (window, process) in excess {
+            notes.push((window, format!("Over the window limit for {}", process)));
+            suppress.push(window);
+        }
+    }
+    
+    // Apps that used up their daily focus budget are distractions now,
+    // whatever rules allowed them before
+    if !config.focus_budgets.is_empty() {
+        let spent: Vec<(&WindowInfo, &FocusBudget)> = windows
+            .iter()
+            .filter(|window| window.hwnd != active && !is_system_window(window) && !exempt.contains(&window.hwnd))
+            .filter(|window| !suppress.iter().any(|other| other.hwnd == window.hwnd))
+            .filter_map(|window| state.focus_budgets.exhausted(window, &config.focus_budgets).map(|budget| (window, budget)))
+            .collect();
+        for (window, budget) in spent {
+            notes.push((window, format!("Used up today's focus budget for {}", budget.process)));
+            suppress.push(window);
+        }
+    }
+    
+    // Elevated windows silently ignore what a non-elevated monitor does to
+    // them, so leave them out instead of pretending
+    let (unreachable, suppress) = suppress.into_iter().partition(|window| window.process_elevated && !state.elevated);
+    PassDecision {
+        suppress,
+        unreachable,
+        notes,
+        settling_until,
+    }
+}
+
+// Monitor loop for embedding in another tool: built from a configuration and
+// run on the calling thread until a stop handle (or Ctrl+C) stops it. Windows
+// it changed are put back before `run` returns. Progress is reported through
+// `tracing`, so nothing reaches the console unless the host installs a
+// subscriber writing there.
+pub struct Monitor {
+    config: MonitorConfig,
+    source: Option<ConfigSource>,
+}
+
+// Handle to stop a running monitor from another thread
+#[derive(Clone, Copy, Debug)]
+pub struct StopHandle;
+
+impl StopHandle {
+    // Ask the monitor to restore what it changed and return from `run`
+    pub fn stop(&self) {
+        shutdown::request();
+    }
+}
+
+impl Monitor {
+    // `source` lets the monitor reload the configuration when its file changes
+    pub fn new(config: MonitorConfig, source: Option<ConfigSource>) -> Self {
+        shutdown::reset();
+        Self { config, source }
+    }
+    
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle
+    }
+    
+    pub fn run(self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        if let Some(opacity) = self.config.dim_inactive_opacity {
+            return inactive::run_dim_inactive(&self.config, opacity);
+        }
+        monitor_windows(self.config, self.source)
+    }
+}
+
+// Main loop of the window monitoring
+fn monitor_windows(mut config: MonitorConfig, source: Option<ConfigSource>) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    // Pre-compute lowercase keywords for faster comparison
+    let mut matchers = RuleSet::new(&config)?;
+    
+    // Pick up keyword and interval changes from the config file while running
+    let mut config_watcher = source.as_ref().and_then(|source| {
+        let watcher = ConfigWatcher::new(&source.path);
+        if watcher.is_none() {
+            info!("ℹ Not watching {} for changes, restart to apply edits", source.path.display());
+        }
+        watcher
+    });
+    
+    let mut last_active_window: Option<HWND> = None;
+    let mut last_active_target: Option<String> = None;
+    let mut window_cache = WindowEnumerator::new(&config);
+    let mut rate_limiter = RateLimiter::new(config.max_actions_per_hour);
+    let mut last_power_state = PowerState::default();
+    let mut last_session_kind = SessionKind::default();
+    // Whether the title hook is off while on Battery Saver or Remote Desktop
+    let mut titles_unhooked = false;
+    #[cfg(feature = "stats")]
+    let mut decision_latency = LatencyStats::default();
+    // From the focus change (or its detection, when polling) until the pass is done
+    #[cfg(feature = "stats")]
+    let mut enforcement_latency = LatencyStats::default();
+    // Set by a config reload until the next pass hands the session over
+    let mut rules_changed = false;
+    let mut deferred_for: Option<Interaction> = None;
+    let mut deferred_since: Option<Instant> = None;
+    let mut recheck_at: Option<Instant> = None;
+    // Foreground window and since when it has been in front
+    let mut foreground_since: Option<(HWND, Instant)> = None;
+    // Set when the idle timeout ended the session, until input comes back
+    let mut idled_out = false;
+    // Time budgeted apps had focus today
+    let mut focus_budgets = if config.ephemeral { FocusBudgets::in_memory() } else { FocusBudgets::load() };
+    // Target waiting out its grace delay before its session starts, and until when
+    let mut grace: Option<(HWND, Instant)> = None;
+    // Whether enforcement is suspended for a fullscreen app in front
+    let mut fullscreen_paused = false;
+    // Started at sign-in: holds the first pass back until the logon settled
+    let mut logon_settle = config.at_logon.then(|| LogonSettle::new(config.logon_settle, config.logon_quiet_cpu));
+    if config.at_logon {
+        match config.logon_quiet_cpu {
+            Some(percent) => info!("Started at sign-in, waiting {:?} and for CPU usage under {}% before acting\n", config.logon_settle, percent),
+            None => info!("Started at sign-in, waiting {:?} before acting\n", config.logon_settle),
+        }
+    }
+    let mut publisher_cache = PublisherCache::default();
+    let mut focus_session = FocusSession::default();
+    let mut cursor_follower = config.follow_cursor.map(CursorFollower::new);
+    let mut decision_cache = DecisionCache::default();
+    let mut focus_history = config.focus_history.then(FocusHistory::load);
+    let mut frontends = Frontends::start(&config)?;
+    let mut paused = false;
+    #[cfg(feature = "stats")]
+    let mut interruptions = InterruptionStats::default();
+    let mut escalation_counters = EscalationCounters::default();
+    let mut focus_recency = FocusRecency::default();
+    let mut window_ages = WindowAges::default();
+    // Features that failed to start; the monitor carries on without them
+    let mut degraded: Vec<String> = Vec::new();
+    let mut foreground_events = if config.foreground_events {
+        let mut events = ForegroundEvents::install();
+        if events.is_none() {
+            warn!("⚠ Could not hook foreground changes, polling every {:?} instead", config.poll_interval);
+            degraded.push("polling instead of foreground events".to_string());
+        }
+        if config.title_events
+            && let Some(events) = events.as_mut()
+            && !events.watch_titles(config.title_spam_limit)
+        {
+            warn!("⚠ Could not hook title changes, only focus changes are noticed");
+            degraded.push("title changes not tracked".to_string());
+        }
+        events
+    } else {
+        None
+    };
+    if config.hides_windows() {
+        switcher::install_exit_handler();
+    }
+    // Windows faded during the current focus session
+    let mut faded: Vec<HWND> = Vec::new();
+    // Elevated windows can only be acted on when the monitor is elevated too
+    let elevated = process::is_elevated();
+    dim::set_animation_duration(config.opacity_animation);
+    if config.fades_windows() {
+        dim::install_exit_handler();
+    }
+    // Runs before the handlers above, undoing everything on Ctrl+C
+    shutdown::install();
+    let mut idle_dimmer = config
+        .idle_dim_after
+        .filter(|_| !config.observe_only())
+        .map(|after| IdleDimmer::new(after, config.idle_dim_ramp, config.idle_dim_opacity));
+    
+    if config.prewarm {
+        // Resolve windows and their processes up front so the first
+        // decision doesn't pay for cold caches
+        let warmed = window_cache.get_windows()?.len();
+        info!("Pre-warmed window cache with {} windows", warmed);
+        
+        if let Err(e) = pin_working_set(16 * 1024 * 1024, 64 * 1024 * 1024) {
+            warn!("Could not pin working set: {}", e);
+        }
+    }
+    
+    // Windows minimized during this run; replaces the previous run's list
+    // once something gets minimized
+    let mut restore_list = RestoreList::default();
+    
+    // Hotkeys for ignoring the foreground window for this session or once
+    let mut scoped_ignores = ScopedIgnores::default();
+    let ignore_hotkeys = [
+        (HOTKEY_IGNORE_FOR_SESSION, &config.ignore_for_session_hotkey, IgnoreScope::Session),
+        (HOTKEY_IGNORE_ONCE, &config.ignore_once_hotkey, IgnoreScope::Once),
+    ];
+    for (id, hotkey, scope) in ignore_hotkeys {
+        let Some(hotkey) = hotkey else { continue };
+        match register_hotkey(id, hotkey) {
+            Ok(()) => info!("Press {} to ignore the foreground window {}\n", hotkey, scope),
+            Err(e) => {
+                warn!("⚠ Could not register hotkey {}, continuing without it: {}\n", hotkey, e);
+                degraded.push(format!("hotkey {} unavailable", hotkey));
+            }
+        }
+    }
+    
+    // Hotkey for undoing everything the current session did
+    if let Some(hotkey) = &config.restore_all_hotkey {
+        match register_hotkey(HOTKEY_RESTORE_ALL, hotkey) {
+            Ok(()) => info!("Press {} to restore everything minimized this session\n", hotkey),
+            Err(e) => {
+                warn!("⚠ Could not register hotkey {}, continuing without it: {}\n", hotkey, e);
+                degraded.push(format!("hotkey {} unavailable", hotkey));
+            }
+        }
+    }
+    
+    // Hotkeys for changing the foreground window's opacity by hand
+    let mut opacity_presets = if config.ephemeral { OpacityPresets::default() } else { OpacityPresets::load() };
+    let opacity_hotkeys = [
+        (HOTKEY_OPACITY_UP, &config.opacity_up_hotkey, "make the foreground window more opaque"),
+        (HOTKEY_OPACITY_DOWN, &config.opacity_down_hotkey, "make the foreground window less opaque"),
+        (HOTKEY_OPACITY_PRESET, &config.opacity_preset_hotkey, "keep its opacity for that app"),
+    ];
+    for (id, hotkey, purpose) in opacity_hotkeys {
+        let Some(hotkey) = hotkey else { continue };
+        match register_hotkey(id, hotkey) {
+            Ok(()) => info!("Press {} to {}", hotkey, purpose),
+            Err(e) => {
+                warn!("⚠ Could not register hotkey {}, continuing without it: {}", hotkey, e);
+                degraded.push(format!("hotkey {} unavailable", hotkey));
+            }
+        }
+    }
+    if config.opacity_up_hotkey.is_some() || config.opacity_down_hotkey.is_some() || config.opacity_preset_hotkey.is_some() {
+        info!("");
+    }
+    
+    // Hotkey for the command palette
+    let palette = CommandPalette::new();
+    let mut reload_requested = false;
+    if let Some(hotkey) = &config.command_palette_hotkey {
+        match register_hotkey(HOTKEY_COMMAND_PALETTE, hotkey) {
+            Ok(()) => info!("Press {} to open the command palette\n", hotkey),
+            Err(e) => {
+                warn!("⚠ Could not register hotkey {}, continuing without it: {}\n", hotkey, e);
+                degraded.push(format!("hotkey {} unavailable", hotkey));
+            }
+        }
+    }
+    
+    // Hotkeys for jumping straight to one of the allowed windows
+    let mut quick_switch = QuickSwitch::default();
+    if let Some(modifiers) = &config.quick_switch_modifiers {
+        let registered = (0..quick_switch::SLOTS)
+            .filter(|&slot| {
+                let hotkey = format!("{}+{}", modifiers, slot + 1);
+                register_hotkey(HOTKEY_QUICK_SWITCH_FIRST + slot as i32, &hotkey)
+                    .map_err(|e| warn!("⚠ Could not register hotkey {}, continuing without it: {}", hotkey, e))
+                    .is_ok()
+            })
+            .count();
+        info!("Press {}+1 to {}+{} to switch between allowed windows ({} registered)\n", modifiers, modifiers, quick_switch::SLOTS, registered);
+        if registered < quick_switch::SLOTS {
+            degraded.push(format!("{} quick-switch hotkeys unavailable", quick_switch::SLOTS - registered));
+        }
+    }
+    if !degraded.is_empty() {
+        warn!("⚠ Running degraded: {} (see `doctor`)\n", degraded.join(", "));
+        frontends.set_degraded(&degraded);
+    }
+    let quick_switch_ids = HOTKEY_QUICK_SWITCH_FIRST..HOTKEY_QUICK_SWITCH_FIRST + quick_switch::SLOTS as i32;
+    
+    loop {
+        // Stop handle, Ctrl+C or closing the console: put every window back before exiting
+        if shutdown::requested() {
+            info!("Stopping, restoring everything changed this session");
+            #[cfg(feature = "stats")]
+            info!("Enforcement latency: {}", enforcement_latency.distribution());
+            info!("Decision cache hits: {}", decision_cache.hit_rate());
+            restore_before_exit(&mut focus_session, &mut faded, &mut quick_switch, &config);
+            dim::undim_all();
+            shutdown::finished();
+            return Ok(());
+        }
+        
+        // Reload the config file when it changed, keeping the running
+        // configuration if the new one doesn't load or compile
+        if let Some(source) = source.as_ref()
+            && (config_watcher.as_mut().is_some_and(ConfigWatcher::changed) | std::mem::take(&mut reload_requested))
+        {
+            match (source.load)().and_then(|reloaded| Ok((RuleSet::new(&reloaded)?, reloaded))) {
+                Ok((reloaded_matchers, reloaded)) => {
+                    config.reload_from(reloaded);
+                    matchers = reloaded_matchers;
+                    window_cache = WindowEnumerator::new(&config);
+                    // Re-run the pass without ending the focus session, so only
+                    // the windows whose verdict changed are touched
+                    last_active_window = None;
+                    rules_changed = true;
+                    info!("✓ Reloaded {}", source.path.display());
+                    info!("Target keywords: {:?}", config.target_keywords);
+                    info!("Ignored keywords: {:?}\n", config.ignored_keywords);
+                    audit::record("config", &format!(
+                        "reloaded {}, effective settings sha256 {}",
+                        source.path.display(),
+                        audit::sha256_hex(&format!("{:?}", config))
+                    ));
+                }
+                Err(e) => {
+                    warn!("⚠ Could not reload {}, keeping the current config: {}\n", source.path.display(), e);
+                    audit::record("config", &format!("reload of {} rejected: {}", source.path.display(), e));
+                }
+            }
+        }
+        
+        // Keep the pipe's `status` in step with the session ignores
+        if let Some(titles) = scoped_ignores.take_changed() {
+            frontends.set_ignored(&titles);
+        }
+        
+        // When the change this wake-up is for happened; taken every time so
+        // events while paused don't count against a later pass
+        #[cfg(feature = "stats")]
+        let changed_at = foreground_events.as_ref().and_then(ForegroundEvents::take_event_time);
+        
+        // Handle ignore and quick-switch hotkeys pressed since the last check
+        for id in pending_hotkeys() {
+            if quick_switch_ids.contains(&id) {
+                quick_switch.activate((id - HOTKEY_QUICK_SWITCH_FIRST) as usize);
+                continue;
+            }
+            if id == HOTKEY_COMMAND_PALETTE {
+                let targets: Vec<(HWND, String)> = window_cache
+                    .get_windows()
+                    .map(|windows| {
+                        windows
+                            .iter()
+                            .filter(|window| matchers.target(window).is_some())
+                            .map(|window| (window.hwnd, window.title.clone()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                palette.open(palette_entries(paused, &focus_session.suppressed(), &targets, source.is_some()));
+                continue;
+            }
+            if id == HOTKEY_RESTORE_ALL {
+                info!("Restoring everything minimized this session\n");
+                restore_everything(&mut focus_session, &mut faded, &mut quick_switch, &config);
+                last_active_target = None;
+                escalation_counters.clear();
+                scoped_ignores.end_session();
+                continue;
+            }
+            if matches!(id, HOTKEY_OPACITY_UP | HOTKEY_OPACITY_DOWN | HOTKEY_OPACITY_PRESET) {
+                let foreground = unsafe { GetForegroundWindow() };
+                let Some(window) = window_info(foreground, config.include_owned_windows, &mut window_cache.process_names) else { continue };
+                if id == HOTKEY_OPACITY_PRESET {
+                    opacity_presets.remember(&window);
+                } else {
+                    step_opacity(&window, id == HOTKEY_OPACITY_UP);
+                }
+                continue;
+            }
+            let scope = match id {
+                HOTKEY_IGNORE_FOR_SESSION => IgnoreScope::Session,
+                HOTKEY_IGNORE_ONCE => IgnoreScope::Once,
+                _ => continue,
+            };
+            let foreground = unsafe { GetForegroundWindow() };
+            if let Some(window) = window_info(foreground, config.include_owned_windows, &mut window_cache.process_names) {
+                info!("Ignoring {}: {}", scope, window.title);
+                audit::record("ignore", &format!("{} {} ({})", scope, window.title, window.process_name));
+                scoped_ignores.add(scope, &window);
+                scoped_ignores.print_status(&config.ignored_keywords);
+            }
+        }
+        
+        // Carry out what was picked from the command palette; the commands the
+        // tray menu has too are handled along with the tray's below
+        let picks = palette.picks();
+        for pick in &picks {
+            match *pick {
+                PaletteCommand::Tray(_) => {}
+                PaletteCommand::Restore(hwnd) => {
+                    let Some((hwnd, title, effect)) = focus_session.release(HWND(hwnd as *mut _)) else { continue };
+                    info!("Restoring for the rest of the session: {}\n", title);
+                    // Ignored for the session, so the next pass leaves it be
+                    if let Some(window) = window_info(hwnd, config.include_owned_windows, &mut window_cache.process_names) {
+                        scoped_ignores.add(IgnoreScope::Session, &window);
+                    }
+                    match effect {
+                        None => {
+                            switcher::show_in_switcher(&[hwnd]);
+                            restore_session_windows(vec![(hwnd, title)], &config);
+                        }
+                        Some(Effect::Faded) => {
+                            faded.retain(|other| *other != hwnd);
+                            dim::unfade(&[hwnd]);
+                        }
+                        Some(Effect::Covered) => overlay::uncover(&[hwnd]),
+                        Some(Effect::Hidden) => switcher::unwithdraw(&[hwnd]),
+                    }
+                }
+                PaletteCommand::Focus(hwnd) => unsafe {
+                    let hwnd = HWND(hwnd as *mut _);
+                    if IsIconic(hwnd).as_bool() {
+                        let _ = ShowWindow(hwnd, SW_RESTORE);
+                    }
+                    // The palette had the foreground, so this process may hand it on
+                    let _ = SetForegroundWindow(hwnd);
+                },
+                PaletteCommand::ReloadConfig => reload_requested = true,
+            }
+        }
+        if reload_requested {
+            continue;
+        }
+        
+        // Handle picks from the tray menu, the control pipe and the palette
+        let palette_commands = picks.iter().filter_map(|pick| match pick {
+            PaletteCommand::Tray(command) => Some(*command),
+            _ => None,
+        });
+        for command in frontends.commands().into_iter().chain(palette_commands) {
+            match command {
+                TrayCommand::Pause | TrayCommand::Resume => {
+                    paused = command == TrayCommand::Pause;
+                    info!("{}\n", if paused { "Monitoring paused" } else { "Monitoring resumed" });
+                    audit::record(if paused { "pause" } else { "resume" }, "monitoring");
+                    frontends.set_paused(paused);
+                    subscriptions::publish(Event::session(if paused { "pause" } else { "resume" }, None, None));
+                    last_active_window = None;
+                    last_active_target = None;
+                }
+                TrayCommand::RestoreAll => {
+                    info!("Restoring everything minimized this session\n");
+                    restore_everything(&mut focus_session, &mut faded, &mut quick_switch, &config);
+                    last_active_target = None;
+                    escalation_counters.clear();
+                    scoped_ignores.end_session();
+                }
+                TrayCommand::Exit => {
+                    info!("Exiting on request");
+                    #[cfg(feature = "stats")]
+                    info!("Enforcement latency: {}", enforcement_latency.distribution());
+                    // Synchronously, so nothing is left minimized or faded
+                    // once the host (or the service) sees the process end
+                    restore_before_exit(&mut focus_session, &mut faded, &mut quick_switch, &config);
+                    switcher::show_all_in_switcher();
+                    dim::undim_all();
+                    return Ok(());
+                }
+            }
+        }
+        
+        // Count focused time against the budgets; an app that used its budget up
+        // becomes a distraction, and the running session is handed over to that
+        if !config.focus_budgets.is_empty() {
+            let foreground = unsafe { GetForegroundWindow() };
+            let focused = window_info(foreground, config.include_owned_windows, &mut window_cache.process_names).filter(|_| !paused);
+            for change in focus_budgets.tick(focused.as_ref(), &config.focus_budgets) {
+                let message = match change {
+                    BudgetChange::Exhausted(budget) => format!(
+                        "{} used up its {} of focus for today, so it is suppressed like other distractions until tomorrow",
+                        budget.process,
+                        humantime::format_duration(budget.daily)
+                    ),
+                    BudgetChange::NewDay => "A new day started, so every focus budget is available again".to_string(),
+                };
+                info!("ℹ {}\n", message);
+                frontends.notify(&config.app_name, &message);
+                subscriptions::publish(Event::notification("budget", &message));
+                last_active_window = None;
+                rules_changed = true;
+            }
+        }
+        if paused {
+            wait_for_next_pass(foreground_events.as_ref(), config.poll_interval, &config, [None, None, None]);
+            continue;
+        }
+        
+        // Leave the windows of apps still launching at sign-in alone
+        if let Some(settle) = logon_settle.as_mut() {
+            if !settle.settled() {
+                wait_for_next_pass(foreground_events.as_ref(), config.poll_interval, &config, [Some(settle.next_check()), None, None]);
+                continue;
+            }
+            info!("✓ Sign-in settled, enforcement started\n");
+            logon_settle = None;
+        }
+        
+        // Re-run the pass once windows of newly launched apps have settled, or
+        // once a target has kept focus through its grace delay. The session
+        // carries on: a target still waiting out its delay isn't one yet, and
+        // a running session mustn't start over (resume, start event, move).
+        if recheck_at.is_some_and(|at| Instant::now() >= at) {
+            recheck_at = None;
+            last_active_window = None;
+        }
+        
+        // Restore windows whose delayed restore is due
+        restore_session_windows(focus_session.due(), &config);
+        
+        // Check the power source so enforcement can back off on battery
+        let power_state = current_power_state();
+        if power_state != last_power_state {
+            if !power_state.on_ac && !config.enforce_on_battery {
+                info!("Running on battery power, enforcement paused\n");
+            } else if !last_power_state.on_ac && !config.enforce_on_battery {
+                info!("Back on AC power, enforcement resumed\n");
+            }
+            if power_state.battery_saver && !last_power_state.battery_saver {
+                info!("Battery Saver is active, polling every {:?}\n", config.battery_saver_poll_interval);
+            }
+            // AC-only targets start or stop counting, so decide again
+            if power_state.on_ac != last_power_state.on_ac && !config.ac_only_targets.is_empty() {
+                last_active_window = None;
+            }
+            last_power_state = power_state;
+        }
+        
+        // Remote sessions get fewer wakeups and no opacity effects
+        let session_kind = current_session_kind();
+        if session_kind != last_session_kind {
+            if session_kind.is_remote() {
+                info!("Running in a {}, polling less often and pausing idle dimming\n", session_kind);
+                dim::undim_all();
+            } else {
+                info!("Back on the {}\n", session_kind);
+            }
+            last_session_kind = session_kind;
+        }
+        
+        let mut interval = if power_state.battery_saver {
+            config.battery_saver_poll_interval
+        } else {
+            config.poll_interval
+        };
+        if session_kind.is_remote() {
+            interval = interval.max(config.remote_poll_interval);
+        }
+        
+        // Title changes wake the loop far more often than focus changes, so
+        // their hook is left out while wakeups are being kept down
+        let quiet = power_state.battery_saver || session_kind.is_remote();
+        if config.title_events
+            && quiet != titles_unhooked
+            && let Some(events) = foreground_events.as_mut()
+        {
+            if quiet {
+                events.unwatch_titles();
+            } else if !events.watch_titles(config.title_spam_limit) {
+                warn!("⚠ Could not hook title changes again, only focus changes are noticed");
+            }
+            titles_unhooked = quiet;
+        }
+        
+        if !power_state.on_ac && !config.enforce_on_battery {
+            // Re-evaluate the foreground window once AC power returns
+            last_active_window = None;
+            last_active_target = None;
+            thread::sleep(interval);
+            continue;
+        }
+        
+        // Pause quietly while a secure desktop (UAC prompt, lock screen) has input
+        if !is_input_desktop_accessible() {
+            last_active_window = None;
+            last_active_target = None;
+            thread::sleep(interval);
+            continue;
+        }
+        
+        // Suspend everything while a game or other fullscreen app is in front;
+        // a running session picks up again once it is gone
+        if config.pause_when_fullscreen && is_fullscreen_app_active() {
+            if !fullscreen_paused {
+                info!("Fullscreen app in front, pausing enforcement\n");
+                fullscreen_paused = true;
+            }
+            last_active_window = None;
+            wait_for_next_pass(foreground_events.as_ref(), interval, &config, [None, None, None]);
+            continue;
+        }
+        if fullscreen_paused {
+            info!("Fullscreen app gone, enforcement resumed\n");
+            fullscreen_paused = false;
+        }
+        
+        // A target left focused with nobody at the keyboard ends the session
+        if let Some(timeout) = config.target_idle_timeout
+            && last_active_target.is_some()
+            && user_idle_time() >= timeout
+        {
+            info!("No input for {:?} while a target was focused, ending the session\n", timeout);
+            last_active_target = None;
+            restore_everything(&mut focus_session, &mut faded, &mut quick_switch, &config);
+            escalation_counters.clear();
+            scoped_ignores.end_session();
+            idled_out = true;
+        }
+        // Once input comes back, act on the focused window again as if focus
+        // had just moved to it, even when it's the same target as before
+        if idled_out && config.target_idle_timeout.is_none_or(|timeout| user_idle_time() < timeout) {
+            idled_out = false;
+            last_active_window = None;
+        }
+        
+        // Get currently active window
+        let current_active = if config.low_latency_mode {
+            foreground_window()
+        } else {
+            unsafe { GetForegroundWindow() }
+        };
+        if foreground_since.is_none_or(|(hwnd, _)| hwnd != current_active) {
+            foreground_since = Some((current_active, Instant::now()));
+        }
+        
+        if let Some(history) = focus_history.as_mut() {
+            history.save_if_due();
+        }
+        
+        // Keep the suppressed count on the taskbar current
+        frontends.set_suppressed_count(focus_session.suppressed_count());
+        
+        // Undim the focused window right away, and dim stale ones now and then
+        if let Some(dimmer) = idle_dimmer.as_mut() {
+            dimmer.focused(current_active);
+            if dimmer.due() && !session_kind.is_remote() {
+                let windows: Vec<HWND> = window_cache
+                    .get_windows()?
+                    .iter()
+                    .filter(|window| !is_system_window(window))
+                    .map(|window| window.hwnd)
+                    .collect();
+                dimmer.update(&windows, current_active);
+            }
+        }
+        
+        // Keep the focused target on the display the mouse is on, though not
+        // while it is being dragged around
+        if let Some(follower) = cursor_follower.as_mut() {
+            if last_active_target.is_some() && last_active_window == Some(current_active) && !config.observe_only() {
+                if interaction_in_progress(true, false).is_none()
+                    && let Some(original) = follower.update(current_active)
+                {
+                    info!("  → Followed the mouse to another display");
+                    audit::record("move", "target followed the mouse to another display");
+                    focus_session.record_move(current_active, original);
+                }
+            } else {
+                follower.stop();
+            }
+        }
+        
+        // A new title may make the same window a target, or stop it being one
+        if foreground_events.as_ref().is_some_and(ForegroundEvents::take_title_changed) {
+            last_active_window = None;
+        }
+        
+        // Only process if active window changed
+        if last_active_window != Some(current_active) {
+            // Wait for drags and window moves to finish so windows don't vanish
+            // mid-drag, and briefly for menus and dialogs so they aren't orphaned
+            if let Some(interaction) = interaction_in_progress(config.defer_during_drag, config.defer_during_menus) {
+                // A drag turning into a menu starts the menu's own deferral
+                if deferred_for != Some(interaction) {
+                    deferred_since = None;
+                }
+                let since = *deferred_since.get_or_insert_with(Instant::now);
+                if !interaction.is_brief() || since.elapsed() < config.menu_defer_limit {
+                    if deferred_for != Some(interaction) {
+                        info!("Deferring enforcement while {}", interaction);
+                        deferred_for = Some(interaction);
+                    }
+                    thread::sleep(interval);
+                    continue;
+                }
+            }
+            deferred_for = None;
+            deferred_since = None;
+            
+            // Let rapid focus switches settle, acting only on where focus ends up
+            if let Some((_, since)) = foreground_since
+                && since.elapsed() < config.focus_settle
+            {
+                wait_for_next_pass(foreground_events.as_ref(), interval, &config, [Some(since + config.focus_settle), focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+                continue;
+            }
+            
+            last_active_window = Some(current_active);
+            if grace.is_some_and(|(hwnd, _)| hwnd != current_active) {
+                info!("Focus moved on during the grace delay, nothing was suppressed");
+                grace = None;
+            }
+            // Cleared only once the pass gets to hand over, so a pass cut
+            // short (grace delay, rate limit) doesn't lose it
+            let handoff = rules_changed;
+            #[cfg(feature = "stats")]
+            let decision_start = Instant::now();
+            #[cfg(feature = "stats")]
+            let changed_at = changed_at.unwrap_or(decision_start);
+            
+            let active_window = if config.low_latency_mode || !config.enumeration_processes.is_empty() {
+                // Inspect only the foreground window; the full list is
+                // only enumerated once an enforcement pass is needed
+                // (and a scoped list may not contain the target at all)
+                window_info(current_active, config.include_owned_windows, &mut window_cache.process_names)
+            } else {
+                // Find active window in the cached list using early exit
+                window_cache.get_windows()?.iter().find(|w| w.hwnd == current_active).cloned()
+            };
+            
+            if let Some(active_window) = active_window {
+                info!("Active window: {}", active_window.title);
+                opacity_presets.focused(&active_window);
+                focus_recency.focused(active_window.hwnd);
+                if let Some(history) = focus_history.as_mut() {
+                    history.focused(WindowIdentity::of(&active_window));
+                }
+                
+                // Check if active window is target window
+                let active_target = matchers
+                    .target(&active_window)
+                    .filter(|target| config.enforces_target(target, power_state.on_ac))
+                    .cloned();
+                let previous_target = std::mem::replace(&mut last_active_target, active_target.clone());
+                
+                if let Some(active_target) = active_target {
+                    info!("✓ Target window detected: {}", active_window.title);
+                    
+                    // Give a target that just got focus its grace delay before acting
+                    let delay = config.grace_delay_for(&active_target);
+                    if previous_target.is_none() && !delay.is_zero() {
+                        let until = match grace {
+                            Some((hwnd, until)) if hwnd == current_active => until,
+                            _ => {
+                                info!("  · Waiting {:?} before acting, in case focus moves straight on\n", delay);
+                                let until = Instant::now() + delay;
+                                grace = Some((current_active, until));
+                                until
+                            }
+                        };
+                        if Instant::now() < until {
+                            // Not a session yet; the pass runs again once the delay is up
+                            last_active_target = None;
+                            recheck_at = Some(recheck_at.map_or(until, |at| at.min(until)));
+                            wait_for_next_pass(foreground_events.as_ref(), interval, &config, [recheck_at, focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+                            continue;
+                        }
+                        grace = None;
+                    }
+                    
+                    if previous_target.is_none() {
+                        if active_window.process_elevated && !elevated {
+                            warn!("⚠ The target runs elevated, so it can't be moved or made transparent (run the monitor elevated to include it)");
+                        }
+                        focus_session.resume();
+                        subscriptions::publish(Event::session("start", Some(&active_target), Some(&active_window.process_name)));
+                        
+                        // Move the target to its preferred monitor for the session
+                        if !config.observe_only()
+                            && let Some(monitor) = config.monitor_for(&active_target)
+                            && let Some(original) = move_to_monitor(current_active, monitor)
+                        {
+                            info!(hwnd = ?current_active.0, process = %active_window.process_name, rule = %active_target, "  → Moved to display {}: {}", monitor, active_window.title);
+                            audit::record("move", &format!("{} to display {}", active_window.title, monitor));
+                            subscriptions::publish(Event::action("move", &active_window.process_name, &active_window.title));
+                            focus_session.record_move(current_active, original);
+                        }
+                    }
+                    
+                    // With union semantics every target is already allowed, so
+                    // bouncing between targets needs no new enforcement pass
+                    if config.target_resolution == TargetResolution::Union && previous_target.is_some() && !handoff {
+                        info!("Switched between target windows, nothing to do\n");
+                        wait_for_next_pass(foreground_events.as_ref(), interval, &config, [recheck_at, focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+                        continue;
+                    }
+                    
+                    // Skip the pass entirely while this rule is over its hourly cap
+                    let remaining_actions = rate_limiter.remaining(&active_target);
+                    if remaining_actions == 0 {
+                        info!("Rule '{}' is suspended, skipping\n", active_target);
+                        wait_for_next_pass(foreground_events.as_ref(), interval, &config, [recheck_at, focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+                        continue;
+                    }
+                    
+                    // Get cached window list
+                    let windows = window_cache.get_windows()?;
+                    
+                    let plan = plan_enforcement(windows, current_active, &active_target, &config, &matchers, &mut decision_cache);
+                    if let Some(owner) = &plan.owner
+                        && *owner != active_target
+                    {
+                        info!("  · Session owned by higher-priority target '{}'", owner);
+                    }
+                    for window in &plan.snap_group {
+                        info!("  · Kept snap group member: {}", window.title);
+                    }
+                    for window in &plan.shared {
+                        info!("  · Kept shared window: {}", window.title);
+                    }
+                    for window in &plan.playing_audio {
+                        info!("  · Kept window playing audio: {}", window.title);
+                    }
+                    if !plan.other_desktops.is_empty() {
+                        info!("  · Left {} windows on other virtual desktops alone", plan.other_desktops.len());
+                    }
+                    if !plan.other_monitors.is_empty() {
+                        info!("  · Left {} windows on other monitors alone", plan.other_monitors.len());
+                    }
+                    for (window, reason) in &plan.reasons {
+                        info!("  · {}: {}", reason, window.title);
+                    }
+                    
+                    let mut pass_state = PassState {
+                        scoped_ignores: &scoped_ignores,
+                        window_ages: &mut window_ages,
+                        publisher_cache: &mut publisher_cache,
+                        focus_recency: &mut focus_recency,
+                        focus_budgets: &focus_budgets,
+                        elevated,
+                    };
+                    let decision = decide_suppression(windows, current_active, &plan, &config, &mut pass_state);
+                    for (window, note) in &decision.notes {
+                        info!("  · {}: {}", note, window.title);
+                    }
+                    if decision.settling_until.is_some() {
+                        recheck_at = decision.settling_until;
+                    }
+                    for window in &decision.unreachable {
+                        warn!("  ⚠ Unreachable (runs elevated): {}", window.title);
+                    }
+                    frontends.set_unreachable_count(decision.unreachable.len());
+                    let (unreachable, mut windows_to_minimize) = (decision.unreachable, decision.suppress);
+                    
+                    // After a rule change mid-session, give back what the new rules
+                    // allow; windows they newly disallow are suppressed below as usual
+                    if handoff && previous_target.is_some() {
+                        let suppressed: Vec<HWND> = windows_to_minimize.iter().map(|window| window.hwnd).collect();
+                        let released = focus_session.release_allowed(&suppressed);
+                        let (unfaded, still_faded): (Vec<HWND>, Vec<HWND>) = faded.drain(..).partition(|hwnd| !suppressed.contains(hwnd));
+                        faded = still_faded;
+                        switcher::show_in_switcher(&released.iter().map(|(hwnd, _)| *hwnd).collect::<Vec<_>>());
+                        let given_back = released.len() + unfaded.len();
+                        restore_session_windows(released, &config);
+                        dim::unfade(&unfaded);
+                        let given_back = given_back + overlay::uncover_except(&suppressed) + switcher::unwithdraw_except(&suppressed);
+                        if given_back > 0 {
+                            info!("  · Rules changed, giving back {} windows they now allow", given_back);
+                        }
+                    }
+                    rules_changed = false;
+                    windows_to_minimize.truncate(remaining_actions);
+                    
+                    // Number everything that stays on screen for the quick-switch hotkeys
+                    if let Some(modifiers) = &config.quick_switch_modifiers {
+                        let allowed = windows
+                            .iter()
+                            .filter(|window| !is_system_window(window))
+                            .filter(|window| !windows_to_minimize.iter().any(|other| other.hwnd == window.hwnd))
+                            .map(|window| (window.hwnd, window.title.clone()))
+                            .collect();
+                        quick_switch.update(allowed, modifiers);
+                    }
+                    
+                    #[cfg(feature = "stats")]
+                    let decision_time = decision_start.elapsed();
+                    #[cfg(feature = "stats")]
+                    decision_latency.record(decision_time);
+                    #[cfg(feature = "stats")]
+                    frontends.set_decision_latency(&decision_latency.summary(decision_time));
+                    
+                    // Decide per window what the target's action does to it; opacity
+                    // effects are off in remote sessions, so windows those rules
+                    // would fade are left alone there
+                    let mut to_hide: Vec<HWND> = Vec::new();
+                    let mut to_withdraw: Vec<&WindowInfo> = Vec::new();
+                    let mut to_close: Vec<&WindowInfo> = Vec::new();
+                    let mut to_cover: Vec<&WindowInfo> = Vec::new();
+                    let (fade_alpha, to_fade, windows_to_minimize) = match config.suppress_action_for(&active_target) {
+                        SuppressAction::Fade(_) | SuppressAction::Escalate(_) if session_kind.is_remote() => {
+                            for window in &windows_to_minimize {
+                                info!("  · Left alone (no fading in a remote session): {}", window.title);
+                            }
+                            (255, Vec::new(), Vec::new())
+                        }
+                        SuppressAction::Minimize => (255, Vec::new(), windows_to_minimize),
+                        SuppressAction::Fade(alpha) => (alpha, windows_to_minimize, Vec::new()),
+                        SuppressAction::Hide => {
+                            to_withdraw = windows_to_minimize;
+                            (255, Vec::new(), Vec::new())
+                        }
+                        SuppressAction::Close => {
+                            to_close = windows_to_minimize;
+                            (255, Vec::new(), Vec::new())
+                        }
+                        SuppressAction::Overlay => {
+                            to_cover = windows_to_minimize;
+                            (255, Vec::new(), Vec::new())
+                        }
+                        SuppressAction::Nothing => {
+                            for window in &windows_to_minimize {
+                                info!("  · Left alone (rule action is nothing): {}", window.title);
+                            }
+                            (255, Vec::new(), Vec::new())
+                        }
+                        SuppressAction::Escalate(escalation) => {
+                            let mut to_fade = Vec::new();
+                            let mut to_minimize = Vec::new();
+                            for window in windows_to_minimize {
+                                let on_screen = !faded.contains(&window.hwnd) && unsafe { !IsIconic(window.hwnd).as_bool() };
+                                match escalation_counters.offense(window, on_screen, &escalation) {
+                                    EscalationLevel::Fade => to_fade.push(window),
+                                    EscalationLevel::Minimize => to_minimize.push(window),
+                                    EscalationLevel::Hide => {
+                                        to_hide.push(window.hwnd);
+                                        to_minimize.push(window);
+                                    }
+                                }
+                            }
+                            (escalation.fade_opacity, to_fade, to_minimize)
+                        }
+                    };
+                    // Windows still faded from an earlier pass stay as they are
+                    let to_fade: Vec<&WindowInfo> = to_fade.into_iter().filter(|window| !faded.contains(&window.hwnd)).collect();
+                    
+                    // Shadow mode only records what would have been done, a dry run
+                    // only prints it
+                    if config.observe_only() {
+                        let planned = [("fade", &to_fade), ("minimize", &windows_to_minimize), ("hide", &to_withdraw), ("cover", &to_cover), ("close", &to_close)];
+                        for (action, windows) in planned {
+                            for window in windows.iter() {
+                                info!("  · Would {}: {}", action, window.title);
+                            }
+                            if config.shadow_mode
+                                && !windows.is_empty()
+                                && let Err(e) = shadow::record(&active_target, action, windows)
+                            {
+                                warn!("Could not record shadow decisions: {}", e);
+                            }
+                        }
+                        rate_limiter.record(&active_target, to_fade.len() + windows_to_minimize.len() + to_withdraw.len() + to_cover.len() + to_close.len());
+                        scoped_ignores.end_pass();
+                        #[cfg(feature = "stats")]
+                        {
+                            info!("Decision latency: {}", decision_latency.summary(decision_time));
+                            record_enforcement_latency(&mut enforcement_latency, changed_at, &config, &frontends);
+                        }
+                        info!("");
+                        wait_for_next_pass(foreground_events.as_ref(), interval, &config, [recheck_at, focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+                        continue;
+                    }
+                    
+                    // The last session's windows may still be coming back in the background
+                    finish_background_restore();
+                    
+                    // Fade the windows that stay in place
+                    let mut faded_count = 0;
+                    for (process, group) in group_by_process(to_fade) {
+                        for window in fade_group(&process, &active_target, &group, fade_alpha) {
+                            faded_count += 1;
+                            focus_session.record_effect(window.hwnd, &window.title, Effect::Faded);
+                            #[cfg(feature = "stats")]
+                            interruptions.blocked(window, &active_target);
+                            if !faded.contains(&window.hwnd) {
+                                faded.push(window.hwnd);
+                            }
+                        }
+                    }
+                    
+                    // Minimize the rest, one group per process
+                    let mut minimized_count = 0;
+                    let mut restore_list_changed = false;
+                    let already_minimized: Vec<HWND> = windows_to_minimize
+                        .iter()
+                        .filter(|window| unsafe { IsIconic(window.hwnd).as_bool() })
+                        .map(|window| window.hwnd)
+                        .collect();
+                    for (process, group) in group_by_process(windows_to_minimize) {
+                        for window in minimize_group(&process, &active_target, &group) {
+                            minimized_count += 1;
+                            focus_session.record(window.hwnd, &window.title, &active_target, !already_minimized.contains(&window.hwnd));
+                            #[cfg(feature = "stats")]
+                            interruptions.blocked(window, &active_target);
+                            if config.hide_suppressed_from_switcher || to_hide.contains(&window.hwnd) {
+                                switcher::hide_from_switcher(window.hwnd);
+                                subscriptions::publish(Event::action("hide", &window.process_name, &window.title));
+                            }
+                            restore_list_changed |= restore_list.add(WindowIdentity::of(window));
+                        }
+                    }
+                    
+                    // Hide, cover or close the windows of rules that ask for it
+                    let mut hidden_count = 0;
+                    for (process, group) in group_by_process(to_withdraw) {
+                        let withdrawn = withdraw_group(&process, &active_target, &group);
+                        hidden_count += withdrawn.len();
+                        for window in withdrawn {
+                            focus_session.record_effect(window.hwnd, &window.title, Effect::Hidden);
+                            #[cfg(feature = "stats")]
+                            interruptions.blocked(window, &active_target);
+                        }
+                    }
+                    let mut covered_count = 0;
+                    for (process, group) in group_by_process(to_cover) {
+                        let covered = cover_group(&process, &active_target, &group);
+                        covered_count += covered.len();
+                        for window in covered {
+                            focus_session.record_effect(window.hwnd, &window.title, Effect::Covered);
+                            #[cfg(feature = "stats")]
+                            interruptions.blocked(window, &active_target);
+                        }
+                    }
+                    let mut closed_count = 0;
+                    for (process, group) in group_by_process(to_close) {
+                        closed_count += close_group(&process, &active_target, &group).len();
+                    }
+                    
+                    // Remember what was minimized so it can be restored after a reboot
+                    if restore_list_changed && !config.ephemeral && let Err(e) = restore_list.save() {
+                        warn!("Could not save the restore list: {}", e);
+                    }
+                    rate_limiter.record(&active_target, faded_count + minimized_count + hidden_count + covered_count + closed_count);
+                    scoped_ignores.end_pass();
+                    
+                    #[cfg(feature = "stats")]
+                    info!("Decision latency: {}", decision_latency.summary(decision_time));
+                    let parts: Vec<String> = [(faded_count, "faded"), (minimized_count, "minimized"), (hidden_count, "hidden"), (covered_count, "covered"), (closed_count, "closed"), (unreachable.len(), "unreachable")]
+                        .into_iter()
+                        .filter(|(count, _)| *count > 0)
+                        .enumerate()
+                        .map(|(i, (count, action))| if i == 0 { format!("{} windows {}", count, action) } else { format!("{} {}", count, action) })
+                        .collect();
+                    match parts.split_last() {
+                        None => info!("No other windows need to be minimized\n"),
+                        Some((only, [])) => info!("Total {}\n", only),
+                        Some((last, rest)) => info!("Total {} and {}\n", rest.join(", "), last),
+                    }
+                    #[cfg(feature = "stats")]
+                    record_enforcement_latency(&mut enforcement_latency, changed_at, &config, &frontends);
+                } else {
+                    info!("This window is not a target window\n");
+                    // Nothing stays suppressed outside a session, so there's
+                    // nothing left to hand over
+                    rules_changed = false;
+                    
+                    // Point out a pattern of going back to suppressed apps, counting
+                    // only while focus is leaving a target or a session is running
+                    #[cfg(feature = "stats")]
+                    if (previous_target.is_some() || focus_session.is_active())
+                        && let Some(message) = interruptions.focused(&active_window, &config)
+                    {
+                        info!("ℹ {}\n", message);
+                        frontends.notify(&config.app_name, &message);
+                        subscriptions::publish(Event::notification("nudge", &message));
+                    }
+                    
+                    // Focus left the targets, so apply each rule's restore behavior
+                    if let Some(previous_target) = &previous_target {
+                        subscriptions::publish(Event::session("end", Some(previous_target), None));
+                        switcher::show_all_in_switcher();
+                        restore_session_windows(focus_session.end(&config), &config);
+                        escalation_counters.clear();
+                        scoped_ignores.end_session();
+                        dim::unfade(&std::mem::take(&mut faded));
+                        overlay::uncover_all();
+                        quick_switch.clear();
+                        for (hwnd, placement) in focus_session.take_moved() {
+                            restore_placement(hwnd, &placement);
+                        }
+                    }
+                }
+            }
+        }
+        
+        // Reduced wait time for better responsiveness
+        wait_for_next_pass(foreground_events.as_ref(), interval, &config, [recheck_at, focus_session.next_due(), cursor_follower.as_ref().and_then(CursorFollower::next_check)]);
+    }
+}
+
+// Function to list what the command palette offers right now
+fn palette_entries(paused: bool, suppressed: &[(HWND, String)], targets: &[(HWND, String)], can_reload: bool) -> Vec<(String, PaletteCommand)> {
+    let mut entries = vec![if paused {
+        ("Resume monitoring".to_string(), PaletteCommand::Tray(TrayCommand::Resume))
+    } else {
+        ("Pause monitoring".to_string(), PaletteCommand::Tray(TrayCommand::Pause))
+    }];
+    entries.push(("Restore all".to_string(), PaletteCommand::Tray(TrayCommand::RestoreAll)));
+    for (hwnd, title) in suppressed {
+        entries.push((format!("Restore: {}", title), PaletteCommand::Restore(hwnd.0 as usize)));
+    }
+    for (hwnd, title) in targets {
+        entries.push((format!("Start a session: {}", title), PaletteCommand::Focus(hwnd.0 as usize)));
+    }
+    if can_reload {
+        entries.push(("Reload the config file".to_string(), PaletteCommand::ReloadConfig));
+    }
+    entries.push(("Exit".to_string(), PaletteCommand::Tray(TrayCommand::Exit)));
+    entries
+}
+
+// Function to end the focus session undoing everything it did, whatever the
+// restore rules say: un-minimize, unfade and unhide windows, move targets back
+fn restore_everything(focus_session: &mut FocusSession, faded: &mut Vec<HWND>, quick_switch: &mut QuickSwitch, config: &MonitorConfig) {
+    switcher::show_all_in_switcher();
+    restore_session_windows(focus_session.end_restoring_all(), config);
+    dim::unfade(&std::mem::take(faded));
+    overlay::uncover_all();
+    quick_switch.clear();
+    for (hwnd, placement) in focus_session.take_moved() {
+        restore_placement(hwnd, &placement);
+    }
+}
+
+// Function to undo every change before the monitor exits, including the
+// windows earlier sessions kept minimized. Those are restored right away, not
+// in the background, since the process is about to end.
+fn restore_before_exit(focus_session: &mut FocusSession, faded: &mut Vec<HWND>, quick_switch: &mut QuickSwitch, config: &MonitorConfig) {
+    finish_background_restore();
+    let windows: Vec<(HWND, String)> = focus_session
+        .end_restoring_all()
+        .into_iter()
+        .chain(focus_session.take_kept())
+        .filter(|(hwnd, _)| unsafe { IsWindow(Some(*hwnd)).as_bool() && IsIconic(*hwnd).as_bool() })
+        .collect();
+    restore_staggered(windows, Duration::ZERO, false);
+    restore_everything(focus_session, faded, quick_switch, config);
+}
+
+// Function to record how long after the focus change a pass finished,
+// warning when it went over the budget
+#[cfg(feature = "stats")]
+fn record_enforcement_latency(stats: &mut LatencyStats, changed_at: Instant, config: &MonitorConfig, frontends: &Frontends) {
+    let elapsed = changed_at.elapsed();
+    stats.record(elapsed);
+    if let Some(budget) = config.latency_budget
+        && elapsed > budget
+    {
+        warn!(
+            "⚠ Enforced {:.1} ms after the focus change, over the {:?} budget ({})\n",
+            elapsed.as_secs_f64() * 1000.0,
+            budget,
+            stats.distribution()
+        );
+    }
+    frontends.set_metrics(&stats.distribution());
+}
+
+// Function to wait before the next pass: until the foreground changes (or a
+// hotkey, or the next scheduled wake-up) with the hook, one interval without
+fn wait_for_next_pass(events: Option<&ForegroundEvents>, interval: Duration, config: &MonitorConfig, wake_at: [Option<Instant>; 3]) {
+    let Some(events) = events else {
+        thread::sleep(interval);
+        return;
+    };
+    // Housekeeping slows down with polling, e.g. under Battery Saver or
+    // over Remote Desktop
+    let slowdown = interval.as_secs_f64() / config.poll_interval.as_secs_f64().max(0.001);
+    let housekeeping = config.housekeeping_interval.mul_f64(slowdown.max(1.0));
+    let now = Instant::now();
+    let timeout = wake_at
+        .into_iter()
+        .flatten()
+        .map(|at| at.saturating_duration_since(now))
+        .fold(housekeeping, Duration::min);
+    events.wait(timeout);
+}
+
+// Function to bring back the windows minimized during the last run, matched by
+// process and normalized title since window handles don't survive a reboot
+pub fn restore_last(config: &MonitorConfig) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let list = RestoreList::load();
+    if list.windows.is_empty() {
+        info!("No windows were minimized during the last run");
+        return Ok(());
+    }
+    
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut ProcessNameCache::default())?;
+    let processes = running_processes();
+    
+    let mut to_restore = Vec::new();
+    for identity in &list.windows {
+        let matching = windows
+            .iter()
+            .find(|window| WindowIdentity::of(window).same_window(identity));
+        
+        match matching {
+            Some(window) if unsafe { IsIconic(window.hwnd).as_bool() } => {
+                to_restore.push((window.hwnd, window.title.clone()));
+            }
+            Some(window) => info!("  · Already open: {}", window.title),
+            None if processes.iter().any(|(_, name)| name.eq_ignore_ascii_case(&identity.process)) => {
+                info!("  · {} is running but has no window titled '{}'", identity.process, identity.title);
+            }
+            None => info!("  · {} is not running ('{}')", identity.process, identity.title),
+        }
+    }
+    
+    if config.audit_log {
+        audit::open()?;
+    }
+    let restored_count = to_restore.len();
+    restore_staggered(to_restore, config.restore_stagger, config.restore_activates);
+    
+    info!("Restored {} of {} windows from the last run", restored_count, list.windows.len());
+    Ok(())
+}
+
+// Function to run a single enforcement pass for one target even if it isn't
+// focused, bringing its window forward first: a scriptable desktop declutter
+pub fn clean_desktop(config: &MonitorConfig, target: Option<&str>) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    // A keyword that isn't configured becomes the top target for this pass
+    let mut config = config.clone();
+    let keyword = match target {
+        Some(keyword) => keyword.to_string(),
+        None => config.target_keywords.first().cloned().ok_or("no target keywords are configured")?,
+    };
+    if !config.target_keywords.contains(&keyword) {
+        config.target_keywords.insert(0, keyword.clone());
+    }
+    let matchers = RuleSet::new(&config)?;
+    let target_matcher = KeywordMatcher::new(&keyword)?;
+    
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut ProcessNameCache::default())?;
+    let Some(target_window) = windows
+        .iter()
+        .find(|window| !is_system_window(window) && target_matcher.is_match(&window.title_lower))
+    else {
+        return Err(format!("no open window matches '{}'", keyword).into());
+    };
+    
+    if config.audit_log {
+        audit::open()?;
+    }
+    
+    unsafe {
+        if IsIconic(target_window.hwnd).as_bool() {
+            restore_window(target_window.hwnd, true);
+        }
+        let _ = SetForegroundWindow(target_window.hwnd);
+    }
+    info!("→ Brought forward: {}", target_window.title);
+    
+    let plan = plan_enforcement(&windows, target_window.hwnd, &keyword, &config, &matchers, &mut DecisionCache::default());
+    let mut windows_to_minimize = plan.minimize;
+    if !config.trusted_publishers.is_empty() {
+        let mut publisher_cache = PublisherCache::default();
+        windows_to_minimize.retain(|window| {
+            process_image_path(window.process_id)
+                .and_then(|path| publisher_cache.trusted_publisher(&path, &config.trusted_publishers))
+                .is_none()
+        });
+    }
+    windows_to_minimize.retain(|window| unsafe { !IsIconic(window.hwnd).as_bool() });
+    
+    let mut restore_list = RestoreList::default();
+    let mut minimized_count = 0;
+    for (process, group) in group_by_process(windows_to_minimize) {
+        for window in minimize_group(&process, &keyword, &group) {
+            minimized_count += 1;
+            restore_list.add(WindowIdentity::of(window));
+        }
+    }
+    
+    // `restore-last` brings the desktop back
+    if minimized_count > 0 {
+        restore_list.save()?;
+    }
+    info!("✓ Minimized {} windows for '{}'", minimized_count, keyword);
+    Ok(())
+}
+
+// Function to time every rule against the current windows, to find slow ones
+pub fn profile_rules(config: &MonitorConfig) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let mut process_names = ProcessNameCache::default();
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut process_names)?;
+    let titles: Vec<&str> = windows.iter().map(|w| w.title_lower.as_str()).collect();
+    
+    let mut profiles = profile_keywords("target", &titles, &compile_keywords(&config.target_keywords)?);
+    profiles.extend(profile_keywords("ignored", &titles, &compile_keywords(&config.ignored_keywords)?));
+    for exception in &config.process_exceptions {
+        // Exception titles only apply to windows of their own process
+        let process_titles: Vec<&str> = windows
+            .iter()
+            .filter(|w| w.matches_process(&exception.process))
+            .map(|w| w.title_lower.as_str())
+            .collect();
+        let allowed_titles: Vec<KeywordMatcher> = exception.allowed_titles.iter().map(|t| KeywordMatcher::substring(t)).collect();
+        let mut exception_profiles = profile_keywords("exception", &process_titles, &allowed_titles);
+        for profile in &mut exception_profiles {
+            profile.rule = format!("{}: {}", exception.process, profile.rule);
+        }
+        profiles.extend(exception_profiles);
+    }
+    
+    print_profiles(profiles, windows.len());
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::IsIconic;
+
+use crate::config::ProcessLimit;
+use crate::{is_system_window, WindowInfo};
+
+// Order in which windows last had focus during this run
+#[derive(Default)]
+pub struct FocusRecency {
+    ticks: HashMap<usize, u64>,
+    next_tick: u64,
+}
+
+impl FocusRecency {
+    pub fn focused(&mut self, hwnd: HWND) {
+        self.next_tick += 1;
+        self.ticks.insert(hwnd.0 as usize, self.next_tick);
+    }
+    
+    // Forget windows that no longer exist
+    pub fn retain(&mut self, windows: &[WindowInfo]) {
+        self.ticks.retain(|hwnd, _| windows.iter().any(|window| window.hwnd.0 as usize == *hwnd));
+    }
+    
+    fn tick(&self, hwnd: HWND) -> Option<u64> {
+        self.ticks.get(&(hwnd.0 as usize)).copied()
+    }
+}
+
+// Function to find the windows over each process's limit: of the visible
+// windows the pass would keep, the least recently focused beyond the limit.
+// Windows never focused this run count as older than any focused one, and
+// keep their z-order (enumeration order) among themselves. Exempt windows
+// count towards the limit but, like the focused one, always stay.
+pub fn over_limit<'a, 'l>(
+    windows: &'a [WindowInfo],
+    minimize: &[&WindowInfo],
+    exempt: &[HWND],
+    active: HWND,
+    limits: &'l [ProcessLimit],
+    recency: &FocusRecency,
+) -> Vec<(&'a WindowInfo, &'l str)> {
+    let mut excess = Vec::new();
+    for limit in limits {
+        let mut kept: Vec<(usize, &WindowInfo)> = windows
+            .iter()
+            .enumerate()
+            .filter(|(_, window)| window.matches_process(&limit.process))
+            .filter(|(_, window)| !is_system_window(window) && unsafe { !IsIconic(window.hwnd).as_bool() })
+            .filter(|(_, window)| !minimize.iter().any(|other| other.hwnd == window.hwnd))
+            .collect();
+        if kept.len() <= limit.max_windows {
+            continue;
+        }
+        
+        // Most recently used first, after the windows that always stay
+        let stays = |window: &WindowInfo| window.hwnd == active || exempt.contains(&window.hwnd);
+        kept.sort_by_key(|(position, window)| (!stays(window), std::cmp::Reverse(recency.tick(window.hwnd)), *position));
+        excess.extend(
+            kept.into_iter()
+                .skip(limit.max_windows)
+                .filter(|(_, window)| !stays(window))
+                .map(|(_, window)| (window, limit.process.as_str())),
+        );
+    }
+    excess
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::VersionStrings;
+    
+    // Made-up handles; IsIconic is false for them, so every window is on screen
+    fn hwnd(value: usize) -> HWND {
+        HWND(value as *mut _)
+    }
+    
+    fn window(value: usize, process: &str) -> WindowInfo {
+        let title = format!("window {}", value);
+        WindowInfo {
+            hwnd: hwnd(value),
+            title_lower: title.clone(),
+            title,
+            class_name: "CabinetWClass".to_string(),
+            process_id: 1,
+            process_name: process.to_string(),
+            process_path: String::new(),
+            process_version: VersionStrings::default(),
+            process_elevated: false,
+        }
+    }
+    
+    fn limit(max_windows: usize) -> Vec<ProcessLimit> {
+        vec![ProcessLimit { process: "explorer.exe".to_string(), max_windows }]
+    }
+    
+    fn excess(windows: &[WindowInfo], minimize: &[&WindowInfo], exempt: &[HWND], limits: &[ProcessLimit], recency: &FocusRecency) -> Vec<usize> {
+        over_limit(windows, minimize, exempt, hwnd(1), limits, recency)
+            .into_iter()
+            .map(|(window, _)| window.hwnd.0 as usize)
+            .collect()
+    }
+    
+    #[test]
+    fn least_recently_focused_windows_go_first() {
+        let windows: Vec<WindowInfo> = (1..=4).map(|value| window(value, "explorer.exe")).chain([window(5, "chrome.exe")]).collect();
+        let mut recency = FocusRecency::default();
+        recency.focused(hwnd(3));
+        recency.focused(hwnd(2));
+        // 1 is active; 2 was focused last, 3 before it and 4 never
+        assert_eq!(excess(&windows, &[], &[], &limit(2), &recency), vec![3, 4]);
+        assert_eq!(excess(&windows, &[], &[], &limit(3), &recency), vec![4]);
+        assert!(excess(&windows, &[], &[], &limit(4), &recency).is_empty());
+    }
+    
+    #[test]
+    fn unfocused_windows_keep_their_z_order() {
+        let windows: Vec<WindowInfo> = (1..=4).map(|value| window(value, "explorer.exe")).collect();
+        assert_eq!(excess(&windows, &[], &[], &limit(2), &FocusRecency::default()), vec![3, 4]);
+    }
+    
+    #[test]
+    fn windows_already_minimized_by_the_pass_do_not_count() {
+        let windows: Vec<WindowInfo> = (1..=4).map(|value| window(value, "explorer.exe")).collect();
+        let minimize = [&windows[3]];
+        assert_eq!(excess(&windows, &minimize, &[], &limit(2), &FocusRecency::default()), vec![3]);
+    }
+    
+    #[test]
+    fn exempt_windows_count_but_stay() {
+        let windows: Vec<WindowInfo> = (1..=4).map(|value| window(value, "explorer.exe")).collect();
+        let exempt = [hwnd(4)];
+        assert_eq!(excess(&windows, &[], &exempt, &limit(2), &FocusRecency::default()), vec![2, 3]);
+    }
+}
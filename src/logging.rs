@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::config::{data_dir, MonitorConfig};
+
+// Daily log files kept before the oldest is deleted
+const KEEP_FILES: usize = 14;
+
+// An event's message and the fields logged with it
+#[derive(Default)]
+struct Fields {
+    message: String,
+    values: Map<String, Value>,
+}
+
+impl Fields {
+    fn insert(&mut self, field: &Field, value: Value) {
+        self.values.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.insert(field, Value::String(format!("{:?}", value)));
+        }
+    }
+    
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.insert(field, Value::from(value));
+        }
+    }
+    
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+    
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+    
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+}
+
+// Console output: only the message, so the console reads as it always has
+struct Console;
+
+impl<S, N> FormatEvent<S, N> for Console
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        writeln!(writer, "{}", fields.message)
+    }
+}
+
+// Log file output: one JSON object per line with the time, level, message
+// and every field, e.g.
+// {"time":"..","level":"DEBUG","message":"Minimized: ..","hwnd":"0x1a2b","process":"slack.exe","rule":"Trae"}
+struct JsonLines;
+
+impl<S, N> FormatEvent<S, N> for JsonLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let message = fields.message.trim();
+        // Blank lines only space out the console
+        if message.is_empty() && fields.values.is_empty() {
+            return Ok(());
+        }
+        
+        let mut line = Map::new();
+        line.insert("time".to_string(), Value::from(humantime::format_rfc3339_millis(SystemTime::now()).to_string()));
+        line.insert("level".to_string(), Value::from(event.metadata().level().as_str()));
+        line.insert("message".to_string(), Value::from(message));
+        line.extend(fields.values);
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+// Function to set up logging for the rest of the process. Warnings and errors
+// go to stderr and everything else at info level to stdout. With
+// `log_file` on, every event down to debug level (which adds one line per
+// window acted on) also goes to a daily file under %LOCALAPPDATA%\fak-opacity\logs,
+// whose directory is returned.
+pub fn init(config: &MonitorConfig) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let console = tracing_subscriber::fmt::layer()
+        .event_format(Console)
+        .with_writer(std::io::stderr.with_max_level(Level::WARN).or_else(std::io::stdout))
+        .with_filter(LevelFilter::INFO);
+    
+    let dir = if config.log_file {
+        Some(data_dir().ok_or("LOCALAPPDATA is not set, can't write a log file")?.join("logs"))
+    } else {
+        None
+    };
+    let file = match &dir {
+        Some(dir) => {
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("fak-opacity")
+                .filename_suffix("log")
+                .max_log_files(KEEP_FILES)
+                .build(dir)?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .event_format(JsonLines)
+                    .with_writer(appender)
+                    .with_filter(LevelFilter::DEBUG),
+            )
+        }
+        None => None,
+    };
+    
+    tracing_subscriber::registry().with(console).with(file).try_init()?;
+    Ok(dir)
+}
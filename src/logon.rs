@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use tracing::info;
+use windows::Win32::Foundation::FILETIME;
+use windows::Win32::System::Threading::GetSystemTimes;
+
+// How long each CPU usage sample is taken over
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Busy machines get this long past the settle period at most, so a stuck
+// process can't keep enforcement off for good
+const QUIET_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+fn ticks(time: FILETIME) -> u64 {
+    (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64
+}
+
+// Function to read the idle and total CPU time of all processors so far,
+// in 100 ns ticks
+fn cpu_times() -> Option<(u64, u64)> {
+    let (mut idle, mut kernel, mut user) = (FILETIME::default(), FILETIME::default(), FILETIME::default());
+    unsafe { GetSystemTimes(Some(&mut idle), Some(&mut kernel), Some(&mut user)) }.ok()?;
+    // Kernel time includes the idle time
+    Some((ticks(idle), ticks(kernel) + ticks(user)))
+}
+
+// Holds the first enforcement pass back after a launch at sign-in: for a
+// fixed settle period, then until CPU usage drops below a threshold, so apps
+// restoring their windows aren't minimized mid-launch and the monitor doesn't
+// add to the logon rush
+pub struct LogonSettle {
+    started: Instant,
+    settle: Duration,
+    // CPU usage (percent) that counts as quiet, None to only wait the period
+    quiet_cpu: Option<u8>,
+    // CPU times at the start of the current sample
+    sample: Option<(Instant, (u64, u64))>,
+}
+
+impl LogonSettle {
+    pub fn new(settle: Duration, quiet_cpu: Option<u8>) -> Self {
+        Self { started: Instant::now(), settle, quiet_cpu, sample: None }
+    }
+    
+    // Whether the logon has settled and enforcement can start
+    pub fn settled(&mut self) -> bool {
+        let elapsed = self.started.elapsed();
+        if elapsed < self.settle {
+            return false;
+        }
+        let Some(quiet_cpu) = self.quiet_cpu else { return true };
+        if elapsed >= self.settle + QUIET_TIMEOUT {
+            info!("ℹ CPU still busy {:?} after sign-in, starting enforcement anyway", elapsed);
+            return true;
+        }
+        
+        let Some(now) = cpu_times() else { return true };
+        match self.sample {
+            Some((at, (idle, total))) if at.elapsed() >= SAMPLE_INTERVAL => {
+                self.sample = Some((Instant::now(), now));
+                let busy = 100u64.saturating_sub((now.0 - idle) * 100 / (now.1 - total).max(1));
+                busy < quiet_cpu as u64
+            }
+            Some(_) => false,
+            None => {
+                self.sample = Some((Instant::now(), now));
+                false
+            }
+        }
+    }
+    
+    // When to check again while holding off
+    pub fn next_check(&self) -> Instant {
+        let settled_at = self.started + self.settle;
+        match self.sample {
+            Some((at, _)) => settled_at.max(at + SAMPLE_INTERVAL),
+            None => settled_at,
+        }
+    }
+}
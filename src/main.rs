@@ -1,240 +1,296 @@
-use std::collections::HashMap;
-use std::ffi::OsString;
-use std::os::windows::ffi::OsStringExt;
-use std::thread;
-use std::time::{Duration, Instant};
+mod cli;
 
-use windows::{
-    core::*,
-    Win32::Foundation::*,
-    Win32::UI::WindowsAndMessaging::*,
+use clap::Parser;
+use cli::{AuditCommand, AutostartCommand, Cli, Command, ConfigCommand, RunArgs};
+#[cfg(feature = "service")]
+use cli::ServiceCommand;
+#[cfg(feature = "update")]
+use cli::UpdateCommand;
+use fak_opacity::config::{self, MonitorConfig};
+#[cfg(feature = "update")]
+use fak_opacity::config::UpdateCheck;
+use fak_opacity::config_watch::ConfigSource;
+use fak_opacity::{
+    audit, autostart, build_info, clean_desktop, clutter, conflicts, doctor, geometry, logging, profile_rules,
+    repl, restore_last, secrets, selftest, shadow, task, watch, Monitor,
 };
+#[cfg(feature = "ipc")]
+use fak_opacity::control;
+#[cfg(feature = "service")]
+use fak_opacity::service;
+#[cfg(feature = "update")]
+use fak_opacity::update;
+use tracing::info;
 
-// Structure to store window information
-#[derive(Debug, Clone, PartialEq)]
-struct WindowInfo {
-    hwnd: HWND,
-    title: String,
-    class_name: String,
-}
-
-// Cache structure for performance optimization
-struct WindowCache {
-    windows: Vec<WindowInfo>,
-    last_update: Instant,
-    cache_duration: Duration,
-}
-
-impl WindowCache {
-    fn new() -> Self {
-        Self {
-            windows: Vec::new(),
-            last_update: Instant::now() - Duration::from_secs(1), // Force initial update
-            cache_duration: Duration::from_millis(50), // Cache for 50ms
-        }
-    }
+// Function to build the default monitoring configuration
+// You can modify the values here according to your needs; these and most
+// other settings can also be set in %APPDATA%\fak-opacity\config.toml
+// without recompiling (see config.rs for its layout)
+fn build_config() -> MonitorConfig {
+    // List of keywords for target windows (case-insensitive substrings, or
+    // regexes matched against the whole title such as "re:^Visual Studio Code - .*\\.rs$")
+    let target_keywords = vec![
+        "Trae".to_string(),
+        // Add other keywords as needed
+    ];
     
-    fn get_windows(&mut self) -> std::result::Result<&Vec<WindowInfo>, Box<dyn std::error::Error>> {
-        if self.last_update.elapsed() > self.cache_duration {
-            self.windows = get_all_windows_uncached()?;
-            self.last_update = Instant::now();
-        }
-        Ok(&self.windows)
+    // List of keywords for windows to ignore (never minimize)
+    let ignored_keywords = vec![
+        "WhatsApp".to_string(),
+        // Add other keywords as needed
+    ];
+    
+    // Per-process exceptions: windows of these processes are only kept
+    // when their title contains one of the listed keywords. The process can
+    // be the exe name or its description, e.g. "Visual Studio Code". With
+    // while_playing_audio they are also kept while the app is audible.
+    // [[exceptions]] in config.toml replaces this list.
+    let process_exceptions = vec![
+        // ProcessException {
+        //     process: "chrome.exe".to_string(),
+        //     allowed_titles: vec!["Google Docs".to_string(), "Jira".to_string()],
+        //     while_playing_audio: false,
+        // },
+        // ProcessException {
+        //     process: "Spotify.exe".to_string(),
+        //     allowed_titles: vec![],
+        //     while_playing_audio: true,
+        // },
+    ];
+    
+    // Everything else starts from the defaults in config.rs (where each setting
+    // is described); override a field here to change it, e.g.
+    // `restore_behavior: RestoreBehavior::Restore`
+    MonitorConfig {
+        target_keywords,
+        ignored_keywords,
+        process_exceptions,
+        ..MonitorConfig::default()
     }
 }
 
-// Callback function for EnumWindows
-unsafe extern "system" fn enum_windows_proc(
-    hwnd: HWND,
-    lparam: LPARAM,
-) -> BOOL {
-    let windows = unsafe { &mut *(lparam.0 as *mut Vec<WindowInfo>) };
-    
-    // Only get visible windows that are not child windows
-    if unsafe { IsWindowVisible(hwnd).as_bool() && GetParent(hwnd).unwrap_or(HWND(std::ptr::null_mut())) == HWND(std::ptr::null_mut()) } {
-        let mut title_buffer = [0u16; 256];
-        let mut class_buffer = [0u16; 256];
-        
-        let title_len = unsafe { GetWindowTextW(hwnd, &mut title_buffer) };
-        let class_len = unsafe { GetClassNameW(hwnd, &mut class_buffer) };
-        
-        if title_len > 0 {
-            let title = OsString::from_wide(&title_buffer[..title_len as usize])
-                .to_string_lossy()
-                .to_string();
-            let class_name = OsString::from_wide(&class_buffer[..class_len as usize])
-                .to_string_lossy()
-                .to_string();
-            
-            windows.push(WindowInfo {
-                hwnd,
-                title,
-                class_name,
-            });
+// Function to report the settings the monitor starts with
+fn print_settings(config: &MonitorConfig) {
+    if let Some(opacity) = config.dim_inactive_opacity {
+        info!("Dimming every window but the focused one to {}% opacity", (opacity as u32 * 100 + 127) / 255);
+        info!("Ignored keywords: {:?}", config.ignored_keywords);
+        info!("Press Ctrl+C to stop the program\n");
+        return;
+    }
+    info!("Starting optimized window monitoring...");
+    info!("Target keywords: {:?}", config.target_keywords);
+    info!("Ignored keywords: {:?}", config.ignored_keywords);
+    info!("Multiple target resolution: {:?}", config.target_resolution);
+    info!("When focus leaves the targets: {:?}", config.restore_behavior);
+    if config.shadow_mode {
+        info!("ℹ Shadow mode: decisions are recorded (see `shadow-report`) but no windows are touched");
+    }
+    if config.dry_run {
+        info!("ℹ Dry run: every decision is printed with the rule behind it, no windows are touched");
+    }
+    for exception in &config.process_exceptions {
+        info!("Process exception: {} allowed when title contains {:?}", exception.process, exception.allowed_titles);
+        if exception.while_playing_audio {
+            info!("Process exception: {} allowed while playing audio", exception.process);
         }
     }
-    
-    TRUE
+    info!("Press Ctrl+C to stop the program\n");
 }
 
-// Function to get all open windows (uncached)
-fn get_all_windows_uncached() -> std::result::Result<Vec<WindowInfo>, Box<dyn std::error::Error>> {
-    let mut windows = Vec::with_capacity(50); // Pre-allocate for better performance
-    
-    unsafe {
-        EnumWindows(
-            Some(enum_windows_proc),
-            LPARAM(&mut windows as *mut _ as isize),
-        )?;
-    }
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    // Geometry checks compare physical pixels across monitors
+    geometry::enable_per_monitor_dpi_awareness();
     
-    Ok(windows)
-}
-
-// Function to minimize window
-fn minimize_window(hwnd: HWND) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    unsafe {
-        let _ = ShowWindow(hwnd, SW_MINIMIZE);
+    let cli = Cli::parse();
+    if cli.version {
+        println!("{}", build_info::version_string());
+        return Ok(());
     }
-    Ok(())
-}
-
-// Optimized function to check if window title contains specific keywords
-fn is_target_window(window: &WindowInfo, target_keywords: &[String], keyword_cache: &HashMap<String, String>) -> bool {
-    let title_lower = window.title.to_lowercase();
-    target_keywords.iter().any(|keyword| {
-        let keyword_lower = keyword_cache.get(keyword).unwrap();
-        title_lower.contains(keyword_lower)
-    })
-}
-
-// Function to check if window should be skipped (system windows and ignored windows)
-fn should_skip_window(window: &WindowInfo, ignored_keywords: &[String], ignored_cache: &HashMap<String, String>) -> bool {
-    // Skip empty titles and system windows
-    if window.title.is_empty() ||
-       window.title.contains("Program Manager") ||
-       window.title.contains("Desktop") ||
-       window.class_name.contains("Shell_TrayWnd") {
-        return true;
+    
+    // Built-in defaults, overridden by %APPDATA%\fak-opacity\config.toml when
+    // present, then by command-line arguments. An ephemeral run skips the
+    // file and writes no logs, so it is decided before either happens.
+    let mut config = build_config();
+    let ephemeral = matches!(&cli.command, Some(Command::Run(run_args)) if run_args.ephemeral);
+    if ephemeral {
+        config.ephemeral();
     }
+    let config_file = config::config_path().filter(|_| !ephemeral);
+    let config_loaded = match &config_file {
+        Some(path) => config.apply_file(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => false,
+    };
+    let log_dir = logging::init(&config)?;
     
-    // Skip windows that match ignored keywords
-    let title_lower = window.title.to_lowercase();
-    ignored_keywords.iter().any(|keyword| {
-        let keyword_lower = ignored_cache.get(keyword).unwrap();
-        title_lower.contains(keyword_lower)
-    })
-}
-
-// Optimized main function for window monitoring
-fn monitor_windows(target_keywords: Vec<String>, ignored_keywords: Vec<String>) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    println!("Starting optimized window monitoring...");
-    println!("Target keywords: {:?}", target_keywords);
-    println!("Ignored keywords: {:?}", ignored_keywords);
-    println!("Press Ctrl+C to stop the program\n");
-    
-    // Pre-compute lowercase keywords for faster comparison
-    let keyword_cache: HashMap<String, String> = target_keywords
-        .iter()
-        .map(|k| (k.clone(), k.to_lowercase()))
-        .collect();
-    
-    let ignored_cache: HashMap<String, String> = ignored_keywords
-        .iter()
-        .map(|k| (k.clone(), k.to_lowercase()))
-        .collect();
-    
-    let mut last_active_window: Option<HWND> = None;
-    let mut window_cache = WindowCache::new();
-    
-    loop {
-        // Get currently active window
-        let current_active = unsafe { GetForegroundWindow() };
-        
-        // Only process if active window changed
-        if last_active_window != Some(current_active) {
-            last_active_window = Some(current_active);
-            
-            // Get cached window list
-            let windows = window_cache.get_windows()?;
-            
-            // Find active window in list using early exit
-            if let Some(active_window) = windows.iter().find(|w| w.hwnd == current_active) {
-                println!("Active window: {}", active_window.title);
-                
-                // Check if active window is target window
-                if is_target_window(active_window, &target_keywords, &keyword_cache) {
-                    println!("✓ Target window detected: {}", active_window.title);
-                    
-                    // Collect windows to minimize (filter first, then minimize)
-                    let windows_to_minimize: Vec<&WindowInfo> = windows
-                        .iter()
-                        .filter(|window| {
-                            window.hwnd != current_active &&
-                            !is_target_window(window, &target_keywords, &keyword_cache) &&
-                            !should_skip_window(window, &ignored_keywords, &ignored_cache)
-                        })
-                        .collect();
-                    
-                    // Minimize collected windows
-                    let mut minimized_count = 0;
-                    for window in windows_to_minimize {
-                        if let Err(e) = minimize_window(window.hwnd) {
-                            eprintln!("Error minimizing {}: {}", window.title, e);
-                        } else {
-                            println!("  → Minimized: {}", window.title);
-                            minimized_count += 1;
-                        }
-                    }
-                    
-                    if minimized_count > 0 {
-                        println!("Total {} windows minimized\n", minimized_count);
-                    } else {
-                        println!("No other windows need to be minimized\n");
-                    }
-                } else {
-                    println!("This window is not a target window\n");
-                }
+    let run_args = match cli.command.unwrap_or(Command::Run(RunArgs::default())) {
+        Command::Run(run_args) => run_args,
+        #[cfg(feature = "ipc")]
+        Command::Embedded { app_name, pipe, run_args } => {
+            // Before the console goes, so a missing token is still reported
+            let tokens = control::tokens_from_env()?;
+            control::silence_console();
+            config.embed(&app_name, pipe.unwrap_or_else(|| format!("{}-fak-opacity", app_name)), tokens);
+            run_args
+        }
+        Command::InstallTask { highest, user, run_args } => {
+            // Catch typos now rather than at the next sign-in
+            Cli::try_parse_from(["fak-opacity", "run"].into_iter().map(String::from).chain(run_args.iter().cloned()))?;
+            return task::install_task(&run_args, highest, user);
+        }
+        Command::UninstallTask => return task::uninstall_task(),
+        Command::Autostart { command: AutostartCommand::Enable { run_args } } => {
+            Cli::try_parse_from(["fak-opacity", "run"].into_iter().map(String::from).chain(run_args.iter().cloned()))?;
+            return autostart::enable(&run_args);
+        }
+        Command::Autostart { command: AutostartCommand::Disable } => return autostart::disable(),
+        #[cfg(feature = "service")]
+        Command::Service { command } => {
+            return match command {
+                ServiceCommand::Install => service::install(),
+                ServiceCommand::Uninstall => service::uninstall(),
+                ServiceCommand::Start => service::start(),
+                ServiceCommand::Stop => service::stop(),
+                ServiceCommand::Run => service::run(),
+            };
+        }
+        #[cfg(feature = "update")]
+        Command::Update { command: UpdateCommand::Check } => {
+            return update::run_update_check(config.update_check, config.update_proxy.as_deref());
+        }
+        Command::Audit { command: AuditCommand::Verify } => {
+            if !audit::verify()? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Command::Config { command: ConfigCommand::EncryptSecrets } => {
+            let Some(path) = config_file.filter(|_| config_loaded) else {
+                println!("ℹ No config file to encrypt");
+                return Ok(());
+            };
+            let count = secrets::encrypt_secrets(&path)?;
+            if count == 0 {
+                println!("ℹ No plaintext secrets in {}", path.display());
+            }
+            return Ok(());
+        }
+        Command::ShadowReport => return shadow::run_shadow_report(),
+        Command::Clutter { interactive } => return clutter::run_clutter(&config, interactive),
+        Command::Clean { target } => return clean_desktop(&config, target.as_deref()),
+        Command::RestoreLast => return restore_last(&config),
+        Command::Watch { process } => return watch::run_watch(&process, &config),
+        Command::Doctor => {
+            doctor::run_doctor(&config);
+            return Ok(());
+        }
+        Command::Status { pipe } => {
+            println!("{}", build_info::version_string());
+            match config_file.filter(|_| config_loaded) {
+                Some(path) => println!("· Config: {}", path.display()),
+                None => println!("· Config: built-in defaults"),
             }
+            #[cfg(feature = "ipc")]
+            match pipe {
+                Some(pipe) => println!("✓ {}: {}", pipe, control::query_status(&pipe)?),
+                None => println!("ℹ A running monitor reports its state over its control pipe, see --pipe"),
+            }
+            #[cfg(not(feature = "ipc"))]
+            if pipe.is_some() {
+                println!("⚠ Built without the ipc feature, no control pipe to ask");
+            }
+            return Ok(());
         }
-        
-        // Reduced wait time for better responsiveness
-        thread::sleep(Duration::from_millis(100));
+        Command::ProfileRules => return profile_rules(&config),
+        Command::Repl => {
+            // `reload` rebuilds the rules the way startup did
+            let source = config_file.filter(|_| config_loaded).map(|path| ConfigSource {
+                path: path.clone(),
+                load: Box::new(move || {
+                    let mut config = build_config();
+                    config.apply_file(&path)?;
+                    Ok(config)
+                }),
+            });
+            return repl::run_repl(config, source);
+        }
+        Command::Selftest => {
+            if !selftest::run_selftest(&config)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+    run_args.apply(&mut config);
+    
+    info!("Window Monitor for Windows");
+    info!("{}", build_info::version_string());
+    info!("This program will minimize other windows when target windows are opened\n");
+    
+    match (&config_file, config_loaded) {
+        _ if ephemeral => info!("Config: command-line flags only (ephemeral, nothing is written to disk)\n"),
+        (Some(path), true) => info!("Config: {}\n", path.display()),
+        (Some(path), false) => info!("Config: built-in defaults (no file at {})\n", path.display()),
+        (None, _) => info!("Config: built-in defaults (APPDATA is not set)\n"),
     }
-}
-
-fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
-    println!("Window Monitor for Windows");
-    println!("This program will minimize other windows when target windows are opened\n");
     
-    // List of keywords for target windows
-    // You can modify this according to your needs
-    let target_keywords = vec![
-        "Trae".to_string(),
-        // Add other keywords as needed
-    ];
+    info!("Target windows to monitor:");
+    for keyword in &config.target_keywords {
+        info!("  - Windows containing: '{}'", keyword);
+    }
+    info!("");
     
-    // List of keywords for windows to ignore (never minimize)
-    // You can modify this according to your needs
-    let ignored_keywords = vec![
-        "WhatsApp".to_string(),
-        // Add other keywords as needed
-    ];
+    info!("Windows to ignore (never minimize):");
+    for keyword in &config.ignored_keywords {
+        info!("  - Windows containing: '{}'", keyword);
+    }
+    info!("");
+    
+    // Warn about other tools that act on the same windows
+    let conflicts = conflicts::detect_conflicts();
+    conflicts::warn_about_conflicts(&conflicts);
     
-    println!("Target windows to monitor:");
-    for keyword in &target_keywords {
-        println!("  - Windows containing: '{}'", keyword);
+    if let Some(dir) = &log_dir {
+        info!("Log files: {}\n", dir.display());
     }
-    println!();
     
-    println!("Windows to ignore (never minimize):");
-    for keyword in &ignored_keywords {
-        println!("  - Windows containing: '{}'", keyword);
+    // Start the audit trail with the settings this run enforces
+    if config.audit_log {
+        let path = audit::open()?;
+        info!("Audit log: {}\n", path.display());
+        let source = match (&config_file, config_loaded) {
+            (Some(path), true) => {
+                let text = std::fs::read_to_string(path).unwrap_or_default();
+                format!("{} (sha256 {})", path.display(), audit::sha256_hex(&text))
+            }
+            _ => "built-in defaults".to_string(),
+        };
+        audit::record("config", &format!(
+            "{} with {}, effective settings sha256 {}",
+            config.app_name,
+            source,
+            audit::sha256_hex(&format!("{:?}", config))
+        ));
+    }
+    
+    #[cfg(feature = "update")]
+    if config.update_check == UpdateCheck::Background {
+        update::spawn_background_check(config.update_proxy.clone());
     }
-    println!();
     
-    // Start monitoring
-    monitor_windows(target_keywords, ignored_keywords)?;
+    print_settings(&config);
+    
+    // Start monitoring, rebuilding the configuration the same way whenever
+    // the config file changes
+    let source = config_file.map(|path| ConfigSource {
+        path: path.clone(),
+        load: Box::new(move || {
+            let mut config = build_config();
+            config.apply_file(&path)?;
+            run_args.apply(&mut config);
+            Ok(config)
+        }),
+    });
+    Monitor::new(config, source).run()?;
     
     Ok(())
 }
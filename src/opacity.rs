@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use windows::Win32::Foundation::HWND;
+
+use crate::config::data_dir;
+use crate::{dim, WindowInfo};
+
+// One press of the opacity hotkeys changes the opacity by about 10%
+const OPACITY_STEP: i16 = 26;
+
+// Windows are never made more transparent than this, so they can still be found
+const MIN_OPACITY: u8 = 26;
+
+fn presets_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("opacity-presets.json"))
+}
+
+fn percent(alpha: u8) -> u32 {
+    (alpha as u32 * 100 + 127) / 255
+}
+
+// Function to make the window more (up) or less opaque by one step, returning
+// the new opacity or None when the window can't be made transparent
+pub fn step_opacity(window: &WindowInfo, up: bool) -> Option<u8> {
+    let step = if up { OPACITY_STEP } else { -OPACITY_STEP };
+    let alpha = (dim::opacity(window.hwnd) as i16 + step).clamp(MIN_OPACITY as i16, 255) as u8;
+    if !dim::set_manual_opacity(window.hwnd, alpha) {
+        warn!("⚠ {} manages its own transparency, leaving it alone", window.title);
+        return None;
+    }
+    info!("Opacity {}%: {}", percent(alpha), window.title);
+    Some(alpha)
+}
+
+// Opacity picked by hand per process, kept across runs in
+// opacity-presets.json and applied when one of its windows is focused
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OpacityPresets {
+    // Lowercased process name -> opacity (0-255)
+    presets: BTreeMap<String, u8>,
+    // Windows the presets were applied to already
+    #[serde(skip)]
+    applied: Vec<HWND>,
+}
+
+impl OpacityPresets {
+    // Load the stored presets, empty if there are none
+    pub fn load() -> Self {
+        presets_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    
+    fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let path = presets_path().ok_or("LOCALAPPDATA is not set")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    // Remember the window's current opacity for its app; full opacity
+    // removes the app's preset
+    pub fn remember(&mut self, window: &WindowInfo) {
+        let process = window.process_name.to_lowercase();
+        let alpha = dim::opacity(window.hwnd);
+        if alpha == 255 {
+            self.presets.remove(&process);
+        } else {
+            self.presets.insert(process, alpha);
+        }
+        match self.save() {
+            Ok(()) if alpha == 255 => info!("✓ Removed the opacity preset for {}", window.process_name),
+            Ok(()) => info!("✓ Saved {}% opacity as the preset for {}", percent(alpha), window.process_name),
+            Err(e) => warn!("Could not save the opacity presets: {}", e),
+        }
+    }
+    
+    // Give a newly focused window its app's preset, once per window
+    pub fn focused(&mut self, window: &WindowInfo) {
+        if self.applied.contains(&window.hwnd) {
+            return;
+        }
+        let Some(&alpha) = self.presets.get(&window.process_name.to_lowercase()) else { return };
+        self.applied.push(window.hwnd);
+        if dim::opacity(window.hwnd) == 255 && dim::set_manual_opacity(window.hwnd, alpha) {
+            info!("  · Applied the {}% opacity preset for {}", percent(alpha), window.process_name);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::Graphics::Gdi::*,
+    Win32::System::LibraryLoader::GetModuleHandleW,
+    Win32::System::Threading::GetCurrentThreadId,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::geometry::{is_cloaked, visible_frame_rect};
+
+// How often overlays catch up with the windows they cover moving, resizing
+// or being raised
+const TRACK_INTERVAL_MS: u32 = 100;
+
+// Thread message asking the overlay thread to catch up right away
+const WM_RECONCILE: u32 = WM_APP;
+
+// Windows to cover; the overlay thread keeps one overlay over each
+static COVERED: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+// Overlay thread, started with the first window covered (0 = not running)
+static OVERLAY_THREAD: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            unsafe {
+                let mut paint = PAINTSTRUCT::default();
+                let dc = BeginPaint(hwnd, &mut paint);
+                let mut rect = RECT::default();
+                let _ = GetClientRect(hwnd, &mut rect);
+                let background = CreateSolidBrush(COLORREF(0x0020_2020));
+                FillRect(dc, &rect, background);
+                let _ = DeleteObject(HGDIOBJ(background.0));
+                SetBkMode(dc, TRANSPARENT);
+                SetTextColor(dc, COLORREF(0x00C0_C0C0));
+                let mut text: Vec<u16> = "Blocked during focus session".encode_utf16().collect();
+                DrawTextW(dc, &mut text, &mut rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE);
+                let _ = EndPaint(hwnd, &paint);
+            }
+            LRESULT(0)
+        }
+        // Swallow clicks without taking focus from the target
+        WM_MOUSEACTIVATE => LRESULT(MA_NOACTIVATE as isize),
+        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+}
+
+// Function to create an overlay window, hidden until it is positioned. It has
+// no title, so passes count it as a system window and never act on it.
+fn create_overlay(instance: HINSTANCE) -> Option<HWND> {
+    unsafe {
+        CreateWindowExW(
+            WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+            w!("fak-opacity-overlay"),
+            w!(""),
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance),
+            None,
+        )
+        .ok()
+    }
+}
+
+// Function to put an overlay exactly over its window, directly above it in
+// the z-order, or hide it while the window is minimized or elsewhere
+fn track(overlay: HWND, covered: HWND) {
+    unsafe {
+        let hidden = !IsWindowVisible(covered).as_bool() || IsIconic(covered).as_bool() || is_cloaked(covered);
+        let Some(rect) = visible_frame_rect(covered).filter(|_| !hidden) else {
+            let _ = ShowWindow(overlay, SW_HIDE);
+            return;
+        };
+        // Whatever is right above the covered window goes above the overlay too
+        let above = GetWindow(covered, GW_HWNDPREV).ok();
+        let (insert_after, flags) = match above {
+            Some(above) if above == overlay => (None, SWP_NOZORDER),
+            Some(above) => (Some(above), SET_WINDOW_POS_FLAGS(0)),
+            None => (Some(HWND_TOP), SET_WINDOW_POS_FLAGS(0)),
+        };
+        let _ = SetWindowPos(
+            overlay,
+            insert_after,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            flags | SWP_NOACTIVATE | SWP_SHOWWINDOW,
+        );
+    }
+}
+
+// Function to bring the overlays in line with the windows to cover: create
+// missing ones, drop ones no longer wanted and follow the covered windows
+fn reconcile(instance: HINSTANCE, overlays: &mut Vec<(usize, HWND)>) {
+    let mut covered = COVERED.lock().unwrap_or_else(|e| e.into_inner());
+    // Closed windows need no covering
+    covered.retain(|&hwnd| unsafe { IsWindow(Some(HWND(hwnd as *mut _))).as_bool() });
+    
+    overlays.retain(|&(hwnd, overlay)| {
+        let wanted = covered.contains(&hwnd);
+        if !wanted {
+            let _ = unsafe { DestroyWindow(overlay) };
+        }
+        wanted
+    });
+    for &hwnd in covered.iter() {
+        let existing = overlays.iter().find(|&&(other, _)| other == hwnd).map(|&(_, overlay)| overlay);
+        let Some(overlay) = existing.or_else(|| create_overlay(instance)) else { continue };
+        if existing.is_none() {
+            overlays.push((hwnd, overlay));
+        }
+        track(overlay, HWND(hwnd as *mut _));
+    }
+}
+
+// Function to run the overlay thread: its windows and a timer that keeps them
+// following the windows they cover
+fn run_overlays(ready: mpsc::Sender<u32>) {
+    unsafe {
+        let instance: HINSTANCE = GetModuleHandleW(None).map(|module| module.into()).unwrap_or_default();
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            hCursor: LoadCursorW(None, IDC_NO).unwrap_or_default(),
+            lpszClassName: w!("fak-opacity-overlay"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+        SetTimer(None, 0, TRACK_INTERVAL_MS, None);
+        let _ = ready.send(GetCurrentThreadId());
+        
+        let mut overlays: Vec<(usize, HWND)> = Vec::new();
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            if msg.hwnd.is_invalid() && matches!(msg.message, WM_TIMER | WM_RECONCILE) {
+                reconcile(instance, &mut overlays);
+                continue;
+            }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// Function to have the overlay thread catch up now, starting it if needed
+fn wake() {
+    let mut thread_id = OVERLAY_THREAD.load(Ordering::Relaxed);
+    if thread_id == 0 {
+        let (ready, started) = mpsc::channel();
+        thread::spawn(move || run_overlays(ready));
+        thread_id = started.recv().unwrap_or(0);
+        OVERLAY_THREAD.store(thread_id, Ordering::Relaxed);
+    }
+    unsafe {
+        let _ = PostThreadMessageW(thread_id, WM_RECONCILE, WPARAM(0), LPARAM(0));
+    }
+}
+
+// Function to cover windows with an opaque overlay until uncovered; the
+// windows themselves aren't changed in any way. Returns the ones newly covered.
+pub fn cover(windows: &[HWND]) -> Vec<HWND> {
+    let mut newly_covered = Vec::new();
+    {
+        let mut covered = COVERED.lock().unwrap_or_else(|e| e.into_inner());
+        for &hwnd in windows {
+            if unsafe { IsIconic(hwnd).as_bool() } || covered.contains(&(hwnd.0 as usize)) {
+                continue;
+            }
+            covered.push(hwnd.0 as usize);
+            newly_covered.push(hwnd);
+        }
+    }
+    if !newly_covered.is_empty() {
+        wake();
+    }
+    newly_covered
+}
+
+// Function to take the overlays off the windows picked by `uncover`.
+// Returns how many came off.
+fn uncover_where(uncover: impl Fn(HWND) -> bool) -> usize {
+    let removed = {
+        let mut covered = COVERED.lock().unwrap_or_else(|e| e.into_inner());
+        let before = covered.len();
+        covered.retain(|&hwnd| !uncover(HWND(hwnd as *mut _)));
+        before - covered.len()
+    };
+    if removed > 0 {
+        wake();
+    }
+    removed
+}
+
+// Function to take the overlays off the given windows
+pub fn uncover(windows: &[HWND]) {
+    uncover_where(|hwnd| windows.contains(&hwnd));
+}
+
+// Function to take the overlays off every window except the given ones
+pub fn uncover_except(keep: &[HWND]) -> usize {
+    uncover_where(|hwnd| !keep.contains(&hwnd))
+}
+
+// Function to take every overlay off
+pub fn uncover_all() {
+    uncover_except(&[]);
+}
@@ -0,0 +1,312 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::Graphics::Gdi::{GetStockObject, GetSysColorBrush, COLOR_WINDOW, DEFAULT_GUI_FONT},
+    Win32::System::LibraryLoader::GetModuleHandleW,
+    Win32::System::Threading::GetCurrentThreadId,
+    Win32::UI::Input::KeyboardAndMouse::{SetFocus, VK_DOWN, VK_ESCAPE, VK_RETURN, VK_UP},
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::events::WM_WAKE;
+use crate::frontend::TrayCommand;
+use crate::geometry::scale_for_window;
+use crate::placement::cursor_work_area;
+
+// Size of the palette and its margins, in 96-DPI pixels
+const WIDTH: i32 = 560;
+const HEIGHT: i32 = 320;
+const MARGIN: i32 = 8;
+const SEARCH_HEIGHT: i32 = 26;
+
+// Control ids of the search box and the list of matching commands
+const ID_SEARCH: usize = 1;
+const ID_LIST: usize = 2;
+
+// Something picked from the palette, carried out by the monitor loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteCommand {
+    // The same commands the tray menu has
+    Tray(TrayCommand),
+    // Give one window suppressed this session back (the handle as usize)
+    Restore(usize),
+    // Bring a target window to the front, starting its session
+    Focus(usize),
+    // Load the config file again now
+    ReloadConfig,
+}
+
+// The open palette, shared with its window procedure
+struct PaletteState {
+    entries: Vec<(String, PaletteCommand)>,
+    // Indices into entries of the ones matching the search, best first
+    shown: Vec<usize>,
+    search: usize,
+    list: usize,
+    picks: Sender<PaletteCommand>,
+}
+
+static STATE: Mutex<Option<PaletteState>> = Mutex::new(None);
+static OPEN: AtomicBool = AtomicBool::new(false);
+static MONITOR_THREAD: AtomicU32 = AtomicU32::new(0);
+
+// Function to score how well a search matches a label: every character of
+// the search has to appear in order; runs of them and word starts count
+// more. None when it doesn't match at all.
+fn fuzzy_score(search: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in search.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + label[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+// Function to fill the list with the entries matching the search box
+fn refresh(state: &mut PaletteState) {
+    let mut buffer = [0u16; 256];
+    let len = unsafe { GetWindowTextW(HWND(state.search as *mut _), &mut buffer) };
+    let search = String::from_utf16_lossy(&buffer[..len.max(0) as usize]);
+    
+    let mut scored: Vec<(i32, usize)> = state
+        .entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (label, _))| fuzzy_score(&search, label).map(|score| (score, i)))
+        .collect();
+    // Stable, so equally good matches keep their order
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    state.shown = scored.into_iter().map(|(_, i)| i).collect();
+    
+    let list = HWND(state.list as *mut _);
+    unsafe {
+        SendMessageW(list, LB_RESETCONTENT, None, None);
+        for &i in &state.shown {
+            let label = HSTRING::from(state.entries[i].0.as_str());
+            SendMessageW(list, LB_ADDSTRING, None, Some(LPARAM(label.as_ptr() as isize)));
+        }
+        SendMessageW(list, LB_SETCURSEL, Some(WPARAM(0)), None);
+    }
+}
+
+// Function to move the selection up or down the list
+fn move_selection(state: &PaletteState, down: bool) {
+    if state.shown.is_empty() {
+        return;
+    }
+    let list = HWND(state.list as *mut _);
+    let current = unsafe { SendMessageW(list, LB_GETCURSEL, None, None) }.0.max(0) as usize;
+    let next = if down { (current + 1).min(state.shown.len() - 1) } else { current.saturating_sub(1) };
+    unsafe {
+        SendMessageW(list, LB_SETCURSEL, Some(WPARAM(next)), None);
+    }
+}
+
+// Function to hand the selected command to the monitor loop and close
+fn choose(hwnd: HWND) {
+    if let Some(state) = STATE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        let selected = unsafe { SendMessageW(HWND(state.list as *mut _), LB_GETCURSEL, None, None) }.0;
+        if let Some(&i) = usize::try_from(selected).ok().and_then(|selected| state.shown.get(selected)) {
+            let _ = state.picks.send(state.entries[i].1);
+            unsafe {
+                let _ = PostThreadMessageW(MONITOR_THREAD.load(Ordering::Relaxed), WM_WAKE, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+    unsafe {
+        let _ = DestroyWindow(hwnd);
+    }
+}
+
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_COMMAND => {
+            let (id, notification) = (wparam.0 & 0xFFFF, (wparam.0 >> 16) as u32 & 0xFFFF);
+            match (id, notification) {
+                (ID_SEARCH, EN_CHANGE) => {
+                    if let Some(state) = STATE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                        refresh(state);
+                    }
+                }
+                (ID_LIST, LBN_DBLCLK) => choose(hwnd),
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        // Clicking anywhere else closes the palette
+        WM_ACTIVATE if (wparam.0 & 0xFFFF) as u32 == WA_INACTIVE => {
+            unsafe {
+                let _ = DestroyWindow(hwnd);
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            unsafe { PostQuitMessage(0) };
+            LRESULT(0)
+        }
+        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+}
+
+// Function to create a child control with the dialog font
+fn create_control(parent: HWND, instance: HINSTANCE, class: PCWSTR, style: WINDOW_STYLE, id: usize) -> Option<HWND> {
+    unsafe {
+        let control = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class,
+            w!(""),
+            WS_CHILD | WS_VISIBLE | WS_BORDER | style,
+            0,
+            0,
+            0,
+            0,
+            Some(parent),
+            Some(HMENU(id as *mut _)),
+            Some(instance),
+            None,
+        )
+        .ok()?;
+        SendMessageW(control, WM_SETFONT, Some(WPARAM(GetStockObject(DEFAULT_GUI_FONT).0 as usize)), Some(LPARAM(1)));
+        Some(control)
+    }
+}
+
+// Function to run the palette window until something is picked or it closes
+fn run_palette(entries: Vec<(String, PaletteCommand)>, picks: Sender<PaletteCommand>) {
+    unsafe {
+        let instance: HINSTANCE = GetModuleHandleW(None).map(|module| module.into()).unwrap_or_default();
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: GetSysColorBrush(COLOR_WINDOW),
+            lpszClassName: w!("fak-opacity-palette"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+        
+        // No title, so passes don't take the palette for a window to act on
+        let hwnd = CreateWindowExW(
+            WS_EX_TOPMOST | WS_EX_TOOLWINDOW,
+            w!("fak-opacity-palette"),
+            w!(""),
+            WS_POPUP | WS_BORDER,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance),
+            None,
+        );
+        let Ok(hwnd) = hwnd else { return };
+        let search = create_control(hwnd, instance, w!("EDIT"), WINDOW_STYLE(ES_AUTOHSCROLL as u32), ID_SEARCH);
+        let list = create_control(hwnd, instance, w!("LISTBOX"), WS_VSCROLL | WINDOW_STYLE(LBS_NOTIFY as u32), ID_LIST);
+        let (Some(search), Some(list)) = (search, list) else {
+            let _ = DestroyWindow(hwnd);
+            return;
+        };
+        
+        // Centered on the monitor the mouse is on
+        let (width, height) = (scale_for_window(WIDTH, hwnd), scale_for_window(HEIGHT, hwnd));
+        let (margin, search_height) = (scale_for_window(MARGIN, hwnd), scale_for_window(SEARCH_HEIGHT, hwnd));
+        let area = cursor_work_area().unwrap_or_default();
+        let left = area.left + (area.right - area.left - width) / 2;
+        let top = area.top + (area.bottom - area.top - height) / 3;
+        let _ = MoveWindow(hwnd, left, top, width, height, false);
+        let inner = width - 2 * margin;
+        let _ = MoveWindow(search, margin, margin, inner, search_height, false);
+        let list_top = 2 * margin + search_height;
+        let _ = MoveWindow(list, margin, list_top, inner, height - list_top - margin, false);
+        
+        let mut state = PaletteState {
+            entries,
+            shown: Vec::new(),
+            search: search.0 as usize,
+            list: list.0 as usize,
+            picks,
+        };
+        refresh(&mut state);
+        *STATE.lock().unwrap_or_else(|e| e.into_inner()) = Some(state);
+        
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        // The hotkey press lets this process take the foreground
+        let _ = SetForegroundWindow(hwnd);
+        let _ = SetFocus(Some(search));
+        
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // Arrow keys, Enter and Escape work wherever the keyboard focus is
+            if msg.message == WM_KEYDOWN {
+                let key = msg.wParam.0 as u16;
+                if key == VK_UP.0 || key == VK_DOWN.0 {
+                    if let Some(state) = STATE.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+                        move_selection(state, key == VK_DOWN.0);
+                    }
+                    continue;
+                }
+                if key == VK_RETURN.0 {
+                    choose(hwnd);
+                    continue;
+                }
+                if key == VK_ESCAPE.0 {
+                    let _ = DestroyWindow(hwnd);
+                    continue;
+                }
+            }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        *STATE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+// Keyboard-driven list of everything the monitor can do: type to narrow it
+// down, arrow keys to pick, Enter to run and Escape to close. Each opening
+// runs on its own thread.
+pub struct CommandPalette {
+    sender: Sender<PaletteCommand>,
+    picks: Receiver<PaletteCommand>,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        MONITOR_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+        let (sender, picks) = mpsc::channel();
+        Self { sender, picks }
+    }
+    
+    // Show the palette with these entries, unless it is open already
+    pub fn open(&self, entries: Vec<(String, PaletteCommand)>) {
+        if OPEN.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            run_palette(entries, sender);
+            OPEN.store(false, Ordering::Relaxed);
+        });
+    }
+    
+    // Commands picked since the last call
+    pub fn picks(&self) -> Vec<PaletteCommand> {
+        self.picks.try_iter().collect()
+    }
+}
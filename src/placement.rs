@@ -0,0 +1,173 @@
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::time::{Duration, Instant};
+
+use windows::{
+    core::BOOL,
+    Win32::Foundation::*,
+    Win32::Graphics::Gdi::{
+        EnumDisplayMonitors, GetMonitorInfoW, MonitorFromPoint, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+        MONITOR_DEFAULTTONEAREST,
+    },
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::geometry::monitor_work_area;
+
+// Callback collecting every monitor's device name and work area
+unsafe extern "system" fn enum_monitors_proc(monitor: HMONITOR, _dc: HDC, _rect: *mut RECT, lparam: LPARAM) -> BOOL {
+    let monitors = unsafe { &mut *(lparam.0 as *mut Vec<(String, RECT)>) };
+    let mut info = MONITORINFOEXW {
+        monitorInfo: MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    if unsafe { GetMonitorInfoW(monitor, &mut info.monitorInfo) }.as_bool() {
+        let len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
+        let device = OsString::from_wide(&info.szDevice[..len]).to_string_lossy().to_string();
+        monitors.push((device, info.monitorInfo.rcWork));
+    }
+    TRUE
+}
+
+// Get the work area of a monitor by its display number, as in
+// "\\.\DISPLAY2" (the number Windows shows when identifying displays)
+fn monitor_work_area_by_number(number: u32) -> Option<RECT> {
+    let mut monitors: Vec<(String, RECT)> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(None, None, Some(enum_monitors_proc), LPARAM(&mut monitors as *mut _ as isize));
+    }
+    let suffix = format!("DISPLAY{}", number);
+    monitors
+        .into_iter()
+        .find(|(device, _)| device.ends_with(&suffix))
+        .map(|(_, work_area)| work_area)
+}
+
+// Map a coordinate from one span onto another, keeping its relative position
+fn map_span(value: i32, from_start: i32, from_len: i32, to_start: i32, to_len: i32) -> i32 {
+    if from_len <= 0 {
+        return to_start;
+    }
+    to_start + ((value - from_start) as i64 * to_len as i64 / from_len as i64) as i32
+}
+
+// Move a window onto the given monitor, keeping its relative position and
+// size within the work area (and its maximized state). Returns the original
+// placement so it can be put back, or None if it was already there.
+pub fn move_to_monitor(hwnd: HWND, number: u32) -> Option<WINDOWPLACEMENT> {
+    move_to_work_area(hwnd, monitor_work_area_by_number(number)?)
+}
+
+// Get the work area of the monitor the mouse cursor is on
+pub fn cursor_work_area() -> Option<RECT> {
+    let mut cursor = POINT::default();
+    unsafe { GetCursorPos(&mut cursor) }.ok()?;
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let found = unsafe { GetMonitorInfoW(MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST), &mut info) };
+    found.as_bool().then_some(info.rcWork)
+}
+
+// Move a window into the given work area, as move_to_monitor does
+fn move_to_work_area(hwnd: HWND, target: RECT) -> Option<WINDOWPLACEMENT> {
+    let current = monitor_work_area(hwnd)?;
+    if current == target {
+        return None;
+    }
+    
+    let mut original = WINDOWPLACEMENT {
+        length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetWindowPlacement(hwnd, &mut original) }.ok()?;
+    
+    let was_maximized = unsafe { IsZoomed(hwnd).as_bool() };
+    unsafe {
+        if was_maximized {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).ok()?;
+        let (from_w, from_h) = (current.right - current.left, current.bottom - current.top);
+        let (to_w, to_h) = (target.right - target.left, target.bottom - target.top);
+        let left = map_span(rect.left, current.left, from_w, target.left, to_w);
+        let top = map_span(rect.top, current.top, from_h, target.top, to_h);
+        let right = map_span(rect.right, current.left, from_w, target.left, to_w);
+        let bottom = map_span(rect.bottom, current.top, from_h, target.top, to_h);
+        
+        SetWindowPos(hwnd, None, left, top, right - left, bottom - top, SWP_NOZORDER | SWP_NOACTIVATE).ok()?;
+        if was_maximized {
+            let _ = ShowWindow(hwnd, SW_MAXIMIZE);
+        }
+    }
+    Some(original)
+}
+
+// Put a window back where it was before it was moved
+pub fn restore_placement(hwnd: HWND, placement: &WINDOWPLACEMENT) {
+    unsafe {
+        if IsWindow(Some(hwnd)).as_bool() {
+            let _ = SetWindowPlacement(hwnd, placement);
+        }
+    }
+}
+
+// How often the cursor's monitor is checked while following it
+const FOLLOW_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// Keeps the focused target on the monitor the mouse is on. The cursor has to
+// stay on another monitor for `delay` before the target follows, so passing
+// over a screen on the way somewhere doesn't drag the window along.
+pub struct CursorFollower {
+    delay: Duration,
+    // Monitor the cursor moved to and since when
+    pending: Option<(RECT, Instant)>,
+    last_check: Option<Instant>,
+}
+
+impl CursorFollower {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay, pending: None, last_check: None }
+    }
+    
+    // Check the cursor while the target has focus; returns the target's
+    // original placement when it was moved
+    pub fn update(&mut self, hwnd: HWND) -> Option<WINDOWPLACEMENT> {
+        self.last_check = Some(Instant::now());
+        let cursor = cursor_work_area()?;
+        if monitor_work_area(hwnd) == Some(cursor) {
+            self.pending = None;
+            return None;
+        }
+        match self.pending {
+            Some((monitor, since)) if monitor == cursor => {
+                if since.elapsed() < self.delay {
+                    return None;
+                }
+                self.pending = None;
+                move_to_work_area(hwnd, cursor)
+            }
+            _ => {
+                self.pending = Some((cursor, Instant::now()));
+                None
+            }
+        }
+    }
+    
+    // No target has focus: stop checking until one does
+    pub fn stop(&mut self) {
+        self.pending = None;
+        self.last_check = None;
+    }
+    
+    // When to check the cursor again, while following
+    pub fn next_check(&self) -> Option<Instant> {
+        self.last_check.map(|at| at + FOLLOW_CHECK_INTERVAL)
+    }
+}
@@ -0,0 +1,32 @@
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+// Snapshot of the machine's power source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerState {
+    pub on_ac: bool,
+    pub battery_saver: bool,
+}
+
+impl Default for PowerState {
+    fn default() -> Self {
+        Self {
+            on_ac: true,
+            battery_saver: false,
+        }
+    }
+}
+
+// Query the current power source, assuming AC power when the status is unknown
+pub fn current_power_state() -> PowerState {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if unsafe { GetSystemPowerStatus(&mut status) }.is_err() {
+        return PowerState::default();
+    }
+
+    PowerState {
+        // 0 = offline, 1 = online, 255 = unknown
+        on_ac: status.ACLineStatus != 0,
+        // 1 = Battery Saver is on
+        battery_saver: status.SystemStatusFlag == 1,
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::Path;
+
+use windows::{
+    core::{HSTRING, PWSTR},
+    Win32::Foundation::*,
+    Win32::Storage::FileSystem::{GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW},
+    Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, Thread32First, Thread32Next, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD, THREADENTRY32,
+    },
+    Win32::System::Memory::{
+        SetProcessWorkingSetSizeEx, QUOTA_LIMITS_HARDWS_MAX_DISABLE, QUOTA_LIMITS_HARDWS_MIN_ENABLE,
+    },
+    Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY},
+    Win32::System::Threading::*,
+    Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId,
+};
+
+// Get the id of the process that owns a window
+pub fn window_process_id(hwnd: HWND) -> u32 {
+    let mut process_id = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    }
+    process_id
+}
+
+// Get the full executable path of a process
+pub fn process_image_path(process_id: u32) -> Option<String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id).ok()?;
+        
+        let mut buffer = [0u16; 1024];
+        let mut size = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut size,
+        );
+        let _ = CloseHandle(process);
+        result.ok()?;
+        
+        Some(
+            OsString::from_wide(&buffer[..size as usize])
+                .to_string_lossy()
+                .to_string(),
+        )
+    }
+}
+
+// Function to tell whether the process behind a handle runs elevated. A
+// token we may not even query belongs to a process above our integrity level,
+// which counts as elevated: its windows are just as out of reach.
+fn token_elevated(process: HANDLE) -> bool {
+    let mut token = HANDLE::default();
+    if let Err(e) = unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) } {
+        return e.code() == E_ACCESSDENIED;
+    }
+    let mut elevation = TOKEN_ELEVATION::default();
+    let mut size = 0u32;
+    let queried = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut _ as *mut core::ffi::c_void),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut size,
+        )
+    };
+    let _ = unsafe { CloseHandle(token) };
+    queried.is_ok() && elevation.TokenIsElevated != 0
+}
+
+// Function to tell whether this process runs elevated
+pub fn is_elevated() -> bool {
+    token_elevated(unsafe { GetCurrentProcess() })
+}
+
+// Function to tell whether another process runs elevated. Windows of elevated
+// processes ignore ShowWindow and opacity changes from a process that isn't,
+// without reporting an error. A process we can't open for being denied
+// access counts as elevated too.
+pub fn is_process_elevated(process_id: u32) -> bool {
+    let process = match unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id) } {
+        Ok(process) => process,
+        Err(e) => return e.code() == E_ACCESSDENIED,
+    };
+    let elevated = token_elevated(process);
+    let _ = unsafe { CloseHandle(process) };
+    elevated
+}
+
+// Get the executable file name (e.g. "chrome.exe") of a process
+pub fn process_name(process_id: u32) -> String {
+    process_image_path(process_id)
+        .and_then(|path| {
+            Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_default()
+}
+
+// Descriptive names from an executable's version resource
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionStrings {
+    // e.g. "Visual Studio Code"
+    pub file_description: String,
+    pub product_name: String,
+}
+
+// Read the FileDescription and ProductName of an executable, using the first
+// language listed in its version resource (empty when there is none)
+pub fn exe_version_strings(path: &str) -> VersionStrings {
+    let path = HSTRING::from(path);
+    unsafe {
+        let size = GetFileVersionInfoSizeW(&path, None);
+        if size == 0 {
+            return VersionStrings::default();
+        }
+        
+        let mut data = vec![0u8; size as usize];
+        if GetFileVersionInfoW(&path, None, size, data.as_mut_ptr().cast()).is_err() {
+            return VersionStrings::default();
+        }
+        
+        // Pairs of (language, code page); fall back to US English / Unicode
+        let mut translation = (0x0409u16, 0x04B0u16);
+        let mut buffer: *mut core::ffi::c_void = std::ptr::null_mut();
+        let mut len = 0u32;
+        if VerQueryValueW(data.as_ptr().cast(), &HSTRING::from("\\VarFileInfo\\Translation"), &mut buffer, &mut len).as_bool()
+            && len >= 4
+        {
+            let pair = buffer as *const u16;
+            translation = (*pair, *pair.add(1));
+        }
+        
+        let query = |name: &str| -> String {
+            let sub_block = HSTRING::from(format!("\\StringFileInfo\\{:04x}{:04x}\\{}", translation.0, translation.1, name));
+            let mut buffer: *mut core::ffi::c_void = std::ptr::null_mut();
+            let mut len = 0u32;
+            if !VerQueryValueW(data.as_ptr().cast(), &sub_block, &mut buffer, &mut len).as_bool() || len == 0 {
+                return String::new();
+            }
+            // len counts characters including the terminating null
+            let chars = std::slice::from_raw_parts(buffer as *const u16, len as usize);
+            let end = chars.iter().position(|&c| c == 0).unwrap_or(chars.len());
+            OsString::from_wide(&chars[..end]).to_string_lossy().trim().to_string()
+        };
+        
+        VersionStrings {
+            file_description: query("FileDescription"),
+            product_name: query("ProductName"),
+        }
+    }
+}
+
+// List running processes as (process id, executable file name)
+pub fn running_processes() -> Vec<(u32, String)> {
+    let mut processes = Vec::new();
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+            return processes;
+        };
+        
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        if Process32FirstW(snapshot, &mut entry).is_ok() {
+            loop {
+                let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+                let name = OsString::from_wide(&entry.szExeFile[..len]).to_string_lossy().to_string();
+                processes.push((entry.th32ProcessID, name));
+                
+                if Process32NextW(snapshot, &mut entry).is_err() {
+                    break;
+                }
+            }
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    processes
+}
+
+// List the thread ids belonging to the given processes
+pub fn process_thread_ids(process_ids: &[u32]) -> Vec<u32> {
+    let mut threads = Vec::new();
+    if process_ids.is_empty() {
+        return threads;
+    }
+    
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) else {
+            return threads;
+        };
+        
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        if Thread32First(snapshot, &mut entry).is_ok() {
+            loop {
+                if process_ids.contains(&entry.th32OwnerProcessID) {
+                    threads.push(entry.th32ThreadID);
+                }
+                if Thread32Next(snapshot, &mut entry).is_err() {
+                    break;
+                }
+            }
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    threads
+}
+
+// Executable name, path and version strings of the process owning a window
+#[derive(Debug, Clone, Default)]
+pub struct ProcessDetails {
+    pub name: String,
+    // Full path of the executable, empty when the process can't be opened
+    pub path: String,
+    pub version: VersionStrings,
+    pub elevated: bool,
+}
+
+// Cache of process details keyed by window, so repeated enumerations don't
+// reopen every process. A window never changes its owning process.
+#[derive(Default)]
+pub struct ProcessNameCache {
+    names: HashMap<(usize, u32), ProcessDetails>,
+}
+
+impl ProcessNameCache {
+    // Get the process details for a window, resolving them on first use
+    pub fn get(&mut self, hwnd: HWND, process_id: u32) -> ProcessDetails {
+        self.names
+            .entry((hwnd.0 as usize, process_id))
+            .or_insert_with(|| {
+                let path = process_image_path(process_id).unwrap_or_default();
+                ProcessDetails {
+                    name: Path::new(&path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    version: if path.is_empty() { VersionStrings::default() } else { exe_version_strings(&path) },
+                    path,
+                    elevated: is_process_elevated(process_id),
+                }
+            })
+            .clone()
+    }
+    
+    // Drop entries for windows that no longer exist
+    pub fn retain_windows(&mut self, alive: &[(HWND, u32)]) {
+        let alive: HashSet<(usize, u32)> = alive.iter().map(|(hwnd, process_id)| (hwnd.0 as usize, *process_id)).collect();
+        self.names.retain(|key, _| alive.contains(key));
+    }
+}
+
+// Keep the process's working set resident so the enforcement path doesn't
+// page fault after long idle periods
+pub fn pin_working_set(minimum_bytes: usize, maximum_bytes: usize) -> windows::core::Result<()> {
+    unsafe {
+        SetProcessWorkingSetSizeEx(
+            GetCurrentProcess(),
+            minimum_bytes,
+            maximum_bytes,
+            QUOTA_LIMITS_HARDWS_MIN_ENABLE | QUOTA_LIMITS_HARDWS_MAX_DISABLE,
+        )
+    }
+}
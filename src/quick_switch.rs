@@ -0,0 +1,60 @@
+use tracing::info;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{IsIconic, IsWindow, SetForegroundWindow, ShowWindow, SW_RESTORE};
+
+// Number of quick-switch slots (modifier+1 to modifier+9)
+pub const SLOTS: usize = 9;
+
+// The windows allowed in the current session, numbered for quick switching.
+// Numbers stay put while the session runs: new windows are appended and
+// only closed or suppressed ones drop out.
+#[derive(Default)]
+pub struct QuickSwitch {
+    windows: Vec<(HWND, String)>,
+}
+
+impl QuickSwitch {
+    // Replace the allowed set after an enforcement pass, keeping the existing numbering
+    pub fn update(&mut self, allowed: Vec<(HWND, String)>, modifiers: &str) {
+        let before: Vec<HWND> = self.windows.iter().map(|(hwnd, _)| *hwnd).collect();
+        
+        self.windows.retain(|(hwnd, _)| allowed.iter().any(|(other, _)| other == hwnd));
+        for (hwnd, title) in allowed {
+            match self.windows.iter_mut().find(|(existing, _)| *existing == hwnd) {
+                Some(existing) => existing.1 = title,
+                None => self.windows.push((hwnd, title)),
+            }
+        }
+        
+        let after: Vec<HWND> = self.windows.iter().map(|(hwnd, _)| *hwnd).collect();
+        if before != after {
+            for (i, (_, title)) in self.windows.iter().take(SLOTS).enumerate() {
+                info!("  · {}+{}: {}", modifiers, i + 1, title);
+            }
+        }
+    }
+    
+    // The session ended, so there is nothing left to switch between
+    pub fn clear(&mut self) {
+        self.windows.clear();
+    }
+    
+    // Function to bring the window in the given slot (0-based) to the foreground
+    pub fn activate(&self, slot: usize) {
+        let Some((hwnd, title)) = self.windows.get(slot) else {
+            info!("No allowed window in slot {}", slot + 1);
+            return;
+        };
+        unsafe {
+            if !IsWindow(Some(*hwnd)).as_bool() {
+                return;
+            }
+            if IsIconic(*hwnd).as_bool() {
+                let _ = ShowWindow(*hwnd, SW_RESTORE);
+            }
+            // Receiving the hotkey grants this process the right to set the foreground
+            let _ = SetForegroundWindow(*hwnd);
+        }
+        info!("Switched to: {}", title);
+    }
+}
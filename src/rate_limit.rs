@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+// Tracks actions per rule over the last hour so a misconfigured rule
+// cannot minimize the same app hundreds of times
+pub struct RateLimiter {
+    max_actions_per_hour: usize,
+    actions: HashMap<String, VecDeque<Instant>>,
+    suspended: HashSet<String>,
+}
+
+impl RateLimiter {
+    // A cap of 0 disables rate limiting
+    pub fn new(max_actions_per_hour: usize) -> Self {
+        Self {
+            max_actions_per_hour,
+            actions: HashMap::new(),
+            suspended: HashSet::new(),
+        }
+    }
+    
+    // Number of actions the rule may still take this hour
+    pub fn remaining(&mut self, rule: &str) -> usize {
+        if self.max_actions_per_hour == 0 {
+            return usize::MAX;
+        }
+        
+        let used = match self.actions.get_mut(rule) {
+            Some(timestamps) => {
+                while timestamps.front().is_some_and(|t| t.elapsed() > WINDOW) {
+                    timestamps.pop_front();
+                }
+                timestamps.len()
+            }
+            None => 0,
+        };
+        
+        let remaining = self.max_actions_per_hour.saturating_sub(used);
+        if remaining == 0 {
+            if self.suspended.insert(rule.to_string()) {
+                warn!(
+                    "⚠ Rule '{}' reached its cap of {} actions per hour and is suspended",
+                    rule, self.max_actions_per_hour
+                );
+            }
+        } else if self.suspended.remove(rule) {
+            info!("Rule '{}' is below its hourly cap again and has resumed", rule);
+        }
+        remaining
+    }
+    
+    // Record actions taken by a rule
+    pub fn record(&mut self, rule: &str, count: usize) {
+        if self.max_actions_per_hour == 0 || count == 0 {
+            return;
+        }
+        
+        let now = Instant::now();
+        let timestamps = self.actions.entry(rule.to_string()).or_default();
+        timestamps.extend(std::iter::repeat_n(now, count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn caps_actions_per_rule() {
+        let mut limiter = RateLimiter::new(3);
+        assert_eq!(limiter.remaining("chrome"), 3);
+        limiter.record("chrome", 2);
+        assert_eq!(limiter.remaining("chrome"), 1);
+        limiter.record("chrome", 1);
+        assert_eq!(limiter.remaining("chrome"), 0);
+        assert_eq!(limiter.remaining("slack"), 3);
+    }
+    
+    #[test]
+    fn actions_older_than_the_window_expire() {
+        let mut limiter = RateLimiter::new(2);
+        let Some(old) = Instant::now().checked_sub(WINDOW + Duration::from_secs(1)) else { return };
+        limiter.actions.entry("chrome".to_string()).or_default().extend([old, old]);
+        limiter.record("chrome", 1);
+        assert_eq!(limiter.remaining("chrome"), 1);
+        assert_eq!(limiter.actions["chrome"].len(), 1);
+    }
+    
+    #[test]
+    fn a_rule_resumes_once_below_its_cap() {
+        let mut limiter = RateLimiter::new(1);
+        limiter.record("chrome", 1);
+        assert_eq!(limiter.remaining("chrome"), 0);
+        assert!(limiter.suspended.contains("chrome"));
+        limiter.actions.get_mut("chrome").unwrap().clear();
+        assert_eq!(limiter.remaining("chrome"), 1);
+        assert!(!limiter.suspended.contains("chrome"));
+    }
+    
+    #[test]
+    fn zero_disables_the_cap() {
+        let mut limiter = RateLimiter::new(0);
+        limiter.record("chrome", 1000);
+        assert_eq!(limiter.remaining("chrome"), usize::MAX);
+    }
+}
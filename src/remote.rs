@@ -0,0 +1,43 @@
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTECONTROL, SM_REMOTESESSION};
+
+// How the current session is being viewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionKind {
+    #[default]
+    Local,
+    // Running inside a Remote Desktop session
+    RemoteDesktop,
+    // The console session is being shadowed/remote controlled
+    RemoteControlled,
+}
+
+impl SessionKind {
+    pub fn is_remote(&self) -> bool {
+        *self != SessionKind::Local
+    }
+}
+
+impl std::fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            SessionKind::Local => "local console",
+            SessionKind::RemoteDesktop => "Remote Desktop session",
+            SessionKind::RemoteControlled => "remotely controlled console",
+        };
+        f.write_str(description)
+    }
+}
+
+// Find out whether the session is local or viewed remotely. This can change
+// while running when a user connects or reconnects over RDP.
+pub fn current_session_kind() -> SessionKind {
+    unsafe {
+        if GetSystemMetrics(SM_REMOTESESSION) != 0 {
+            SessionKind::RemoteDesktop
+        } else if GetSystemMetrics(SM_REMOTECONTROL) != 0 {
+            SessionKind::RemoteControlled
+        } else {
+            SessionKind::Local
+        }
+    }
+}
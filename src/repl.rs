@@ -0,0 +1,188 @@
+use std::io::{self, BufRead, Write};
+
+use windows::Win32::Foundation::HWND;
+
+use crate::budgets::FocusBudgets;
+use crate::config::MonitorConfig;
+use crate::config_watch::ConfigSource;
+use crate::decisions::DecisionCache;
+use crate::ignores::ScopedIgnores;
+use crate::limits::FocusRecency;
+use crate::process::{is_elevated, ProcessNameCache};
+use crate::signature::PublisherCache;
+use crate::window_ages::WindowAges;
+use crate::{
+    decide_suppression, get_all_windows_uncached, is_system_window, plan_enforcement, RuleSet, PassState,
+};
+
+const HELP: &str = "\
+Commands:
+  match \"TITLE\"            Which rules match a window title
+  simulate focus \"TITLE\"   What a pass would do to the open windows if TITLE got focus
+  rules                    List the loaded target and ignore rules
+  reload                   Load the config file again
+  help                     Show this help
+  quit                     Leave (also Ctrl+Z, Enter)";
+
+// Function to strip the quotes around an argument, if it has them
+fn unquote(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text)
+}
+
+// Function to print which rules match a title and what that means for a window with it
+fn print_match(title: &str, matchers: &RuleSet) {
+    let title_lower = title.to_lowercase();
+    let target = matchers.targets.iter().find(|matcher| matcher.is_match(&title_lower));
+    let ignored = matchers.ignored.iter().find(|matcher| matcher.is_match(&title_lower));
+    match target {
+        Some(target) => println!("  ✓ Target rule '{}'", target.keyword),
+        None => println!("  · No target rule matches"),
+    }
+    match ignored {
+        Some(ignored) => println!("  · Ignore rule '{}'", ignored.keyword),
+        None => println!("  · No ignore rule matches"),
+    }
+    let verdict = match (target, ignored) {
+        (Some(_), None) => "focusing it starts a session; it stays while other targets are focused",
+        (Some(_), Some(_)) => "ignore rules win over target rules: never suppressed, and focusing it ends the session",
+        (None, Some(_)) => "never suppressed",
+        (None, None) => "suppressed while a target is focused, unless a process exception keeps it",
+    };
+    println!("  → {}", verdict);
+}
+
+// Function to run one pass in dry-run mode against the open windows, as if
+// the window titled `title` had just got focus; nothing is touched
+fn simulate_focus(title: &str, config: &MonitorConfig, matchers: &RuleSet) -> Result<(), Box<dyn std::error::Error>> {
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut ProcessNameCache::default())?;
+    let title_lower = title.to_lowercase();
+    let ignored = matchers.ignored.iter().any(|matcher| matcher.is_match(&title_lower));
+    let Some(target) = matchers
+        .targets
+        .iter()
+        .find(|matcher| !ignored && matcher.is_match(&title_lower))
+        .map(|matcher| &matcher.keyword)
+    else {
+        println!("  · Not a target: focusing it ends the session ({:?})", config.restore_behavior);
+        return Ok(());
+    };
+    
+    // Stand in for an open window with that title when there is one, so it is
+    // its snap group that stays; otherwise no open window is the focused one
+    let active = windows
+        .iter()
+        .find(|window| window.title_lower.contains(&title_lower) && matchers.target(window) == Some(target))
+        .map_or(HWND::default(), |window| window.hwnd);
+    let mut config = config.clone();
+    config.dry_run = true;
+    let plan = plan_enforcement(&windows, active, target, &config, matchers, &mut DecisionCache::default());
+    // A fresh monitor's view: nothing ignored or focused yet, and every open
+    // window already settled; today's focus budgets are the real ones
+    let mut state = PassState {
+        scoped_ignores: &ScopedIgnores::default(),
+        window_ages: &mut WindowAges::default(),
+        publisher_cache: &mut PublisherCache::default(),
+        focus_recency: &mut FocusRecency::default(),
+        focus_budgets: &FocusBudgets::load(),
+        elevated: is_elevated(),
+    };
+    let decision = decide_suppression(&windows, active, &plan, &config, &mut state);
+    
+    println!("  ✓ Target rule '{}', action {:?}", target, config.suppress_action_for(target));
+    if let Some(owner) = plan.owner.as_ref().filter(|owner| *owner != target) {
+        println!("  · Session owned by higher-priority target '{}'", owner);
+    }
+    for window in &plan.snap_group {
+        println!("  · Kept snap group member: {}", window.title);
+    }
+    for window in &plan.shared {
+        println!("  · Kept shared window: {}", window.title);
+    }
+    for window in &plan.playing_audio {
+        println!("  · Kept window playing audio: {}", window.title);
+    }
+    if !plan.other_desktops.is_empty() {
+        println!("  · Left {} windows on other virtual desktops alone", plan.other_desktops.len());
+    }
+    if !plan.other_monitors.is_empty() {
+        println!("  · Left {} windows on other monitors alone", plan.other_monitors.len());
+    }
+    for (window, reason) in &plan.reasons {
+        if !is_system_window(window) {
+            println!("  · {}: {}", reason, window.title);
+        }
+    }
+    for (window, note) in &decision.notes {
+        println!("  · {}: {}", note, window.title);
+    }
+    for window in &decision.unreachable {
+        println!("  ⚠ Unreachable (runs elevated): {}", window.title);
+    }
+    println!("  → {} of {} windows would be suppressed", decision.suppress.len(), windows.len());
+    Ok(())
+}
+
+// Function to print the loaded rules
+fn print_rules(config: &MonitorConfig) {
+    println!("  Targets: {:?}", config.target_keywords);
+    println!("  Ignored: {:?}", config.ignored_keywords);
+    for exception in &config.process_exceptions {
+        println!("  Exception: {} allowed when title contains {:?}", exception.process, exception.allowed_titles);
+        if exception.while_playing_audio {
+            println!("  Exception: {} allowed while playing audio", exception.process);
+        }
+    }
+    println!("  Multiple target resolution: {:?}", config.target_resolution);
+}
+
+// Function to try rules out interactively against the loaded configuration,
+// reading one command per line until `quit` or the end of input
+pub fn run_repl(mut config: MonitorConfig, source: Option<ConfigSource>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut matchers = RuleSet::new(&config)?;
+    let loaded_from = source.as_ref().map_or("the built-in defaults".to_string(), |source| source.path.display().to_string());
+    println!("Trying rules from {}; nothing here touches a window", loaded_from);
+    println!("{}\n", HELP);
+    
+    let mut input = io::stdin().lock();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = line.trim();
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command {
+            "" => continue,
+            "match" if !argument.is_empty() => print_match(unquote(argument), &matchers),
+            "match" => println!("  ✗ Usage: match \"TITLE\""),
+            "simulate" => match argument.trim().strip_prefix("focus") {
+                Some(title) if !title.trim().is_empty() => {
+                    if let Err(e) = simulate_focus(unquote(title), &config, &matchers) {
+                        println!("  ✗ Could not list the open windows: {}", e);
+                    }
+                }
+                _ => println!("  ✗ Usage: simulate focus \"TITLE\""),
+            },
+            "rules" => print_rules(&config),
+            "reload" => match &source {
+                // Keep the rules in use when the new ones don't load or compile
+                Some(source) => match (source.load)().and_then(|reloaded| Ok((RuleSet::new(&reloaded)?, reloaded))) {
+                    Ok((reloaded_matchers, reloaded)) => {
+                        config = reloaded;
+                        matchers = reloaded_matchers;
+                        println!("  ✓ Reloaded {}", source.path.display());
+                    }
+                    Err(e) => println!("  ✗ Could not reload {}, keeping the current rules: {}", source.path.display(), e),
+                },
+                None => println!("  ℹ No config file to reload"),
+            },
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(()),
+            _ => println!("  ✗ Unknown command '{}', see `help`", line),
+        }
+        println!();
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_dir;
+use crate::WindowInfo;
+
+// Characters of the normalized title that go into a fingerprint. Titles tend
+// to start with what the window is and end with what it currently shows.
+const TITLE_PREFIX_CHARS: usize = 48;
+
+// Fingerprint of a window that survives the app restarting (and reboots),
+// unlike its HWND: executable path, window class and the start of the
+// normalized title. Entries saved before paths and classes were recorded
+// leave them empty and are matched by process name and title alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowIdentity {
+    // Executable file name, e.g. "slack.exe"
+    pub process: String,
+    // Lowercased full executable path
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    // Window class name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub class: String,
+    // Start of the normalized window title
+    pub title: String,
+}
+
+impl WindowIdentity {
+    pub fn of(window: &WindowInfo) -> Self {
+        Self {
+            process: window.process_name.to_lowercase(),
+            path: window.process_path.to_lowercase(),
+            class: window.class_name.clone(),
+            title: title_prefix(&window.title),
+        }
+    }
+    
+    // Whether two fingerprints name the same window; parts missing on either
+    // side (older entries, processes that couldn't be opened) are skipped
+    pub fn same_window(&self, other: &WindowIdentity) -> bool {
+        let matches = |a: &str, b: &str| a.is_empty() || b.is_empty() || a == b;
+        self.process == other.process
+            && matches(&self.path, &other.path)
+            && matches(&self.class, &other.class)
+            && title_prefix(&self.title) == title_prefix(&other.title)
+    }
+}
+
+// Function to cut a title down to the part that goes into a fingerprint
+fn title_prefix(title: &str) -> String {
+    normalize_title(title).chars().take(TITLE_PREFIX_CHARS).collect::<String>().trim_end().to_string()
+}
+
+// Windows minimized during the last run
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RestoreList {
+    pub windows: Vec<WindowIdentity>,
+}
+
+// Normalize a title so unread counters and unsaved markers don't break matching:
+// "(3) Slack - General*" becomes "slack - general"
+pub fn normalize_title(title: &str) -> String {
+    let mut rest = title.trim();
+    
+    // Drop a leading "(N)" counter
+    if let Some(stripped) = rest.strip_prefix('(')
+        && let Some(end) = stripped.find(')')
+        && stripped[..end].chars().all(|c| c.is_ascii_digit() || c == '+')
+    {
+        rest = stripped[end + 1..].trim_start();
+    }
+    
+    rest.trim_start_matches('*')
+        .trim_end_matches('*')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn restore_list_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("last-session.json"))
+}
+
+impl RestoreList {
+    // Load the list saved by the last run, empty if there is none
+    pub fn load() -> Self {
+        restore_list_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+    
+    pub fn save(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let path = restore_list_path().ok_or("LOCALAPPDATA is not set")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    // Remember a window, returning whether it was new
+    pub fn add(&mut self, identity: WindowIdentity) -> bool {
+        if self.windows.iter().any(|existing| existing.same_window(&identity)) {
+            return false;
+        }
+        self.windows.push(identity);
+        true
+    }
+}
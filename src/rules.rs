@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+// Rulesets at least this large are evaluated across threads; smaller
+// ones are faster to run inline than to hand out to the pool
+const PARALLEL_THRESHOLD: usize = 64;
+
+// How many times each rule is run when profiling, to smooth out timer noise
+const PROFILE_ITERATIONS: u32 = 100;
+
+// Keywords starting with this are regular expressions matched against the
+// whole title, e.g. "re:^Visual Studio Code - .*\.rs$"
+const REGEX_PREFIX: &str = "re:";
+
+enum MatchKind {
+    // Lowercased keyword the title must contain
+    Substring(String),
+    // Case-insensitive pattern
+    Regex(Regex),
+}
+
+// A title keyword compiled once from the configuration
+pub struct KeywordMatcher {
+    // Keyword as configured, for messages
+    pub keyword: String,
+    kind: MatchKind,
+}
+
+impl KeywordMatcher {
+    // Compile a keyword; "re:" keywords must be valid regular expressions
+    pub fn new(keyword: &str) -> Result<Self, String> {
+        let kind = match keyword.strip_prefix(REGEX_PREFIX) {
+            Some(pattern) => {
+                let regex = RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("invalid regex keyword '{}': {}", keyword, e))?;
+                MatchKind::Regex(regex)
+            }
+            None => MatchKind::Substring(keyword.to_lowercase()),
+        };
+        Ok(Self { keyword: keyword.to_string(), kind })
+    }
+    
+    // A plain substring keyword, never treated as a regex
+    pub fn substring(keyword: &str) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            kind: MatchKind::Substring(keyword.to_lowercase()),
+        }
+    }
+    
+    // Whether a lowercased title matches the keyword
+    pub fn is_match(&self, title_lower: &str) -> bool {
+        match &self.kind {
+            MatchKind::Substring(keyword) => title_lower.contains(keyword.as_str()),
+            MatchKind::Regex(regex) => regex.is_match(title_lower),
+        }
+    }
+    
+    // Whether every title this keyword matches is matched by `other` too,
+    // as far as can be told without running regexes
+    pub fn always_matched_by(&self, other: &KeywordMatcher) -> bool {
+        match (&self.kind, &other.kind) {
+            (MatchKind::Substring(keyword), MatchKind::Substring(other)) => keyword.contains(other.as_str()),
+            _ => false,
+        }
+    }
+    
+    // Whether the keyword can match inside a run of digits; any regex might
+    pub fn may_match_digits(&self) -> bool {
+        match &self.kind {
+            MatchKind::Substring(keyword) => keyword.chars().any(|c| c.is_ascii_digit()),
+            MatchKind::Regex(_) => true,
+        }
+    }
+}
+
+// Function to compile every keyword of a rule list
+pub fn compile_keywords(keywords: &[String]) -> Result<Vec<KeywordMatcher>, String> {
+    keywords.iter().map(|keyword| KeywordMatcher::new(keyword)).collect()
+}
+
+// Function to find, for each title, the index of the first keyword it matches
+// (titles are expected to be lowercased already)
+pub fn first_matches(titles: &[&str], keywords: &[KeywordMatcher]) -> Vec<Option<usize>> {
+    if keywords.len() < PARALLEL_THRESHOLD {
+        titles
+            .iter()
+            .map(|title| keywords.iter().position(|keyword| keyword.is_match(title)))
+            .collect()
+    } else {
+        titles
+            .par_iter()
+            .map(|title| keywords.par_iter().position_first(|keyword| keyword.is_match(title)))
+            .collect()
+    }
+}
+
+// Timing of a single rule against the current window set
+pub struct RuleProfile {
+    pub kind: &'static str,
+    pub rule: String,
+    pub matches: usize,
+    pub elapsed: Duration,
+}
+
+// Function to time each keyword rule against the given titles; rules are
+// measured one at a time so the timings aren't skewed by each other
+pub fn profile_keywords(kind: &'static str, titles: &[&str], keywords: &[KeywordMatcher]) -> Vec<RuleProfile> {
+    keywords
+        .iter()
+        .map(|keyword| {
+            let mut matches = 0;
+            let start = Instant::now();
+            for _ in 0..PROFILE_ITERATIONS {
+                matches = titles.iter().filter(|title| keyword.is_match(title)).count();
+            }
+            RuleProfile {
+                kind,
+                rule: keyword.keyword.clone(),
+                matches,
+                elapsed: start.elapsed() / PROFILE_ITERATIONS,
+            }
+        })
+        .collect()
+}
+
+// Function to print rule timings, slowest first
+pub fn print_profiles(mut profiles: Vec<RuleProfile>, window_count: usize) {
+    profiles.sort_by_key(|profile| std::cmp::Reverse(profile.elapsed));
+    
+    println!("Rule timings against {} windows (average of {} runs, slowest first):", window_count, PROFILE_ITERATIONS);
+    for profile in &profiles {
+        println!(
+            "  {:>9.3} µs  {:<9} {:>4} matches  '{}'",
+            profile.elapsed.as_secs_f64() * 1_000_000.0,
+            profile.kind,
+            profile.matches,
+            profile.rule
+        );
+    }
+    
+    let total: Duration = profiles.iter().map(|p| p.elapsed).sum();
+    println!("Total: {:.3} µs across {} rules", total.as_secs_f64() * 1_000_000.0, profiles.len());
+    if profiles.len() >= PARALLEL_THRESHOLD {
+        println!("ℹ Rulesets of {} or more rules are evaluated in parallel while monitoring", PARALLEL_THRESHOLD);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn matcher(keyword: &str) -> KeywordMatcher {
+        KeywordMatcher::new(keyword).unwrap()
+    }
+    
+    #[test]
+    fn a_longer_keyword_is_always_matched_by_its_substring() {
+        assert!(matcher("Visual Studio Code").always_matched_by(&matcher("studio")));
+        assert!(matcher("Trae").always_matched_by(&matcher("trae")));
+        assert!(!matcher("studio").always_matched_by(&matcher("Visual Studio Code")));
+        assert!(!matcher("Trae").always_matched_by(&matcher("WhatsApp")));
+    }
+    
+    #[test]
+    fn regexes_are_never_known_to_overlap() {
+        assert!(!matcher("re:^trae$").always_matched_by(&matcher("trae")));
+        assert!(!matcher("trae").always_matched_by(&matcher("re:trae")));
+        assert!(!matcher("re:trae").always_matched_by(&matcher("re:trae")));
+    }
+    
+    #[test]
+    fn an_empty_keyword_matches_everything() {
+        assert!(matcher("Trae").always_matched_by(&matcher("")));
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use windows::Win32::{
+    Foundation::{HLOCAL, LocalFree},
+    Security::Cryptography::{
+        BCRYPT_USE_SYSTEM_PREFERRED_RNG, BCryptGenRandom, CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN, CryptProtectData,
+        CryptUnprotectData,
+    },
+};
+
+// Marks a config value as DPAPI-encrypted; the rest is the hex-encoded blob
+const PROTECTED_PREFIX: &str = "dpapi:";
+
+// Config keys holding secrets, as (section, key). Only these are encrypted
+// by `config encrypt-secrets` and decrypted at load.
+pub const SECRET_FIELDS: &[(&str, &str)] = &[("update", "proxy")];
+
+pub fn is_protected(value: &str) -> bool {
+    value.starts_with(PROTECTED_PREFIX)
+}
+
+// Copy a blob returned by DPAPI and free it
+fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    let data = unsafe { std::slice::from_raw_parts(blob.pbData, blob.cbData as usize) }.to_vec();
+    unsafe {
+        LocalFree(Some(HLOCAL(blob.pbData as _)));
+    }
+    data
+}
+
+// Make a random 256-bit key, hex-encoded
+pub fn random_hex() -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = [0u8; 32];
+    unsafe { BCryptGenRandom(None, &mut bytes, BCRYPT_USE_SYSTEM_PREFERRED_RNG) }.ok()?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// Encrypt a value for the current user; only the same user on the same
+// machine can decrypt it
+pub fn protect(value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: value.len() as u32,
+        pbData: value.as_ptr() as *mut u8,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(&input, windows::core::w!("fak-opacity"), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)?;
+    }
+    let hex: String = take_blob(output).iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("{}{}", PROTECTED_PREFIX, hex))
+}
+
+// Decrypt a value written by protect; plain values are returned unchanged
+pub fn reveal(value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let Some(hex) = value.strip_prefix(PROTECTED_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    if hex.len() % 2 != 0 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("encrypted value is not valid hex".into());
+    }
+    let mut encrypted: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: encrypted.len() as u32,
+        pbData: encrypted.as_mut_ptr(),
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output)
+            .map_err(|e| format!("can't decrypt (encrypted by another user or machine?): {}", e))?;
+    }
+    Ok(String::from_utf8(take_blob(output))?)
+}
+
+// Rewrite every plaintext secret in a config file as an encrypted value,
+// leaving the rest of the file (comments included) as it was. Returns the
+// number of values encrypted.
+pub fn encrypt_secrets(path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut text = std::fs::read_to_string(path)?;
+    let table: toml::Table = toml::from_str(&text)?;
+    
+    let mut encrypted = 0;
+    for (section, key) in SECRET_FIELDS {
+        let Some(value) = table.get(*section).and_then(|s| s.get(*key)).and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if is_protected(value) {
+            continue;
+        }
+        
+        // Replace the value as written; escaped strings would need a real
+        // TOML editor, so ask for those to be simplified instead
+        let written = [format!("\"{}\"", value), format!("'{}'", value)];
+        let Some(literal) = written.iter().find(|literal| text.matches(literal.as_str()).count() == 1) else {
+            return Err(format!("can't locate {}.{} in the file; write it as a plain \"...\" string", section, key).into());
+        };
+        text = text.replacen(literal.as_str(), &format!("\"{}\"", protect(value)?), 1);
+        println!("✓ Encrypted {}.{}", section, key);
+        encrypted += 1;
+    }
+    
+    if encrypted > 0 {
+        std::fs::write(path, text)?;
+    }
+    Ok(encrypted)
+}
@@ -0,0 +1,178 @@
+use std::thread;
+use std::time::Duration;
+
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::System::LibraryLoader::GetModuleHandleW,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::config::MonitorConfig;
+use crate::decisions::DecisionCache;
+use crate::process::ProcessNameCache;
+use crate::{get_all_windows_uncached, minimize_window, plan_enforcement, RuleSet};
+
+const CLASS_NAME: PCWSTR = w!("FakOpacitySelftestWindow");
+
+// Window procedure for the dummy windows
+unsafe extern "system" fn dummy_window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+// Dummy window with a known title, destroyed when dropped
+struct DummyWindow {
+    hwnd: HWND,
+    title: String,
+}
+
+impl DummyWindow {
+    fn new(title: String, index: i32) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let instance = unsafe { GetModuleHandleW(None)? };
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                CLASS_NAME,
+                &HSTRING::from(title.as_str()),
+                WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+                100 + index * 40,
+                100 + index * 40,
+                320,
+                200,
+                None,
+                None,
+                Some(instance.into()),
+                None,
+            )?
+        };
+        Ok(Self { hwnd, title })
+    }
+}
+
+impl Drop for DummyWindow {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+// Collected TAP results
+#[derive(Default)]
+struct TapReport {
+    results: Vec<(bool, String)>,
+}
+
+impl TapReport {
+    fn check(&mut self, passed: bool, description: String) {
+        self.results.push((passed, description));
+    }
+    
+    fn print(&self) -> bool {
+        println!("TAP version 13");
+        println!("1..{}", self.results.len());
+        for (index, (passed, description)) in self.results.iter().enumerate() {
+            let status = if *passed { "ok" } else { "not ok" };
+            println!("{} {} - {}", status, index + 1, description);
+        }
+        self.results.iter().all(|(passed, _)| *passed)
+    }
+}
+
+// Handle pending messages so the dummy windows are fully created and painted
+fn pump_messages() {
+    let mut msg = MSG::default();
+    unsafe {
+        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// Spawn dummy windows with known titles, run the engine against the
+// configuration and assert that only the expected windows get minimized.
+// Only the dummy windows are ever acted on. Returns whether all checks passed.
+pub fn run_selftest(config: &MonitorConfig) -> std::result::Result<bool, Box<dyn std::error::Error>> {
+    let Some(target_keyword) = config.target_keywords.first() else {
+        println!("TAP version 13");
+        println!("1..0");
+        println!("Bail out! The configuration has no target keywords");
+        return Ok(false);
+    };
+    
+    unsafe {
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(dummy_window_proc),
+            hInstance: GetModuleHandleW(None)?.into(),
+            lpszClassName: CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+    }
+    
+    let target = DummyWindow::new(format!("{} - fak-opacity selftest target", target_keyword), 0)?;
+    let ignored = match config.ignored_keywords.first() {
+        Some(keyword) => Some(DummyWindow::new(format!("{} - fak-opacity selftest ignored", keyword), 1)?),
+        None => None,
+    };
+    let distraction = DummyWindow::new("fak-opacity selftest distraction".to_string(), 2)?;
+    
+    pump_messages();
+    thread::sleep(Duration::from_millis(100));
+    pump_messages();
+    
+    let mut report = TapReport::default();
+    let matchers = RuleSet::new(config)?;
+    let mut process_names = ProcessNameCache::default();
+    let windows = get_all_windows_uncached(config.include_owned_windows, &mut process_names)?;
+    
+    let find = |dummy: &DummyWindow| windows.iter().find(|w| w.hwnd == dummy.hwnd);
+    
+    let Some(target_info) = find(&target) else {
+        report.check(false, format!("target window \"{}\" is enumerated", target.title));
+        return Ok(report.print());
+    };
+    
+    let active_target = matchers.target(target_info).cloned();
+    report.check(
+        active_target.is_some(),
+        format!("\"{}\" is detected as a target window", target.title),
+    );
+    let Some(active_target) = active_target else {
+        return Ok(report.print());
+    };
+    
+    let plan = plan_enforcement(&windows, target.hwnd, &active_target, config, &matchers, &mut DecisionCache::default());
+    let planned = |dummy: &DummyWindow| plan.minimize.iter().any(|w| w.hwnd == dummy.hwnd);
+    
+    report.check(!planned(&target), format!("target window \"{}\" is kept", target.title));
+    if let Some(ignored) = &ignored {
+        report.check(!planned(ignored), format!("ignored window \"{}\" is kept", ignored.title));
+    }
+    report.check(
+        planned(&distraction),
+        format!("distraction window \"{}\" is selected for minimizing", distraction.title),
+    );
+    
+    // Act on the dummy windows only, never on real ones
+    let dummies = [Some(&target), ignored.as_ref(), Some(&distraction)];
+    for dummy in dummies.iter().flatten() {
+        if planned(dummy) {
+            let _ = minimize_window(dummy.hwnd);
+        }
+    }
+    pump_messages();
+    
+    let minimized = |dummy: &DummyWindow| unsafe { IsIconic(dummy.hwnd).as_bool() };
+    report.check(
+        minimized(&distraction),
+        format!("distraction window \"{}\" ends up minimized", distraction.title),
+    );
+    report.check(
+        !minimized(&target),
+        format!("target window \"{}\" stays restored", target.title),
+    );
+    
+    Ok(report.print())
+}
@@ -0,0 +1,402 @@
+use std::ffi::c_void;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::os::windows::io::AsRawHandle;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use tracing::{info, warn};
+use windows::{
+    core::{w, HSTRING, PCWSTR, PWSTR},
+    Win32::Foundation::*,
+    Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock},
+    Win32::System::Pipes::GetNamedPipeServerProcessId,
+    Win32::System::RemoteDesktop::{WTSGetActiveConsoleSessionId, WTSQueryUserToken},
+    Win32::System::Services::*,
+    Win32::System::Threading::{
+        CreateProcessAsUserW, TerminateProcess, WaitForSingleObject, CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT,
+        PROCESS_INFORMATION, STARTUPINFOW,
+    },
+};
+
+// Name the service is registered under
+const SERVICE_NAME: &str = "fak-opacity";
+// Pipe of the monitor the service runs in the user's session
+const PIPE_NAME: &str = "fak-opacity-service";
+// How often the service checks that the monitor is running in the console
+// session, on top of the session-change notifications
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// How long the monitor gets to restore windows after being asked to exit
+const EXIT_TIMEOUT: u32 = 3000;
+// No session is attached to the console, e.g. while switching users
+const NO_SESSION: u32 = u32::MAX;
+
+// What the service manager asked for, handed from the control handler to the
+// service's main thread
+struct Requests {
+    stop: bool,
+    paused: bool,
+    woken: bool,
+}
+
+static REQUESTS: (Mutex<Requests>, Condvar) = (Mutex::new(Requests { stop: false, paused: false, woken: false }), Condvar::new());
+static STATUS_HANDLE: AtomicUsize = AtomicUsize::new(0);
+
+// A service control manager handle, closed on drop
+struct ServiceHandle(SC_HANDLE);
+
+impl Drop for ServiceHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseServiceHandle(self.0) };
+    }
+}
+
+// Function to open the installed service with the given access rights
+fn open_service(manager_access: u32, service_access: u32) -> Result<(ServiceHandle, ServiceHandle), Box<dyn std::error::Error>> {
+    let manager = ServiceHandle(unsafe { OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), manager_access) }?);
+    let service = unsafe { OpenServiceW(manager.0, &HSTRING::from(SERVICE_NAME), service_access) }
+        .map_err(|e| format!("the {} service is not installed ({})", SERVICE_NAME, e))?;
+    Ok((manager, ServiceHandle(service)))
+}
+
+// Function to register the service to start at boot, running this executable.
+// Needs an elevated prompt.
+pub fn install() -> Result<(), Box<dyn std::error::Error>> {
+    let exe = std::env::current_exe()?;
+    let command = HSTRING::from(format!("\"{}\" service run", exe.display()));
+    let manager = ServiceHandle(unsafe { OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CREATE_SERVICE) }?);
+    let service = ServiceHandle(unsafe {
+        CreateServiceW(
+            manager.0,
+            &HSTRING::from(SERVICE_NAME),
+            w!("fak-opacity window monitor"),
+            SERVICE_ALL_ACCESS,
+            SERVICE_WIN32_OWN_PROCESS,
+            SERVICE_AUTO_START,
+            SERVICE_ERROR_NORMAL,
+            &command,
+            PCWSTR::null(),
+            None,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            PCWSTR::null(),
+        )
+    }?);
+    
+    let mut description: Vec<u16> = "Minimizes other windows while a target window is focused, in the signed-in user's session"
+        .encode_utf16()
+        .chain([0])
+        .collect();
+    let description = SERVICE_DESCRIPTIONW { lpDescription: PWSTR(description.as_mut_ptr()) };
+    let _ = unsafe { ChangeServiceConfig2W(service.0, SERVICE_CONFIG_DESCRIPTION, Some(&description as *const _ as *const c_void)) };
+    
+    println!("✓ Installed the {} service running {}", SERVICE_NAME, exe.display());
+    println!("ℹ It starts at boot; run `service start` to start it now");
+    Ok(())
+}
+
+// Function to stop and remove the service
+pub fn uninstall() -> Result<(), Box<dyn std::error::Error>> {
+    let (_manager, service) = open_service(SC_MANAGER_CONNECT, SERVICE_ALL_ACCESS)?;
+    let mut status = SERVICE_STATUS::default();
+    let _ = unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) };
+    unsafe { DeleteService(service.0) }?;
+    println!("✓ Removed the {} service", SERVICE_NAME);
+    Ok(())
+}
+
+// Function to start the installed service
+pub fn start() -> Result<(), Box<dyn std::error::Error>> {
+    let (_manager, service) = open_service(SC_MANAGER_CONNECT, SERVICE_START)?;
+    match unsafe { StartServiceW(service.0, None) } {
+        Ok(()) => println!("✓ Started the {} service", SERVICE_NAME),
+        Err(e) if e.code() == ERROR_SERVICE_ALREADY_RUNNING.to_hresult() => println!("ℹ The {} service is already running", SERVICE_NAME),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+// Function to stop the running service; the monitor restores what it changed first
+pub fn stop() -> Result<(), Box<dyn std::error::Error>> {
+    let (_manager, service) = open_service(SC_MANAGER_CONNECT, SERVICE_STOP)?;
+    let mut status = SERVICE_STATUS::default();
+    match unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) } {
+        Ok(()) => println!("✓ Stopping the {} service", SERVICE_NAME),
+        Err(e) if e.code() == ERROR_SERVICE_NOT_ACTIVE.to_hresult() => println!("ℹ The {} service is not running", SERVICE_NAME),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+// Function to run as the service; only the service control manager starts
+// the executable this way
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut name: Vec<u16> = SERVICE_NAME.encode_utf16().chain([0]).collect();
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: PWSTR(name.as_mut_ptr()),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW::default(),
+    ];
+    match unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } {
+        Err(e) if e.code() == ERROR_FAILED_SERVICE_CONTROLLER_CONNECT.to_hresult() => {
+            Err("`service run` is for the service manager; use `service start`".into())
+        }
+        result => Ok(result?),
+    }
+}
+
+// Function to tell the service manager what state the service is in
+fn report(state: SERVICE_STATUS_CURRENT_STATE) {
+    let settled = state == SERVICE_RUNNING || state == SERVICE_PAUSED;
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: if settled {
+            SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_PAUSE_CONTINUE | SERVICE_ACCEPT_SHUTDOWN | SERVICE_ACCEPT_SESSIONCHANGE
+        } else {
+            0
+        },
+        dwWaitHint: if settled || state == SERVICE_STOPPED { 0 } else { EXIT_TIMEOUT + 2000 },
+        ..Default::default()
+    };
+    let handle = SERVICE_STATUS_HANDLE(STATUS_HANDLE.load(Ordering::Relaxed) as *mut _);
+    let _ = unsafe { SetServiceStatus(handle, &status) };
+}
+
+// Service control handler: record the request and wake the service's main
+// thread, which does the work
+unsafe extern "system" fn control_handler(control: u32, _event_type: u32, _event_data: *mut c_void, _context: *mut c_void) -> u32 {
+    let (requests, woken) = &REQUESTS;
+    let mut requests = requests.lock().unwrap_or_else(|e| e.into_inner());
+    match control {
+        SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+            requests.stop = true;
+            report(SERVICE_STOP_PENDING);
+        }
+        SERVICE_CONTROL_PAUSE => {
+            requests.paused = true;
+            report(SERVICE_PAUSE_PENDING);
+        }
+        SERVICE_CONTROL_CONTINUE => {
+            requests.paused = false;
+            report(SERVICE_CONTINUE_PENDING);
+        }
+        // Someone signed in or switched users; check the console session now
+        SERVICE_CONTROL_SESSIONCHANGE | SERVICE_CONTROL_INTERROGATE => {}
+        _ => return ERROR_CALL_NOT_IMPLEMENTED.0,
+    }
+    requests.woken = true;
+    woken.notify_all();
+    NO_ERROR.0
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+    let Ok(handle) = (unsafe { RegisterServiceCtrlHandlerExW(&HSTRING::from(SERVICE_NAME), Some(control_handler), None) }) else {
+        return;
+    };
+    STATUS_HANDLE.store(handle.0 as usize, Ordering::Relaxed);
+    report(SERVICE_RUNNING);
+    supervise();
+    report(SERVICE_STOPPED);
+}
+
+// Function to keep a monitor running in the session on the console until the
+// service is stopped: services can't see the user's windows themselves, so
+// the monitor runs as the signed-in user (as `embedded`) and the service
+// drives it through its control pipe
+fn supervise() {
+    let mut monitor: Option<Monitor> = None;
+    let mut paused = false;
+    loop {
+        let (requests, woken) = &REQUESTS;
+        let requests = requests.lock().unwrap_or_else(|e| e.into_inner());
+        let mut requests = woken
+            .wait_timeout_while(requests, CHECK_INTERVAL, |requests| !requests.woken)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+        requests.woken = false;
+        let (stop, pause) = (requests.stop, requests.paused);
+        drop(requests);
+        
+        if stop {
+            if let Some(monitor) = monitor.take() {
+                monitor.exit();
+            }
+            return;
+        }
+        if pause != paused {
+            paused = pause;
+            report(if paused { SERVICE_PAUSED } else { SERVICE_RUNNING });
+        }
+        
+        // Follow the console session: start a monitor once someone signs in,
+        // and move it along when another user takes over the console
+        let session = unsafe { WTSGetActiveConsoleSessionId() };
+        let current = monitor.as_ref().is_some_and(|monitor| monitor.running() && monitor.session == session);
+        if !current && session != NO_SESSION {
+            if let Some(monitor) = monitor.take() {
+                monitor.exit();
+            }
+            monitor = match Monitor::launch(session) {
+                Ok(monitor) => Some(monitor),
+                Err(e) => {
+                    // Nobody signed in yet; the next logon wakes the service
+                    info!("No monitor started in session {}: {}", session, e);
+                    None
+                }
+            };
+        }
+        if let Some(monitor) = monitor.as_mut()
+            && monitor.paused != paused
+            && monitor.send(if paused { "pause" } else { "resume" })
+        {
+            monitor.paused = paused;
+        }
+    }
+}
+
+// The monitor process running in a user's session
+struct Monitor {
+    process: HANDLE,
+    id: u32,
+    session: u32,
+    // Control token of its pipe, made fresh for each launch
+    token: String,
+    // Pause state it was last told about
+    paused: bool,
+}
+
+impl Monitor {
+    // Start `embedded` as the user signed in to the session, with that
+    // user's environment so it finds their config file
+    fn launch(session: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        // The pipe token goes in the environment, out of sight of other
+        // users listing command lines
+        let pipe_token = crate::secrets::random_hex()?;
+        let mut token = HANDLE::default();
+        unsafe { WTSQueryUserToken(session, &mut token) }?;
+        let mut environment: *mut c_void = std::ptr::null_mut();
+        let environment_created = unsafe { CreateEnvironmentBlock(&mut environment, Some(token), false) };
+        let mut child_environment = environment_with(
+            environment_created.is_ok().then_some(environment as *const u16),
+            crate::control::CONTROL_TOKEN_VAR,
+            &pipe_token,
+        );
+        
+        let exe = std::env::current_exe()?;
+        let mut command_line: Vec<u16> = format!("\"{}\" embedded --app-name {} --pipe {}", exe.display(), SERVICE_NAME, PIPE_NAME)
+            .encode_utf16()
+            .chain([0])
+            .collect();
+        let mut desktop: Vec<u16> = "winsta0\\default".encode_utf16().chain([0]).collect();
+        let startup = STARTUPINFOW {
+            cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+            lpDesktop: PWSTR(desktop.as_mut_ptr()),
+            ..Default::default()
+        };
+        let mut process = PROCESS_INFORMATION::default();
+        let created = unsafe {
+            CreateProcessAsUserW(
+                Some(token),
+                PCWSTR::null(),
+                Some(PWSTR(command_line.as_mut_ptr())),
+                None,
+                None,
+                false,
+                CREATE_NO_WINDOW | CREATE_UNICODE_ENVIRONMENT,
+                Some(child_environment.as_mut_ptr() as *const c_void),
+                PCWSTR::null(),
+                &startup,
+                &mut process,
+            )
+        };
+        unsafe {
+            if environment_created.is_ok() {
+                let _ = DestroyEnvironmentBlock(environment);
+            }
+            let _ = CloseHandle(token);
+        }
+        created?;
+        
+        let _ = unsafe { CloseHandle(process.hThread) };
+        info!("Started the monitor in session {}", session);
+        Ok(Self {
+            process: process.hProcess,
+            id: process.dwProcessId,
+            session,
+            token: pipe_token,
+            paused: false,
+        })
+    }
+    
+    fn running(&self) -> bool {
+        unsafe { WaitForSingleObject(self.process, 0) == WAIT_TIMEOUT }
+    }
+    
+    // Ask the monitor to restore its windows and exit, ending it if it doesn't
+    fn exit(self) {
+        if !self.send("exit") {
+            warn!("Could not reach the monitor in session {}, ending it", self.session);
+        }
+        if unsafe { WaitForSingleObject(self.process, EXIT_TIMEOUT) } == WAIT_TIMEOUT {
+            let _ = unsafe { TerminateProcess(self.process, 1) };
+        }
+    }
+    
+    // Send one command to the monitor's control pipe; false when it isn't
+    // listening (yet) or didn't accept the command
+    fn send(&self, command: &str) -> bool {
+        let Ok(mut pipe) = OpenOptions::new().read(true).write(true).open(format!(r"\\.\pipe\{}", PIPE_NAME)) else {
+            return false;
+        };
+        // Only hand the token to the monitor this service started, not to
+        // whoever else got to the pipe name first
+        let mut server = 0u32;
+        if unsafe { GetNamedPipeServerProcessId(HANDLE(pipe.as_raw_handle()), &mut server) }.is_err() || server != self.id {
+            warn!("The monitor's pipe in session {} is owned by another process", self.session);
+            return false;
+        }
+        if writeln!(pipe, "hello {}\n{}", self.token, command).is_err() {
+            return false;
+        }
+        let mut replies = BufReader::new(&pipe);
+        let mut reply = String::new();
+        (0..2).all(|_| {
+            reply.clear();
+            replies.read_line(&mut reply).is_ok() && reply.starts_with("ok")
+        })
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.process) };
+    }
+}
+
+// Function to copy an environment block (NAME=value strings, each ending in
+// a null, with one more null at the end) with a variable added. Without a
+// block the service's own environment is copied.
+fn environment_with(block: Option<*const u16>, name: &str, value: &str) -> Vec<u16> {
+    let mut variables = Vec::new();
+    match block {
+        Some(mut at) => loop {
+            let len = (0..).take_while(|&i| unsafe { *at.add(i) } != 0).count();
+            if len == 0 {
+                break;
+            }
+            variables.extend_from_slice(unsafe { std::slice::from_raw_parts(at, len + 1) });
+            at = unsafe { at.add(len + 1) };
+        },
+        None => {
+            for (key, value) in std::env::vars() {
+                variables.extend(format!("{}={}", key, value).encode_utf16().chain([0]));
+            }
+        }
+    }
+    variables.extend(format!("{}={}", name, value).encode_utf16().chain([0, 0]));
+    variables
+}
@@ -0,0 +1,181 @@
+use std::time::Instant;
+
+use tracing::info;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{IsIconic, IsWindow, WINDOWPLACEMENT};
+
+use crate::config::{MonitorConfig, RestoreBehavior};
+
+// A window minimized during the current focus session
+struct SessionWindow {
+    hwnd: HWND,
+    title: String,
+    // Target rule whose pass minimized the window
+    rule: String,
+    // Whether the window was on screen (not already minimized) at the time
+    was_visible: bool,
+}
+
+// What a pass did to a window it didn't minimize
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Faded,
+    Covered,
+    Hidden,
+}
+
+// Windows minimized since focus moved to a target, so each rule's restore
+// behavior can be applied once focus leaves the targets again
+#[derive(Default)]
+pub struct FocusSession {
+    windows: Vec<SessionWindow>,
+    // Windows faded, covered or hidden this session, which all come back when
+    // it ends; kept here so they can be given back one at a time too
+    effects: Vec<(HWND, String, Effect)>,
+    // Delayed restores waiting for their time, cancelled by a new session
+    pending: Vec<(Instant, SessionWindow)>,
+    // Targets moved to their preferred monitor, with where they were before
+    moved: Vec<(HWND, WINDOWPLACEMENT)>,
+    // Windows left minimized when earlier sessions ended
+    kept: Vec<SessionWindow>,
+}
+
+impl FocusSession {
+    pub fn record(&mut self, hwnd: HWND, title: &str, rule: &str, was_visible: bool) {
+        self.kept.retain(|window| window.hwnd != hwnd);
+        if !self.windows.iter().any(|window| window.hwnd == hwnd) {
+            self.windows.push(SessionWindow {
+                hwnd,
+                title: title.to_string(),
+                rule: rule.to_string(),
+                was_visible,
+            });
+        }
+    }
+    
+    pub fn record_effect(&mut self, hwnd: HWND, title: &str, effect: Effect) {
+        if !self.effects.iter().any(|(other, _, _)| *other == hwnd) {
+            self.effects.push((hwnd, title.to_string(), effect));
+        }
+    }
+    
+    pub fn record_move(&mut self, hwnd: HWND, original: WINDOWPLACEMENT) {
+        if !self.moved.iter().any(|(moved, _)| *moved == hwnd) {
+            self.moved.push((hwnd, original));
+        }
+    }
+    
+    // Targets to move back when the session ends
+    pub fn take_moved(&mut self) -> Vec<(HWND, WINDOWPLACEMENT)> {
+        std::mem::take(&mut self.moved)
+    }
+    
+    // Whether a session is running or still has windows waiting to be restored
+    #[cfg(feature = "stats")]
+    pub fn is_active(&self) -> bool {
+        !self.windows.is_empty() || !self.pending.is_empty() || !self.effects.is_empty()
+    }
+    
+    // Number of windows still minimized by this session, or kept minimized
+    // by an earlier one
+    pub fn suppressed_count(&self) -> usize {
+        self.windows
+            .iter()
+            .chain(self.pending.iter().map(|(_, window)| window))
+            .chain(self.kept.iter())
+            .filter(|window| unsafe { IsWindow(Some(window.hwnd)).as_bool() && IsIconic(window.hwnd).as_bool() })
+            .count()
+    }
+    
+    // This session's windows that are still minimized, faded, covered or
+    // hidden, with their titles
+    pub fn suppressed(&self) -> Vec<(HWND, String)> {
+        self.windows
+            .iter()
+            .filter(|window| unsafe { IsIconic(window.hwnd).as_bool() })
+            .map(|window| (window.hwnd, window.title.clone()))
+            .chain(self.effects.iter().map(|(hwnd, title, _)| (*hwnd, title.clone())))
+            .filter(|(hwnd, _)| unsafe { IsWindow(Some(*hwnd)).as_bool() })
+            .collect()
+    }
+    
+    // Drop one window from the session and return it for giving back, with
+    // what was done to it (None when it was minimized)
+    pub fn release(&mut self, hwnd: HWND) -> Option<(HWND, String, Option<Effect>)> {
+        if let Some(index) = self.effects.iter().position(|(other, _, _)| *other == hwnd) {
+            let (hwnd, title, effect) = self.effects.remove(index);
+            return Some((hwnd, title, Some(effect)));
+        }
+        let index = self.windows.iter().position(|window| window.hwnd == hwnd)?;
+        let window = self.windows.remove(index);
+        Some((window.hwnd, window.title, None))
+    }
+    
+    // A target got focus again: windows waiting for a delayed restore stay
+    // minimized and become part of the new session
+    pub fn resume(&mut self) {
+        if !self.pending.is_empty() {
+            info!("  · Cancelled {} pending restores", self.pending.len());
+        }
+        self.windows.extend(self.pending.drain(..).map(|(_, window)| window));
+    }
+    
+    // Focus left the targets: returns the windows to restore right away and
+    // schedules the delayed ones
+    pub fn end(&mut self, config: &MonitorConfig) -> Vec<(HWND, String)> {
+        let mut restore_now = Vec::new();
+        self.effects.clear();
+        self.kept.retain(|window| unsafe { IsWindow(Some(window.hwnd)).as_bool() });
+        for window in self.windows.drain(..) {
+            match config.restore_behavior_for(&window.rule) {
+                RestoreBehavior::KeepMinimized => self.kept.push(window),
+                RestoreBehavior::Restore => restore_now.push((window.hwnd, window.title)),
+                RestoreBehavior::RestoreIfWasVisible => {
+                    if window.was_visible {
+                        restore_now.push((window.hwnd, window.title));
+                    }
+                }
+                RestoreBehavior::RestoreAfter(delay) => self.pending.push((Instant::now() + delay, window)),
+            }
+        }
+        restore_now
+    }
+    
+    // End the session restoring every window, whatever the rules say
+    pub fn end_restoring_all(&mut self) -> Vec<(HWND, String)> {
+        self.effects.clear();
+        self.pending
+            .drain(..)
+            .map(|(_, window)| window)
+            .chain(self.windows.drain(..))
+            .map(|window| (window.hwnd, window.title))
+            .collect()
+    }
+    
+    // Windows earlier sessions left minimized, for undoing everything on exit
+    pub fn take_kept(&mut self) -> Vec<(HWND, String)> {
+        self.kept.drain(..).map(|window| (window.hwnd, window.title)).collect()
+    }
+    
+    // The rules changed mid-session: drops the windows they no longer
+    // suppress and returns them for restoring
+    pub fn release_allowed(&mut self, suppressed: &[HWND]) -> Vec<(HWND, String)> {
+        let (released, kept): (Vec<_>, Vec<_>) = self.windows.drain(..).partition(|window| !suppressed.contains(&window.hwnd));
+        self.windows = kept;
+        self.effects.retain(|(hwnd, _, _)| suppressed.contains(hwnd));
+        released.into_iter().map(|window| (window.hwnd, window.title)).collect()
+    }
+    
+    // When the next delayed restore is due, if any
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.iter().map(|(at, _)| *at).min()
+    }
+    
+    // Delayed restores whose time has come
+    pub fn due(&mut self) -> Vec<(HWND, String)> {
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|(at, _)| *at <= now);
+        self.pending = waiting;
+        due.into_iter().map(|(_, window)| (window.hwnd, window.title)).collect()
+    }
+}
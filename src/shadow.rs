@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_dir;
+use crate::history::now_secs;
+use crate::WindowInfo;
+
+// One decision the monitor would have acted on
+#[derive(Debug, Serialize, Deserialize)]
+struct ShadowDecision {
+    // Seconds since the Unix epoch
+    time: u64,
+    // Target whose session the decision was made in
+    target: String,
+    // "minimize" or "fade"
+    action: String,
+    process: String,
+    title: String,
+}
+
+pub fn shadow_log_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("shadow.jsonl"))
+}
+
+// Function to append the decisions of one pass to shadow.jsonl
+pub fn record(target: &str, action: &str, windows: &[&WindowInfo]) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let path = shadow_log_path().ok_or("LOCALAPPDATA is not set")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let time = now_secs();
+    for window in windows {
+        let decision = ShadowDecision {
+            time,
+            target: target.to_string(),
+            action: action.to_string(),
+            process: window.process_name.clone(),
+            title: window.title.clone(),
+        };
+        writeln!(file, "{}", serde_json::to_string(&decision)?)?;
+    }
+    Ok(())
+}
+
+// Per-app summary of what would have happened to it
+#[derive(Default)]
+struct AppSummary {
+    actions: u64,
+    titles: BTreeMap<String, u64>,
+    targets: BTreeMap<String, u64>,
+}
+
+// Function to summarize the shadow log: which apps would have been acted
+// on, how often, during which targets' sessions and with which titles
+pub fn run_shadow_report() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let path = shadow_log_path().ok_or("LOCALAPPDATA is not set")?;
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("ℹ No shadow decisions recorded yet ({} doesn't exist)", path.display());
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    
+    let mut apps: BTreeMap<String, AppSummary> = BTreeMap::new();
+    let (mut first, mut last, mut total) = (u64::MAX, 0, 0);
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let Ok(decision) = serde_json::from_str::<ShadowDecision>(&line) else { continue };
+        first = first.min(decision.time);
+        last = last.max(decision.time);
+        total += 1;
+        
+        let app = apps.entry(decision.process.to_lowercase()).or_default();
+        app.actions += 1;
+        *app.titles.entry(decision.title).or_default() += 1;
+        *app.targets.entry(decision.target).or_default() += 1;
+    }
+    if total == 0 {
+        println!("ℹ No shadow decisions recorded yet");
+        return Ok(());
+    }
+    
+    let days = (last - first) as f64 / 86400.0;
+    println!("{} decisions over {:.1} days that enforcement would have acted on:\n", total, days);
+    let mut apps: Vec<(String, AppSummary)> = apps.into_iter().collect();
+    apps.sort_by_key(|(_, app)| std::cmp::Reverse(app.actions));
+    for (process, app) in apps {
+        let targets: Vec<String> = app.targets.iter().map(|(target, count)| format!("{} ×{}", target, count)).collect();
+        println!("{} — {} times, during {}", process, app.actions, targets.join(", "));
+        let mut titles: Vec<(String, u64)> = app.titles.into_iter().collect();
+        titles.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        for (title, count) in titles.iter().take(5) {
+            println!("  · {} ×{}", title, count);
+        }
+        if titles.len() > 5 {
+            println!("  · … and {} more titles", titles.len() - 5);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use windows::{
+    core::BOOL,
+    Win32::Foundation::*,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::geometry::{scale_for_window, visible_frame_rect};
+use crate::process::{process_name, window_process_id};
+
+// How far the sharing border may sit outside the shared window's edges (at 96 DPI)
+const BORDER_TOLERANCE: i32 = 16;
+
+// Callback collecting every visible top-level window handle
+unsafe extern "system" fn enum_all_windows_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let handles = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+    if unsafe { IsWindowVisible(hwnd).as_bool() } {
+        handles.push(hwnd);
+    }
+    TRUE
+}
+
+// Check whether a window looks like a screen-sharing border: a topmost,
+// click-through layered window owned by one of the sharing apps
+fn is_sharing_border(hwnd: HWND, sharing_processes: &[String]) -> bool {
+    let ex_style = WINDOW_EX_STYLE(unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) } as u32);
+    let border_style = WS_EX_TOPMOST | WS_EX_TRANSPARENT | WS_EX_LAYERED;
+    if !ex_style.contains(border_style) {
+        return false;
+    }
+    
+    let process = process_name(window_process_id(hwnd));
+    sharing_processes.iter().any(|name| name.eq_ignore_ascii_case(&process))
+}
+
+// Find windows that are currently being shared in a call. Teams and Zoom
+// draw a click-through border window around the window being shared, so a
+// candidate whose bounds sit just inside such a border is treated as shared.
+pub fn shared_windows(sharing_processes: &[String], candidates: &[HWND]) -> Vec<HWND> {
+    if sharing_processes.is_empty() {
+        return Vec::new();
+    }
+    
+    let mut handles: Vec<HWND> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(
+            Some(enum_all_windows_proc),
+            LPARAM(&mut handles as *mut _ as isize),
+        );
+    }
+    
+    let borders: Vec<RECT> = handles
+        .into_iter()
+        .filter(|&hwnd| is_sharing_border(hwnd, sharing_processes))
+        .filter_map(visible_frame_rect)
+        .collect();
+    if borders.is_empty() {
+        return Vec::new();
+    }
+    
+    candidates
+        .iter()
+        .copied()
+        .filter(|&hwnd| {
+            visible_frame_rect(hwnd).is_some_and(|rect| {
+                let tolerance = scale_for_window(BORDER_TOLERANCE, hwnd);
+                borders.iter().any(|border| is_framed_by(&rect, border, tolerance))
+            })
+        })
+        .collect()
+}
+
+// Check whether a border rect tightly surrounds a window rect
+fn is_framed_by(rect: &RECT, border: &RECT, tolerance: i32) -> bool {
+    let within = |outer: i32, inner: i32| (0..=tolerance).contains(&(inner - outer));
+    within(border.left, rect.left) &&
+        within(border.top, rect.top) &&
+        within(rect.right, border.right) &&
+        within(rect.bottom, border.bottom)
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use tracing::warn;
+use windows::{
+    core::BOOL,
+    Win32::Foundation::{LPARAM, WPARAM},
+    Win32::System::Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT},
+    Win32::System::Threading::GetCurrentThreadId,
+    Win32::UI::WindowsAndMessaging::PostThreadMessageW,
+};
+
+use crate::events::WM_WAKE;
+
+// How long the handler waits for the monitor loop to undo its changes.
+// Closing the console window allows about five seconds in total.
+const UNDO_TIMEOUT: Duration = Duration::from_secs(3);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static MONITOR_THREAD: AtomicU32 = AtomicU32::new(0);
+// Set by the monitor loop once everything is restored
+static FINISHED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+// Console control handler: ask the monitor loop to restore what it changed
+// and hold the process open until it has, or the timeout passes
+unsafe extern "system" fn ctrl_handler(ctrl_type: u32) -> BOOL {
+    if !matches!(ctrl_type, CTRL_C_EVENT | CTRL_BREAK_EVENT | CTRL_CLOSE_EVENT) {
+        return false.into();
+    }
+    REQUESTED.store(true, Ordering::Relaxed);
+    unsafe {
+        let _ = PostThreadMessageW(MONITOR_THREAD.load(Ordering::Relaxed), WM_WAKE, WPARAM(0), LPARAM(0));
+    }
+    
+    let (finished, done) = &FINISHED;
+    let finished = finished.lock().unwrap_or_else(|e| e.into_inner());
+    let _ = done.wait_timeout_while(finished, UNDO_TIMEOUT, |finished| !*finished);
+    // Let the remaining handlers clean up what's left and exit the process
+    false.into()
+}
+
+// Function to install the handler from the monitor thread. Handlers run
+// newest first, so install it after the ones it should run before.
+pub fn install() {
+    MONITOR_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(ctrl_handler), true) } {
+        warn!("Could not install exit handler, Ctrl+C will leave windows minimized: {}", e);
+    }
+}
+
+// Function to ask the monitor loop to stop the way Ctrl+C does, from any thread
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+    unsafe {
+        let _ = PostThreadMessageW(MONITOR_THREAD.load(Ordering::Relaxed), WM_WAKE, WPARAM(0), LPARAM(0));
+    }
+}
+
+// Function to forget an earlier stop, before a new monitor starts
+pub fn reset() {
+    REQUESTED.store(false, Ordering::Relaxed);
+    *FINISHED.0.lock().unwrap_or_else(|e| e.into_inner()) = false;
+}
+
+// Whether Ctrl+C (or closing the console) asked the monitor to stop
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+// Function to let the handler know the changes have been undone
+pub fn finished() {
+    let (finished, done) = &FINISHED;
+    *finished.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    done.notify_all();
+}
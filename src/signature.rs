@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::Foundation::*,
+    Win32::Security::Cryptography::{CertGetNameStringW, CERT_NAME_SIMPLE_DISPLAY_TYPE},
+    Win32::Security::WinTrust::{
+        WTHelperGetProvCertFromChain, WTHelperGetProvSignerFromChain, WTHelperProvDataFromStateData, WinVerifyTrust,
+        WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_DATA, WINTRUST_DATA_0, WINTRUST_FILE_INFO,
+        WTD_CACHE_ONLY_URL_RETRIEVAL, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE,
+        WTD_STATEACTION_VERIFY, WTD_UI_NONE,
+    },
+};
+
+// Get the names of every certificate in a file's verified signing chain
+// (signer first, root CA last). Empty when the file has no valid embedded
+// signature; catalog-signed system binaries are not resolved.
+pub fn signing_chain(path: &str) -> Vec<String> {
+    let path = HSTRING::from(path);
+    let mut file_info = WINTRUST_FILE_INFO {
+        cbStruct: std::mem::size_of::<WINTRUST_FILE_INFO>() as u32,
+        pcwszFilePath: PCWSTR(path.as_ptr()),
+        ..Default::default()
+    };
+    let mut trust_data = WINTRUST_DATA {
+        cbStruct: std::mem::size_of::<WINTRUST_DATA>() as u32,
+        dwUIChoice: WTD_UI_NONE,
+        fdwRevocationChecks: WTD_REVOKE_NONE,
+        dwUnionChoice: WTD_CHOICE_FILE,
+        Anonymous: WINTRUST_DATA_0 { pFile: &mut file_info },
+        dwStateAction: WTD_STATEACTION_VERIFY,
+        // Never go to the network from the enforcement path
+        dwProvFlags: WTD_CACHE_ONLY_URL_RETRIEVAL,
+        ..Default::default()
+    };
+    let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+    
+    let mut chain = Vec::new();
+    unsafe {
+        let status = WinVerifyTrust(HWND(INVALID_HANDLE_VALUE.0), &mut action, &mut trust_data as *mut _ as *mut _);
+        if status == 0 {
+            let provider = WTHelperProvDataFromStateData(trust_data.hWVTStateData);
+            let signer = if provider.is_null() {
+                std::ptr::null_mut()
+            } else {
+                WTHelperGetProvSignerFromChain(provider, 0, false, 0)
+            };
+            if !signer.is_null() {
+                for index in 0..(*signer).csCertChain {
+                    let cert = WTHelperGetProvCertFromChain(signer, index);
+                    if cert.is_null() || (*cert).pCert.is_null() {
+                        continue;
+                    }
+                    let mut buffer = [0u16; 256];
+                    let len = CertGetNameStringW((*cert).pCert, CERT_NAME_SIMPLE_DISPLAY_TYPE, 0, None, Some(&mut buffer));
+                    // len includes the terminating null
+                    if len > 1 {
+                        chain.push(OsString::from_wide(&buffer[..len as usize - 1]).to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+        
+        // Release the state WinVerifyTrust kept for the helper calls
+        trust_data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(HWND(INVALID_HANDLE_VALUE.0), &mut action, &mut trust_data as *mut _ as *mut _);
+    }
+    chain
+}
+
+// Cache of signing chains keyed by executable path, since verifying a
+// signature means hashing the whole file
+#[derive(Default)]
+pub struct PublisherCache {
+    chains: HashMap<String, Vec<String>>,
+}
+
+impl PublisherCache {
+    // Get the name of the trusted publisher the executable is signed by
+    // (matched anywhere in its chain, so a corporate CA covers everything it issued)
+    pub fn trusted_publisher<'a>(&mut self, path: &str, publishers: &'a [String]) -> Option<&'a String> {
+        let chain = self
+            .chains
+            .entry(path.to_lowercase())
+            .or_insert_with(|| signing_chain(path));
+        publishers.iter().find(|publisher| {
+            let publisher = publisher.to_lowercase();
+            chain.iter().any(|name| name.to_lowercase().contains(&publisher))
+        })
+    }
+}
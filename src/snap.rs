@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use windows::Win32::{
+    Foundation::{HWND, RECT},
+    UI::WindowsAndMessaging::{IsIconic, IsZoomed},
+};
+
+use crate::geometry::{monitor_work_area, scale_for_window, visible_frame_rect};
+
+// How far apart (in pixels at 96 DPI) two edges may be and still count as touching
+const EDGE_TOLERANCE: i32 = 8;
+
+// Windows does not expose Snap Group membership through a public API, so
+// the group is reconstructed geometrically: snapped windows sit flush with
+// the monitor work area and touch each other edge to edge.
+pub fn snap_group_members(target: HWND, candidates: &[HWND]) -> Vec<HWND> {
+    let Some(work_area) = monitor_work_area(target) else {
+        return Vec::new();
+    };
+    // Every candidate is on the target's monitor, so one tolerance fits all
+    let tolerance = scale_for_window(EDGE_TOLERANCE, target);
+    let Some(target_rect) = snapped_rect(target, &work_area, tolerance) else {
+        return Vec::new();
+    };
+    
+    // Only consider snapped windows on the same monitor as the target
+    let mut pending: Vec<(HWND, RECT)> = candidates
+        .iter()
+        .filter(|&&hwnd| hwnd != target)
+        .filter(|&&hwnd| monitor_work_area(hwnd) == Some(work_area))
+        .filter_map(|&hwnd| snapped_rect(hwnd, &work_area, tolerance).map(|rect| (hwnd, rect)))
+        .collect();
+    
+    // Walk outwards from the target through touching windows
+    let mut members = Vec::new();
+    let mut queue = VecDeque::from([target_rect]);
+    while let Some(rect) = queue.pop_front() {
+        let (touching, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, other)| are_adjacent(&rect, other, tolerance));
+        pending = rest;
+        
+        for (hwnd, other) in touching {
+            members.push(hwnd);
+            queue.push_back(other);
+        }
+    }
+    members
+}
+
+// Get a window's rect if it looks snapped: restored, inside the work area
+// and flush with at least two of its edges
+fn snapped_rect(hwnd: HWND, work_area: &RECT, tolerance: i32) -> Option<RECT> {
+    if unsafe { IsIconic(hwnd).as_bool() || IsZoomed(hwnd).as_bool() } {
+        return None;
+    }
+    
+    let rect = visible_frame_rect(hwnd)?;
+    let inside = rect.left >= work_area.left - tolerance &&
+        rect.top >= work_area.top - tolerance &&
+        rect.right <= work_area.right + tolerance &&
+        rect.bottom <= work_area.bottom + tolerance;
+    
+    let flush_edges = [
+        near(rect.left, work_area.left, tolerance),
+        near(rect.top, work_area.top, tolerance),
+        near(rect.right, work_area.right, tolerance),
+        near(rect.bottom, work_area.bottom, tolerance),
+    ]
+    .iter()
+    .filter(|&&flush| flush)
+    .count();
+    
+    (inside && flush_edges >= 2).then_some(rect)
+}
+
+// Check whether two rects share an edge with overlapping extent
+fn are_adjacent(a: &RECT, b: &RECT, tolerance: i32) -> bool {
+    let overlap_vertically = a.top < b.bottom - tolerance && b.top < a.bottom - tolerance;
+    let overlap_horizontally = a.left < b.right - tolerance && b.left < a.right - tolerance;
+    
+    ((near(a.right, b.left, tolerance) || near(b.right, a.left, tolerance)) && overlap_vertically) ||
+        ((near(a.bottom, b.top, tolerance) || near(b.bottom, a.top, tolerance)) && overlap_horizontally)
+}
+
+fn near(a: i32, b: i32, tolerance: i32) -> bool {
+    (a - b).abs() <= tolerance
+}
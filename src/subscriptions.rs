@@ -0,0 +1,172 @@
+// Without the ipc feature nobody can subscribe, so events are only dropped
+#![cfg_attr(not(feature = "ipc"), allow(dead_code))]
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::history::now_secs;
+
+// Events buffered per subscriber before the oldest are dropped; a client
+// that stops reading can't make the monitor hold on to everything
+const BUFFER_LIMIT: usize = 256;
+
+// Something the monitor did, streamed to subscribed pipe clients as one JSON
+// object per line, e.g. {"time":..,"kind":"action","event":"minimize","process":"slack.exe","title":".."}
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    time: u64,
+    kind: &'static str,
+    event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+impl Event {
+    fn new(kind: &'static str, event: &str) -> Self {
+        Self {
+            time: now_secs(),
+            kind,
+            event: event.to_string(),
+            process: None,
+            title: None,
+            target: None,
+        }
+    }
+    
+    // A window was minimized, faded, hidden, moved or restored
+    pub fn action(action: &str, process: &str, title: &str) -> Self {
+        Self {
+            process: Some(process.to_string()),
+            title: Some(title.to_string()),
+            ..Self::new("action", action)
+        }
+    }
+    
+    // A focus session started or ended, or monitoring was paused or resumed
+    pub fn session(event: &str, target: Option<&str>, process: Option<&str>) -> Self {
+        Self {
+            target: target.map(str::to_string),
+            process: process.map(str::to_string),
+            ..Self::new("session", event)
+        }
+    }
+    
+    // Something also shown as a tray notification: a "nudge" or a "budget" notice
+    pub fn notification(event: &str, text: &str) -> Self {
+        Self {
+            title: Some(text.to_string()),
+            ..Self::new("notification", event)
+        }
+    }
+}
+
+// Which events a client wants. No kinds means every kind; with a process,
+// events about other processes are left out (ones about no process, such as
+// pausing, still come through).
+#[derive(Debug, Default)]
+pub struct Filter {
+    kinds: Vec<&'static str>,
+    process: Option<String>,
+}
+
+impl Filter {
+    // Parse the arguments of `subscribe`, e.g. "actions sessions process=slack.exe"
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        for arg in args.split_whitespace() {
+            match arg {
+                "actions" => filter.kinds.push("action"),
+                "sessions" => filter.kinds.push("session"),
+                "notifications" => filter.kinds.push("notification"),
+                _ => match arg.strip_prefix("process=") {
+                    Some(process) if !process.is_empty() => filter.process = Some(process.to_lowercase()),
+                    _ => return Err(format!("unknown subscription filter {:?}", arg)),
+                },
+            }
+        }
+        Ok(filter)
+    }
+    
+    fn matches(&self, event: &Event) -> bool {
+        let kind_matches = self.kinds.is_empty() || self.kinds.contains(&event.kind);
+        let process_matches = match (&self.process, &event.process) {
+            (Some(wanted), Some(process)) => process.to_lowercase() == *wanted,
+            _ => true,
+        };
+        kind_matches && process_matches
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+// One client's stream: its filter and the events it hasn't read yet
+pub struct Subscriber {
+    filter: Filter,
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+impl Subscriber {
+    // Wait up to `timeout` for events and take everything buffered, with a
+    // note first when some had to be dropped; empty when nothing came
+    pub fn next_batch(&self, timeout: Duration) -> Vec<String> {
+        let queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut queue, _) = self
+            .ready
+            .wait_timeout_while(queue, timeout, |queue| queue.lines.is_empty())
+            .unwrap_or_else(|e| e.into_inner());
+        let mut batch = Vec::with_capacity(queue.lines.len() + 1);
+        if queue.dropped > 0 {
+            batch.push(format!("{{\"kind\":\"dropped\",\"count\":{}}}", queue.dropped));
+            queue.dropped = 0;
+        }
+        batch.extend(queue.lines.drain(..));
+        batch
+    }
+}
+
+static SUBSCRIBERS: Mutex<Vec<Arc<Subscriber>>> = Mutex::new(Vec::new());
+
+// Function to start a stream for a client
+pub fn subscribe(filter: Filter) -> Arc<Subscriber> {
+    let subscriber = Arc::new(Subscriber {
+        filter,
+        queue: Mutex::new(Queue::default()),
+        ready: Condvar::new(),
+    });
+    SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner()).push(subscriber.clone());
+    subscriber
+}
+
+// Function to end a client's stream once it has gone away
+pub fn unsubscribe(subscriber: &Arc<Subscriber>) {
+    SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner()).retain(|existing| !Arc::ptr_eq(existing, subscriber));
+}
+
+// Function to hand an event to every subscriber whose filter wants it; does
+// nothing when nobody is listening
+pub fn publish(event: Event) {
+    let subscribers = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut line = None;
+    for subscriber in subscribers.iter().filter(|subscriber| subscriber.filter.matches(&event)) {
+        let line = line.get_or_insert_with(|| serde_json::to_string(&event).unwrap_or_default());
+        let mut queue = subscriber.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.lines.len() == BUFFER_LIMIT {
+            queue.lines.pop_front();
+            queue.dropped += 1;
+        }
+        queue.lines.push_back(line.clone());
+        subscriber.ready.notify_one();
+    }
+}
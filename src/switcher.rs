@@ -0,0 +1,138 @@
+use std::sync::Mutex;
+
+use tracing::warn;
+use windows::{
+    core::BOOL,
+    Win32::Foundation::*,
+    Win32::System::Console::SetConsoleCtrlHandler,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+// Suppressed windows taken out of Alt-Tab and the taskbar, with their original
+// extended styles. Kept globally so the console control handler can put them
+// back when the monitor exits.
+static HIDDEN: Mutex<Vec<(usize, isize)>> = Mutex::new(Vec::new());
+// Windows hidden outright (SW_HIDE), shown again along with the ones above
+static WITHDRAWN: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+// Function to change a window's extended style so the taskbar notices:
+// the shell only re-reads it when the window is shown again
+fn apply_ex_style(hwnd: HWND, ex_style: isize) {
+    unsafe {
+        let minimized = IsIconic(hwnd).as_bool();
+        let _ = ShowWindow(hwnd, SW_HIDE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style);
+        let _ = ShowWindow(hwnd, if minimized { SW_SHOWMINNOACTIVE } else { SW_SHOWNOACTIVATE });
+    }
+}
+
+// Console control handler: give windows their taskbar buttons back on exit
+unsafe extern "system" fn ctrl_handler(_ctrl_type: u32) -> BOOL {
+    show_all_in_switcher();
+    // Let the default handler exit the process
+    false.into()
+}
+
+// Function to install the exit handler; call once before hiding anything
+pub fn install_exit_handler() {
+    if let Err(e) = unsafe { SetConsoleCtrlHandler(Some(ctrl_handler), true) } {
+        warn!("Could not install exit handler, hidden windows may stay off the taskbar: {}", e);
+    }
+}
+
+// Function to take a window out of Alt-Tab and the taskbar by making it a
+// tool window
+pub fn hide_from_switcher(hwnd: HWND) {
+    let mut hidden = HIDDEN.lock().unwrap_or_else(|e| e.into_inner());
+    if hidden.iter().any(|(existing, _)| *existing == hwnd.0 as usize) {
+        return;
+    }
+    
+    let original = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) };
+    let tool_window = (original | WS_EX_TOOLWINDOW.0 as isize) & !(WS_EX_APPWINDOW.0 as isize);
+    if tool_window != original {
+        apply_ex_style(hwnd, tool_window);
+        hidden.push((hwnd.0 as usize, original));
+    }
+}
+
+// Function to hide a window completely, taking it off the screen, Alt-Tab
+// and the taskbar at once. Returns false for windows that weren't visible.
+pub fn withdraw(hwnd: HWND) -> bool {
+    let mut withdrawn = WITHDRAWN.lock().unwrap_or_else(|e| e.into_inner());
+    if !unsafe { IsWindowVisible(hwnd).as_bool() } {
+        return false;
+    }
+    unsafe {
+        let _ = ShowWindow(hwnd, SW_HIDE);
+    }
+    withdrawn.push(hwnd.0 as usize);
+    true
+}
+
+// Function to show the withdrawn windows picked by `show` again. Returns how
+// many were shown.
+fn unwithdraw_where(show: impl Fn(HWND) -> bool) -> usize {
+    let mut withdrawn = WITHDRAWN.lock().unwrap_or_else(|e| e.into_inner());
+    let before = withdrawn.len();
+    withdrawn.retain(|&hwnd| {
+        let hwnd = HWND(hwnd as *mut _);
+        if !show(hwnd) {
+            return true;
+        }
+        if unsafe { IsWindow(Some(hwnd)).as_bool() } {
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+        false
+    });
+    before - withdrawn.len()
+}
+
+// Function to show just these withdrawn windows again
+pub fn unwithdraw(windows: &[HWND]) {
+    unwithdraw_where(|hwnd| windows.contains(&hwnd));
+}
+
+// Function to show the withdrawn windows again, except the given ones.
+// Returns how many were shown.
+pub fn unwithdraw_except(keep: &[HWND]) -> usize {
+    unwithdraw_where(|hwnd| !keep.contains(&hwnd))
+}
+
+// Function to give just these windows their original extended style back
+pub fn show_in_switcher(windows: &[HWND]) {
+    let mut hidden = HIDDEN.lock().unwrap_or_else(|e| e.into_inner());
+    hidden.retain(|&(hwnd, original)| {
+        let hwnd = HWND(hwnd as *mut _);
+        if !windows.contains(&hwnd) {
+            return true;
+        }
+        if unsafe { IsWindow(Some(hwnd)).as_bool() } {
+            apply_ex_style(hwnd, original);
+        }
+        false
+    });
+}
+
+// Function to give every hidden window its original extended style back and
+// show the withdrawn ones again
+pub fn show_all_in_switcher() {
+    for hwnd in WITHDRAWN.lock().unwrap_or_else(|e| e.into_inner()).drain(..) {
+        let hwnd = HWND(hwnd as *mut _);
+        if unsafe { IsWindow(Some(hwnd)).as_bool() } {
+            unsafe {
+                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+    }
+    
+    let mut hidden = HIDDEN.lock().unwrap_or_else(|e| e.into_inner());
+    for (hwnd, original) in hidden.drain(..) {
+        let hwnd = HWND(hwnd as *mut _);
+        if unsafe { IsWindow(Some(hwnd)).as_bool() } {
+            apply_ex_style(hwnd, original);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use windows::{
+    core::{w, Interface, BSTR, HSTRING, PCWSTR},
+    Win32::Foundation::{CloseHandle, E_ACCESSDENIED, ERROR_FILE_NOT_FOUND, VARIANT_FALSE},
+    Win32::System::Com::COINIT_MULTITHREADED,
+    Win32::System::TaskScheduler::*,
+    Win32::System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE},
+    Win32::System::Variant::VARIANT,
+    Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW},
+    Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL,
+};
+
+use crate::com;
+use crate::process::is_elevated;
+
+// Name of the task in the root folder of the Task Scheduler library
+const TASK_NAME: &str = "fak-opacity";
+
+// Function to name the signed-in user the way the Task Scheduler expects (DOMAIN\user)
+fn current_user() -> Result<String, Box<dyn std::error::Error>> {
+    let user = std::env::var("USERNAME").map_err(|_| "USERNAME is not set")?;
+    Ok(match std::env::var("USERDOMAIN") {
+        Ok(domain) => format!("{}\\{}", domain, user),
+        Err(_) => user,
+    })
+}
+
+// Function to quote one command-line argument the way the C runtime splits them
+pub(crate) fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // Backslashes only need escaping in front of a quote
+        let escaped = if c == '"' { backslashes * 2 + 1 } else { backslashes };
+        quoted.push_str(&"\\".repeat(escaped));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+// Function to run this executable again elevated (showing the UAC prompt)
+// and wait for it, for the parts that need administrator rights
+fn run_elevated(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let exe = HSTRING::from(std::env::current_exe()?.as_os_str());
+    let parameters = HSTRING::from(args.iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" "));
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(exe.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    unsafe { ShellExecuteExW(&mut info) }.map_err(|e| format!("elevation was refused or failed: {}", e))?;
+    
+    let mut exit_code = 1u32;
+    unsafe {
+        WaitForSingleObject(info.hProcess, INFINITE);
+        let _ = GetExitCodeProcess(info.hProcess, &mut exit_code);
+        let _ = CloseHandle(info.hProcess);
+    }
+    if exit_code != 0 {
+        return Err(format!("the elevated instance failed (exit code {})", exit_code).into());
+    }
+    Ok(())
+}
+
+// Function to connect to the Task Scheduler on this machine
+fn connect() -> Result<ITaskService, Box<dyn std::error::Error>> {
+    let scheduler: ITaskService = com::create_instance(&TaskScheduler, COINIT_MULTITHREADED)?;
+    unsafe {
+        scheduler.Connect(&VARIANT::default(), &VARIANT::default(), &VARIANT::default(), &VARIANT::default())?;
+        Ok(scheduler)
+    }
+}
+
+// Function to create (or replace) a task that starts monitoring when the user
+// signs in, passing the given run flags along. Highest privileges let the
+// monitor act on elevated windows too; registering such a task needs
+// elevation, which is asked for when missing.
+pub fn install_task(run_args: &[String], highest: bool, user: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let user = match user {
+        Some(user) => user,
+        None => current_user()?,
+    };
+    if highest && !is_elevated() {
+        println!("ℹ Highest privileges need administrator rights, asking for elevation");
+        let mut args = vec!["install-task".to_string(), "--highest".to_string(), "--user".to_string(), user.clone()];
+        args.extend(run_args.iter().cloned());
+        run_elevated(&args)?;
+        println!("✓ Installed the {} task for {} with highest privileges", TASK_NAME, user);
+        return Ok(());
+    }
+    
+    let exe = std::env::current_exe()?;
+    let arguments: Vec<String> = ["run".to_string(), "--at-logon".to_string()].into_iter().chain(run_args.iter().map(|arg| quote(arg))).collect();
+    let arguments = arguments.join(" ");
+    let user_id = BSTR::from(user.as_str());
+    unsafe {
+        let scheduler = connect()?;
+        let folder = scheduler.GetFolder(&BSTR::from("\\"))?;
+        let task = scheduler.NewTask(0)?;
+        task.RegistrationInfo()?.SetDescription(&BSTR::from("Starts fak-opacity window monitoring at sign-in"))?;
+        
+        let principal = task.Principal()?;
+        principal.SetUserId(&user_id)?;
+        principal.SetLogonType(TASK_LOGON_INTERACTIVE_TOKEN)?;
+        principal.SetRunLevel(if highest { TASK_RUNLEVEL_HIGHEST } else { TASK_RUNLEVEL_LUA })?;
+        
+        // Run for as long as the session lasts, on battery too, only once
+        let settings = task.Settings()?;
+        settings.SetDisallowStartIfOnBatteries(VARIANT_FALSE)?;
+        settings.SetStopIfGoingOnBatteries(VARIANT_FALSE)?;
+        settings.SetExecutionTimeLimit(&BSTR::from("PT0S"))?;
+        settings.SetMultipleInstances(TASK_INSTANCES_IGNORE_NEW)?;
+        
+        let trigger: ILogonTrigger = task.Triggers()?.Create(TASK_TRIGGER_LOGON)?.cast()?;
+        trigger.SetUserId(&user_id)?;
+        let action: IExecAction = task.Actions()?.Create(TASK_ACTION_EXEC)?.cast()?;
+        action.SetPath(&BSTR::from(exe.to_string_lossy().as_ref()))?;
+        action.SetArguments(&BSTR::from(arguments.as_str()))?;
+        
+        folder
+            .RegisterTaskDefinition(
+                &BSTR::from(TASK_NAME),
+                &task,
+                TASK_CREATE_OR_UPDATE.0,
+                &VARIANT::from(user.as_str()),
+                &VARIANT::default(),
+                TASK_LOGON_INTERACTIVE_TOKEN,
+                &VARIANT::default(),
+            )
+            .map_err(|e| match e.code() {
+                E_ACCESSDENIED => "access denied; a task for another user needs an elevated prompt".to_string(),
+                _ => e.to_string(),
+            })?;
+    }
+    println!("✓ Installed the {} task: `{} {}` when {} signs in", TASK_NAME, exe.display(), arguments, user);
+    Ok(())
+}
+
+// Function to delete the task; one registered with highest privileges can
+// only be deleted elevated, which is asked for when needed
+pub fn uninstall_task() -> Result<(), Box<dyn std::error::Error>> {
+    let deleted = unsafe { connect()?.GetFolder(&BSTR::from("\\"))?.DeleteTask(&BSTR::from(TASK_NAME), 0) };
+    match deleted {
+        Ok(()) => println!("✓ Removed the {} task", TASK_NAME),
+        Err(e) if e.code() == E_ACCESSDENIED && !is_elevated() => {
+            println!("ℹ The task needs administrator rights to remove, asking for elevation");
+            run_elevated(&["uninstall-task".to_string()])?;
+            println!("✓ Removed the {} task", TASK_NAME);
+        }
+        Err(e) if e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => {
+            println!("ℹ No {} task is installed", TASK_NAME);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use windows::{
+    core::*,
+    Win32::Foundation::*,
+    Win32::System::LibraryLoader::GetModuleHandleW,
+    Win32::System::Threading::GetCurrentThreadId,
+    Win32::UI::Shell::*,
+    Win32::UI::WindowsAndMessaging::*,
+};
+
+use crate::build_info;
+use crate::events::WM_WAKE;
+use crate::frontend::TrayCommand;
+
+// Callback message the shell sends for clicks on the icon
+const WM_TRAY: u32 = WM_APP + 2;
+// Thread messages to the tray thread
+const WM_REFRESH: u32 = WM_APP;
+const WM_NOTIFY_PENDING: u32 = WM_APP + 3;
+
+// Menu item ids
+const ID_PAUSE: usize = 1;
+const ID_RESUME: usize = 2;
+const ID_RESTORE_ALL: usize = 3;
+const ID_EXIT: usize = 4;
+
+// State shared with the window procedure, which runs on the tray thread
+static COMMANDS: Mutex<Option<Sender<TrayCommand>>> = Mutex::new(None);
+static MONITOR_THREAD: AtomicU32 = AtomicU32::new(0);
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static DEGRADED: Mutex<String> = Mutex::new(String::new());
+// Notification waiting to be shown, as (title, text)
+static NOTIFICATION: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+// Function to copy text into a fixed-size, NUL-terminated buffer
+fn copy_wide(buffer: &mut [u16], text: &str) {
+    let max = buffer.len() - 1;
+    for (slot, unit) in buffer.iter_mut().take(max).zip(text.encode_utf16()) {
+        *slot = unit;
+    }
+}
+
+// Function to fill in the icon's data; the tooltip says whether the monitor is
+// paused, which features failed to start and which build is running
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: 1,
+        uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP,
+        uCallbackMessage: WM_TRAY,
+        hIcon: unsafe { LoadIconW(None, IDI_APPLICATION) }.unwrap_or_default(),
+        ..Default::default()
+    };
+    // State first, since the tooltip is cut off at 127 characters
+    let mut tip = String::new();
+    if PAUSED.load(Ordering::Relaxed) {
+        tip.push_str("paused\n");
+    }
+    let degraded = DEGRADED.lock().unwrap_or_else(|e| e.into_inner());
+    if !degraded.is_empty() {
+        tip.push_str(&format!("degraded: {}\n", degraded));
+    }
+    tip.push_str(&build_info::version_string());
+    copy_wide(&mut data.szTip, &tip);
+    data
+}
+
+// Function to show the context menu at the cursor and return the picked item
+fn show_menu(hwnd: HWND) -> usize {
+    unsafe {
+        let Ok(menu) = CreatePopupMenu() else { return 0 };
+        let paused = PAUSED.load(Ordering::Relaxed);
+        let (pause_flags, resume_flags) = if paused { (MF_GRAYED, MF_ENABLED) } else { (MF_ENABLED, MF_GRAYED) };
+        let _ = AppendMenuW(menu, MF_STRING | pause_flags, ID_PAUSE, w!("Pause monitoring"));
+        let _ = AppendMenuW(menu, MF_STRING | resume_flags, ID_RESUME, w!("Resume"));
+        let _ = AppendMenuW(menu, MF_STRING, ID_RESTORE_ALL, w!("Restore all"));
+        let _ = AppendMenuW(menu, MF_SEPARATOR, 0, None);
+        let _ = AppendMenuW(menu, MF_STRING, ID_EXIT, w!("Exit"));
+        
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        // Without this the menu doesn't close when clicking elsewhere
+        let _ = SetForegroundWindow(hwnd);
+        let picked = TrackPopupMenu(menu, TPM_RETURNCMD | TPM_RIGHTBUTTON, cursor.x, cursor.y, None, hwnd, None);
+        let _ = DestroyMenu(menu);
+        picked.0 as usize
+    }
+}
+
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+    if TASKBAR_CREATED.load(Ordering::Relaxed) == 0 {
+        TASKBAR_CREATED.store(unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) }, Ordering::Relaxed);
+    }
+    
+    match msg {
+        WM_TRAY if matches!(lparam.0 as u32, WM_RBUTTONUP | WM_LBUTTONUP) => {
+            let command = match show_menu(hwnd) {
+                ID_PAUSE => TrayCommand::Pause,
+                ID_RESUME => TrayCommand::Resume,
+                ID_RESTORE_ALL => TrayCommand::RestoreAll,
+                ID_EXIT => TrayCommand::Exit,
+                _ => return LRESULT(0),
+            };
+            if let Some(commands) = COMMANDS.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+                let _ = commands.send(command);
+            }
+            unsafe {
+                let _ = PostThreadMessageW(MONITOR_THREAD.load(Ordering::Relaxed), WM_WAKE, WPARAM(0), LPARAM(0));
+            }
+            LRESULT(0)
+        }
+        // Explorer restarted; the icon has to be added again
+        _ if msg == TASKBAR_CREATED.load(Ordering::Relaxed) => {
+            unsafe {
+                let _ = Shell_NotifyIconW(NIM_ADD, &icon_data(hwnd));
+            }
+            LRESULT(0)
+        }
+        _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+}
+
+// Function to run the tray icon's window and message loop until WM_QUIT
+fn run_icon(ready: Sender<Option<u32>>) {
+    unsafe {
+        let instance: HINSTANCE = GetModuleHandleW(None).map(|module| module.into()).unwrap_or_default();
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            lpszClassName: w!("fak-opacity-tray"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+        
+        // Never shown; only here to receive the icon's messages
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("fak-opacity-tray"),
+            w!("fak-opacity"),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance),
+            None,
+        );
+        let Ok(hwnd) = hwnd else {
+            let _ = ready.send(None);
+            return;
+        };
+        if !Shell_NotifyIconW(NIM_ADD, &icon_data(hwnd)).as_bool() {
+            let _ = DestroyWindow(hwnd);
+            let _ = ready.send(None);
+            return;
+        }
+        let _ = ready.send(Some(GetCurrentThreadId()));
+        
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // Thread messages (no window) come from the monitor thread
+            if msg.hwnd.is_invalid() {
+                let mut data = icon_data(hwnd);
+                if msg.message == WM_NOTIFY_PENDING
+                    && let Some((title, text)) = NOTIFICATION.lock().unwrap_or_else(|e| e.into_inner()).take()
+                {
+                    // Shown as a toast on Windows 10 and later
+                    data.uFlags |= NIF_INFO;
+                    data.dwInfoFlags = NIIF_INFO;
+                    copy_wide(&mut data.szInfoTitle, &title);
+                    copy_wide(&mut data.szInfo, &text);
+                }
+                let _ = Shell_NotifyIconW(NIM_MODIFY, &data);
+                continue;
+            }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        
+        let _ = Shell_NotifyIconW(NIM_DELETE, &icon_data(hwnd));
+        let _ = DestroyWindow(hwnd);
+    }
+}
+
+// Notification-area icon with Pause, Resume, Restore all and Exit. It runs on
+// its own thread so the menu stays responsive whatever the monitor is doing.
+pub struct TrayIcon {
+    commands: Receiver<TrayCommand>,
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TrayIcon {
+    // Show the icon; None when the notification area isn't available
+    pub fn spawn() -> Option<Self> {
+        let (sender, commands) = mpsc::channel();
+        *COMMANDS.lock().unwrap_or_else(|e| e.into_inner()) = Some(sender);
+        MONITOR_THREAD.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+        
+        let (ready, started) = mpsc::channel();
+        let thread = thread::spawn(move || run_icon(ready));
+        let thread_id = started.recv().ok().flatten()?;
+        Some(Self {
+            commands,
+            thread_id,
+            thread: Some(thread),
+        })
+    }
+    
+    // Commands picked since the last call
+    pub fn commands(&self) -> Vec<TrayCommand> {
+        self.commands.try_iter().collect()
+    }
+    
+    // Reflect the pause state in the menu and tooltip
+    pub fn set_paused(&self, paused: bool) {
+        PAUSED.store(paused, Ordering::Relaxed);
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_REFRESH, WPARAM(0), LPARAM(0));
+        }
+    }
+    
+    // Record the features that failed to start, for the tooltip
+    pub fn set_degraded(&self, reasons: &[String]) {
+        *DEGRADED.lock().unwrap_or_else(|e| e.into_inner()) = reasons.join(", ");
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_REFRESH, WPARAM(0), LPARAM(0));
+        }
+    }
+    
+    // Show a notification from the icon (nudges and focus budgets)
+    pub fn notify(&self, title: &str, text: &str) {
+        *NOTIFICATION.lock().unwrap_or_else(|e| e.into_inner()) = Some((title.to_string(), text.to_string()));
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_NOTIFY_PENDING, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+impl Drop for TrayIcon {
+    // Remove the icon before the process exits
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::{info, warn};
+use ureq::tls::{RootCerts, TlsConfig, TlsProvider};
+use ureq::{Agent, Proxy};
+
+use crate::build_info;
+use crate::config::UpdateCheck;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/clarifei/fak-opacity/releases/latest";
+
+// Latest published release
+pub struct Release {
+    pub version: String,
+    pub url: String,
+}
+
+// Fetch the latest release from GitHub
+fn latest_release(proxy: Option<&str>) -> std::result::Result<Release, Box<dyn std::error::Error>> {
+    let proxy = match proxy {
+        Some(proxy) => Some(Proxy::new(proxy)?),
+        // Environment variables first, then the Windows system proxy
+        None => Proxy::try_from_env(),
+    };
+    
+    let agent: Agent = Agent::config_builder()
+        .proxy(proxy)
+        .tls_config(
+            // Use schannel and the Windows certificate store
+            TlsConfig::builder()
+                .provider(TlsProvider::NativeTls)
+                .root_certs(RootCerts::PlatformVerifier)
+                .build(),
+        )
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into();
+    
+    let release: Value = agent
+        .get(LATEST_RELEASE_URL)
+        .header("User-Agent", concat!("fak-opacity/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .call()?
+        .body_mut()
+        .read_json()?;
+    
+    let tag = release["tag_name"].as_str().ok_or("release has no tag_name")?;
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        url: release["html_url"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+// Parse a dotted version ("1.2.3") into comparable numeric parts
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+// Check whether a released version is newer than the running one
+fn is_newer(candidate: &str, current: &str) -> bool {
+    version_parts(candidate) > version_parts(current)
+}
+
+// Return the latest release if it is newer than this build
+pub fn check_for_update(proxy: Option<&str>) -> std::result::Result<Option<Release>, Box<dyn std::error::Error>> {
+    let release = latest_release(proxy)?;
+    Ok(is_newer(&release.version, build_info::VERSION).then_some(release))
+}
+
+// Handle the `update check` command
+pub fn run_update_check(policy: UpdateCheck, proxy: Option<&str>) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    if policy == UpdateCheck::Disabled {
+        println!("Update checks are disabled in the configuration");
+        return Ok(());
+    }
+    
+    println!("Current version: {}", build_info::version_string());
+    match check_for_update(proxy)? {
+        Some(release) => println!("A newer version is available: {} ({})", release.version, release.url),
+        None => println!("You are running the latest version"),
+    }
+    Ok(())
+}
+
+// Check for updates once on a background thread, only printing a notice
+pub fn spawn_background_check(proxy: Option<String>) {
+    thread::spawn(move || match check_for_update(proxy.as_deref()) {
+        Ok(Some(release)) => {
+            info!("ℹ A newer version is available: {} ({})\n", release.version, release.url);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Update check failed: {}", e),
+    });
+}
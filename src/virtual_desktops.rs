@@ -0,0 +1,29 @@
+use std::cell::RefCell;
+
+use windows::{
+    Win32::Foundation::HWND,
+    Win32::System::Com::COINIT_APARTMENTTHREADED,
+    Win32::UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager},
+};
+
+use crate::com;
+
+thread_local! {
+    // The shell's desktop manager, created once per thread asking
+    static MANAGER: RefCell<Option<IVirtualDesktopManager>> = const { RefCell::new(None) };
+}
+
+// Find the candidates parked on another virtual desktop. The target has focus,
+// so its desktop is the current one; windows pinned to every desktop count as
+// being on it. Without the shell's desktop manager (older Windows, no
+// Explorer) every window is treated as being here.
+pub fn on_other_desktops(candidates: &[HWND]) -> Vec<HWND> {
+    let Ok(manager) = com::thread_instance(&MANAGER, &VirtualDesktopManager, COINIT_APARTMENTTHREADED) else {
+        return Vec::new();
+    };
+    candidates
+        .iter()
+        .copied()
+        .filter(|&hwnd| unsafe { manager.IsWindowOnCurrentVirtualDesktop(hwnd) }.is_ok_and(|here| !here.as_bool()))
+        .collect()
+}
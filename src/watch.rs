@@ -0,0 +1,103 @@
+use std::thread;
+use std::time::Instant;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, IsIconic};
+
+use crate::config::MonitorConfig;
+use crate::process::ProcessNameCache;
+use crate::{get_process_windows_uncached, window_info, RuleSet, WindowInfo};
+
+// Last observed state of a watched window
+struct WatchedWindow {
+    hwnd: HWND,
+    title: String,
+    minimized: bool,
+}
+
+// Function to print one lifecycle event with the time since watching started
+fn report(start: Instant, event: &str, title: &str) {
+    println!("[{:>9.3}s] {:<10} {}", start.elapsed().as_secs_f64(), event, title);
+}
+
+// Function to stream the window lifecycle of one process (created, title
+// changed, focused, minimized, restored, closed) until Ctrl+C
+pub fn run_watch(process: &str, config: &MonitorConfig) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    println!("Watching windows of {} (Ctrl+C to stop)\n", process);
+    
+    let matchers = RuleSet::new(config)?;
+    let mut process_names = ProcessNameCache::default();
+    let processes = [process.to_string()];
+    let start = Instant::now();
+    let mut known: Vec<WatchedWindow> = Vec::new();
+    let mut last_foreground = HWND::default();
+    let mut first_pass = true;
+    
+    loop {
+        let windows = get_process_windows_uncached(&processes, config.include_owned_windows, &mut process_names);
+        let foreground = unsafe { GetForegroundWindow() };
+        
+        for window in &windows {
+            let minimized = unsafe { IsIconic(window.hwnd).as_bool() };
+            match known.iter_mut().find(|known| known.hwnd == window.hwnd) {
+                None => {
+                    let event = if first_pass { "existing" } else { "created" };
+                    report(start, event, &window.title);
+                    if minimized {
+                        report(start, "minimized", &window.title);
+                    }
+                    known.push(WatchedWindow { hwnd: window.hwnd, title: window.title.clone(), minimized });
+                }
+                Some(known) => {
+                    if known.title != window.title {
+                        report(start, "title", &format!("'{}' → '{}'", known.title, window.title));
+                        known.title = window.title.clone();
+                    }
+                    if minimized != known.minimized {
+                        if minimized {
+                            report(start, "minimized", &minimized_by(window, foreground, config, &matchers, &mut process_names));
+                        } else {
+                            report(start, "restored", &window.title);
+                        }
+                        known.minimized = minimized;
+                    }
+                }
+            }
+        }
+        
+        known.retain(|known| {
+            let alive = windows.iter().any(|window| window.hwnd == known.hwnd);
+            if !alive {
+                report(start, "closed", &known.title);
+            }
+            alive
+        });
+        
+        if foreground != last_foreground {
+            if let Some(window) = windows.iter().find(|window| window.hwnd == foreground) {
+                report(start, "focused", &window.title);
+            }
+            last_foreground = foreground;
+        }
+        
+        first_pass = false;
+        thread::sleep(config.poll_interval);
+    }
+}
+
+// Function to describe a minimize, noting when a configured target had focus
+// (which is when the monitor would have minimized it)
+fn minimized_by(
+    window: &WindowInfo,
+    foreground: HWND,
+    config: &MonitorConfig,
+    matchers: &RuleSet,
+    process_names: &mut ProcessNameCache,
+) -> String {
+    let target = window_info(foreground, config.include_owned_windows, process_names)
+        .and_then(|active| matchers.target(&active).cloned());
+    match target {
+        Some(target) => format!("{} (target '{}' had focus, likely by fak-opacity)", window.title, target),
+        None => format!("{} (no target had focus, likely by the user)", window.title),
+    }
+}
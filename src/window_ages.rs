@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::WindowInfo;
+
+// How long each window has been around, judged from the enumerations the
+// passes make. A window missing from one enumeration and there in the next
+// was created in between; it is taken to be as old as the earlier one, so a
+// long gap between passes never holds a window back. Windows there from the
+// first enumeration on predate the run and count as settled.
+#[derive(Default)]
+pub struct WindowAges {
+    // None for windows that predate the run
+    first_seen: HashMap<usize, Option<Instant>>,
+    last_update: Option<Instant>,
+}
+
+impl WindowAges {
+    // Note the windows of an enumeration, forgetting the ones gone since
+    pub fn update(&mut self, windows: &[WindowInfo]) {
+        self.first_seen.retain(|hwnd, _| windows.iter().any(|window| window.hwnd.0 as usize == *hwnd));
+        for window in windows {
+            self.first_seen.entry(window.hwnd.0 as usize).or_insert(self.last_update);
+        }
+        self.last_update = Some(Instant::now());
+    }
+    
+    // How long ago a window appeared; None when it predates the run
+    pub fn age(&self, window: &WindowInfo) -> Option<Duration> {
+        self.first_seen.get(&(window.hwnd.0 as usize)).copied().flatten().map(|seen| seen.elapsed())
+    }
+}